@@ -2,10 +2,10 @@
 
 pub mod profiles;
 
-use crate::CrawlerConfig;
+use crate::{CrawlerConfig, Seed};
 use std::path::PathBuf;
-use url::Url;
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_config(
     base_url: String,
     domain: Option<String>,
@@ -23,18 +23,17 @@ pub fn build_config(
             config.max_depth = p.max_depth;
             config.max_workers = p.max_workers;
             config.rate_limit = p.rate_limit;
+            config.max_per_host = p.max_per_host;
             config.timeout = p.timeout;
+            config.retry = p.retry;
+            config.adaptive = p.adaptive;
         }
     }
     
-    config.base_url = base_url.clone();
-
     // Extract domain from base_url if not provided
-    config.allowed_domain = domain.or_else(|| {
-        Url::parse(&base_url)
-            .ok()
-            .and_then(|url| url.domain().map(|d| d.to_string()))
-    });
+    let seed = Seed::new(base_url, domain);
+    config.base_url = seed.url;
+    config.allowed_domain = seed.allowed_domain;
     
     if let Some(w) = workers {
         config.max_workers = w;
@@ -59,3 +58,50 @@ pub fn build_config(
 
     config
 }
+
+/// Parses a seed list: one URL per line, optionally followed by the domain
+/// it is restricted to. Blank lines and `#` comments are skipped.
+///
+/// Seeds without a domain are scoped to `default_domain`, or to their own
+/// domain if there is none.
+pub fn parse_seeds(text: &str, default_domain: Option<&str>) -> Vec<Seed> {
+    text.lines()
+        .filter_map(|line| {
+            // A comment starts at a field beginning with `#`, so URL fragments survive
+            let mut fields = line.split_whitespace().take_while(|field| !field.starts_with('#'));
+            let url = fields.next()?;
+            let domain = fields.next().or(default_domain).map(str::to_string);
+            Some(Seed::new(url, domain))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_seeds() {
+        let text = "\
+# Documentation sites
+https://docs.example.com/start
+https://example.org/blog  blog.example.org   # blog lives elsewhere
+
+https://sub.example.net/";
+
+        let seeds = parse_seeds(text, None);
+        assert_eq!(
+            seeds,
+            vec![
+                Seed::new("https://docs.example.com/start", Some("docs.example.com".to_string())),
+                Seed::new("https://example.org/blog", Some("blog.example.org".to_string())),
+                Seed::new("https://sub.example.net/", Some("sub.example.net".to_string())),
+            ]
+        );
+
+        let scoped = parse_seeds("https://a.example.com/#top\nhttps://b.example.com/ b.example.com", Some("example.com"));
+        assert_eq!(scoped[0].url, "https://a.example.com/#top");
+        assert_eq!(scoped[0].allowed_domain.as_deref(), Some("example.com"));
+        assert_eq!(scoped[1].allowed_domain.as_deref(), Some("b.example.com"));
+    }
+}
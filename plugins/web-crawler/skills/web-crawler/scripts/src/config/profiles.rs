@@ -1,5 +1,7 @@
 //! Predefined crawl profiles
 
+use crate::crawler::concurrency::AdaptiveConfig;
+use crate::crawler::retry::RetryPolicy;
 use crate::CrawlProfile;
 
 pub fn get_profiles() -> Vec<CrawlProfile> {
@@ -10,7 +12,20 @@ pub fn get_profiles() -> Vec<CrawlProfile> {
             max_depth: 3,
             max_workers: 50,
             rate_limit: 10.0,
+            max_per_host: 10,
             timeout: 15,
+            retry: RetryPolicy {
+                max_attempts: 2,
+                backoff_base_ms: 250,
+                ..RetryPolicy::default()
+            },
+            // Up to 10 requests per host: 50 workers only fill up across several hosts
+            adaptive: AdaptiveConfig {
+                enabled: true,
+                min_workers: 8,
+                max_workers: 200,
+                ..AdaptiveConfig::default()
+            },
         },
         CrawlProfile {
             name: "deep".to_string(),
@@ -18,7 +33,10 @@ pub fn get_profiles() -> Vec<CrawlProfile> {
             max_depth: 10,
             max_workers: 20,
             rate_limit: 3.0,
+            max_per_host: 0,
             timeout: 30,
+            retry: RetryPolicy::default(),
+            adaptive: AdaptiveConfig::default(),
         },
         CrawlProfile {
             name: "gentle".to_string(),
@@ -26,7 +44,23 @@ pub fn get_profiles() -> Vec<CrawlProfile> {
             max_depth: 5,
             max_workers: 5,
             rate_limit: 1.0,
+            max_per_host: 1,
             timeout: 45,
+            retry: RetryPolicy {
+                max_attempts: 5,
+                backoff_base_ms: 2000,
+                max_backoff_ms: 120_000,
+                ..RetryPolicy::default()
+            },
+            // One request per host: the range only matters across several hosts
+            adaptive: AdaptiveConfig {
+                enabled: true,
+                min_workers: 1,
+                max_workers: 10,
+                target_p95_ms: 1000,
+                max_error_rate: 0.02,
+                ..AdaptiveConfig::default()
+            },
         },
     ]
 }
@@ -0,0 +1,234 @@
+//! Response body limits and decoding
+//!
+//! Bodies are streamed chunk by chunk and abandoned once they pass the size
+//! limit (or once the crawl's byte budget runs out), and responses whose
+//! content type is not wanted are never read.
+//! Text is decoded with the charset found, in order, from a byte order mark,
+//! the Content-Type header, a `<meta>` tag, or else guessed from the bytes.
+
+use crate::crawler::budget::BudgetTracker;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+use futures_util::StreamExt;
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+/// Content types downloaded by default
+pub const DEFAULT_CONTENT_TYPES: &[&str] = &["text/html", "application/xhtml+xml"];
+
+/// Bytes scanned for a `<meta>` charset declaration (as in the HTML prescan)
+const META_PRESCAN_BYTES: usize = 1024;
+
+/// Decoded body along with the encoding that was used
+#[derive(Debug)]
+pub struct Decoded {
+	pub text: String,
+	pub encoding: &'static Encoding,
+}
+
+/// Why a body could not be read
+#[derive(Debug)]
+pub enum BodyError {
+	/// Body (or its Content-Length) is larger than the limit
+	TooLarge { limit: u64 },
+	/// The crawl's byte budget ran out during the transfer
+	BudgetExhausted,
+	/// Transfer failed midway
+	Request(reqwest::Error),
+}
+
+/// Media type of a Content-Type header, lowercased and without parameters
+/// (`text/html` for `text/html; charset=UTF-8`)
+pub fn media_type(content_type: &str) -> String {
+	content_type
+		.split(';')
+		.next()
+		.unwrap_or("")
+		.trim()
+		.to_ascii_lowercase()
+}
+
+/// Checks a Content-Type against an allowlist of media types, which may end
+/// in `/*` (`text/*`). An empty allowlist or unknown content type is allowed.
+pub fn is_allowed_type(content_type: Option<&str>, allowed: &[String]) -> bool {
+	let media_type = match content_type.map(media_type) {
+		Some(media_type) if !media_type.is_empty() => media_type,
+		_ => return true,
+	};
+
+	allowed.is_empty()
+		|| allowed.iter().any(|allowed| {
+			let allowed = allowed.trim().to_ascii_lowercase();
+			match allowed.strip_suffix("/*") {
+				Some(prefix) => media_type.split('/').next() == Some(prefix),
+				None => media_type == allowed,
+			}
+		})
+}
+
+/// Reads a response body, giving up as soon as it exceeds `max_bytes`
+/// (0 = no limit). A Content-Length over the limit fails before any read.
+/// Every chunk is counted against the crawl's byte `budget`.
+pub async fn read_limited(
+	response: reqwest::Response,
+	max_bytes: u64,
+	budget: &BudgetTracker,
+) -> Result<Vec<u8>, BodyError> {
+	let limit = (max_bytes > 0).then_some(max_bytes);
+
+	if let (Some(limit), Some(length)) = (limit, response.content_length()) {
+		if length > limit {
+			return Err(BodyError::TooLarge { limit });
+		}
+	}
+
+	let mut body = Vec::with_capacity(response.content_length().unwrap_or(0).min(1 << 20) as usize);
+	let mut stream = response.bytes_stream();
+
+	while let Some(chunk) = stream.next().await {
+		let chunk = chunk.map_err(BodyError::Request)?;
+		if let Some(limit) = limit {
+			if (body.len() + chunk.len()) as u64 > limit {
+				return Err(BodyError::TooLarge { limit });
+			}
+		}
+		if !budget.take_bytes(chunk.len() as u64) {
+			return Err(BodyError::BudgetExhausted);
+		}
+		body.extend_from_slice(&chunk);
+	}
+
+	Ok(body)
+}
+
+/// Decodes a body: BOM, then the Content-Type charset, then `<meta>`, then
+/// a guess from the bytes (`tld` of the page's host helps the guess)
+pub fn decode(body: &[u8], content_type: Option<&str>, tld: Option<&str>) -> Decoded {
+	if let Some((encoding, bom_length)) = Encoding::for_bom(body) {
+		let (text, _) = encoding.decode_without_bom_handling(&body[bom_length..]);
+		return Decoded { text: text.into_owned(), encoding };
+	}
+
+	let encoding = content_type
+		.and_then(header_charset)
+		.or_else(|| meta_charset(body))
+		.unwrap_or_else(|| {
+			let mut detector = EncodingDetector::new();
+			detector.feed(body, true);
+			detector.guess(tld.map(str::as_bytes), true)
+		});
+
+	let (text, _) = encoding.decode_without_bom_handling(body);
+	Decoded { text: text.into_owned(), encoding }
+}
+
+/// Encoding named by the `charset` parameter of a Content-Type header
+fn header_charset(content_type: &str) -> Option<&'static Encoding> {
+	content_type
+		.split(';')
+		.skip(1)
+		.filter_map(|param| param.split_once('='))
+		.find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+		.and_then(|(_, label)| Encoding::for_label(label.trim().trim_matches(['"', '\'']).as_bytes()))
+}
+
+/// Encoding declared by `<meta charset>` or `<meta http-equiv="Content-Type">`
+/// near the start of the document
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+	static META: OnceLock<Regex> = OnceLock::new();
+	let meta = META.get_or_init(|| {
+		Regex::new(r#"(?i)<meta\s[^>]*?charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#).unwrap()
+	});
+
+	let head = &body[..body.len().min(META_PRESCAN_BYTES)];
+	let label = meta.captures(head)?.get(1)?.as_bytes();
+
+	// A page that can be read as ASCII to find its <meta> is not UTF-16
+	match Encoding::for_label(label)? {
+		encoding if encoding == UTF_16LE || encoding == UTF_16BE => Some(UTF_8),
+		encoding if encoding == X_USER_DEFINED => Some(WINDOWS_1252),
+		encoding => Some(encoding),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn allowlist(types: &[&str]) -> Vec<String> {
+		types.iter().map(|t| t.to_string()).collect()
+	}
+
+	#[test]
+	fn test_media_type() {
+		assert_eq!(media_type("Text/HTML; charset=UTF-8"), "text/html");
+		assert_eq!(media_type("application/pdf"), "application/pdf");
+	}
+
+	#[test]
+	fn test_content_type_allowlist() {
+		let html_only = allowlist(DEFAULT_CONTENT_TYPES);
+		assert!(is_allowed_type(Some("text/html; charset=utf-8"), &html_only));
+		assert!(is_allowed_type(Some("application/xhtml+xml"), &html_only));
+		assert!(!is_allowed_type(Some("application/pdf"), &html_only));
+		assert!(!is_allowed_type(Some("text/plain"), &html_only));
+		// Missing header: let the body decide
+		assert!(is_allowed_type(None, &html_only));
+
+		let text = allowlist(&["text/*"]);
+		assert!(is_allowed_type(Some("text/plain"), &text));
+		assert!(!is_allowed_type(Some("image/png"), &text));
+
+		assert!(is_allowed_type(Some("image/png"), &[]));
+	}
+
+	fn decode_as(body: &[u8], content_type: Option<&str>) -> (String, &'static str) {
+		let decoded = decode(body, content_type, None);
+		(decoded.text, decoded.encoding.name())
+	}
+
+	#[test]
+	fn test_decode_header_charset() {
+		assert_eq!(
+			decode_as(b"caf\xe9", Some("text/html; charset=ISO-8859-1")),
+			("café".to_string(), "windows-1252")
+		);
+		assert_eq!(
+			decode_as(b"caf\xe9", Some("text/html; charset=\"windows-1252\"")),
+			("café".to_string(), "windows-1252")
+		);
+	}
+
+	#[test]
+	fn test_bom_wins_over_header() {
+		assert_eq!(
+			decode_as(b"\xef\xbb\xbfcaf\xc3\xa9", Some("text/html; charset=ISO-8859-1")),
+			("café".to_string(), "UTF-8")
+		);
+		assert_eq!(decode_as(b"\xff\xfeh\x00i\x00", None), ("hi".to_string(), "UTF-16LE"));
+	}
+
+	#[test]
+	fn test_meta_charset() {
+		let html = b"<html><head><meta charset=\"shift_jis\"><title>\x93\xfa\x96\x7b</title></head></html>";
+		let (text, encoding) = decode_as(html, Some("text/html"));
+		assert_eq!(encoding, "Shift_JIS");
+		assert!(text.contains("<title>日本</title>"));
+
+		let html = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">caf\xe9";
+		assert_eq!(decode_as(html, None).1, "windows-1252");
+
+		// Declared UTF-16 in an ASCII-readable page means UTF-8
+		assert_eq!(decode_as(b"<meta charset=utf-16>hi", None).1, "UTF-8");
+	}
+
+	#[test]
+	fn test_guess_without_declaration() {
+		assert_eq!(decode_as("café crème".as_bytes(), Some("text/html")).1, "UTF-8");
+
+		let french = b"<p>Le caf\xe9 \xe9tait tr\xe8s appr\xe9ci\xe9 \xe0 l'\xe9poque o\xf9 la soci\xe9t\xe9 fran\xe7aise</p>";
+		let (text, encoding) = decode_as(french, None);
+		assert_eq!(encoding, "windows-1252");
+		assert!(text.contains("café était très apprécié"));
+	}
+}
@@ -0,0 +1,309 @@
+//! Crawl budgets
+//!
+//! Hard limits on how much a single run may do: pages fetched, body bytes
+//! downloaded, wall-clock time, and pages per host. Pages are reserved before
+//! their first request and bytes are counted as they stream in, so the limits
+//! hold however many workers run at once. A resumed crawl carries over what
+//! the interrupted run used.
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Limits for one crawl run (0 = unlimited)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CrawlBudget {
+	/// Pages fetched, failed ones included
+	pub max_pages: usize,
+
+	/// Response body bytes downloaded
+	pub max_bytes: u64,
+
+	/// Wall-clock seconds from the start of the crawl
+	pub max_duration_secs: u64,
+
+	/// Pages fetched from any one host; a full host is skipped, the crawl goes on
+	pub max_pages_per_host: usize,
+}
+
+impl CrawlBudget {
+	/// Checks if any limit is set
+	pub fn is_limited(&self) -> bool {
+		self.max_pages > 0 || self.max_bytes > 0 || self.max_duration_secs > 0 || self.max_pages_per_host > 0
+	}
+}
+
+/// Budget that stopped a crawl
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetLimit {
+	Pages,
+	Bytes,
+	Duration,
+}
+
+impl std::fmt::Display for BudgetLimit {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let label = match self {
+			Self::Pages => "page",
+			Self::Bytes => "byte",
+			Self::Duration => "time",
+		};
+		f.write_str(label)
+	}
+}
+
+/// Why a page may not be fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+	/// The page's host has used up its pages
+	HostFull,
+	/// A crawl-wide budget is used up
+	Exhausted(BudgetLimit),
+}
+
+/// Budget used by a crawl, saved in checkpoints
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetUsage {
+	pub pages: usize,
+	pub bytes: u64,
+	/// Milliseconds spent crawling
+	pub elapsed_ms: u64,
+	/// Pages by host
+	pub hosts: HashMap<String, usize>,
+}
+
+/// Usage of a budget, shared by all workers
+pub struct BudgetTracker {
+	budget: CrawlBudget,
+	/// When this run started crawling (None until it does)
+	started: Mutex<Option<Instant>>,
+	/// Time used by earlier runs of a resumed crawl
+	earlier: AtomicU64,
+	pages: AtomicUsize,
+	bytes: AtomicU64,
+	hosts: DashMap<String, usize>,
+	exhausted: Mutex<Option<BudgetLimit>>,
+}
+
+impl BudgetTracker {
+	pub fn new(budget: CrawlBudget) -> Self {
+		Self {
+			budget,
+			started: Mutex::new(None),
+			earlier: AtomicU64::new(0),
+			pages: AtomicUsize::new(0),
+			bytes: AtomicU64::new(0),
+			hosts: DashMap::new(),
+			exhausted: Mutex::new(None),
+		}
+	}
+
+	/// Starts the wall-clock budget, as the crawl starts
+	pub fn start_clock(&self) {
+		*self.started.lock() = Some(Instant::now());
+	}
+
+	/// Reserves a page on `host`, before any request for it is sent
+	pub fn start_page(&self, host: &str) -> Result<(), Refusal> {
+		if let Some(limit) = self.exhausted() {
+			return Err(Refusal::Exhausted(limit));
+		}
+
+		// The host entry stays locked until the global reservation is made,
+		// so a refused page never counts against its host
+		let mut host_pages = self.hosts.entry(host.to_string()).or_insert(0);
+		if self.budget.max_pages_per_host > 0 && *host_pages >= self.budget.max_pages_per_host {
+			return Err(Refusal::HostFull);
+		}
+
+		let max_pages = self.budget.max_pages;
+		if self
+			.pages
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pages| {
+				(max_pages == 0 || pages < max_pages).then_some(pages + 1)
+			})
+			.is_err()
+		{
+			return Err(Refusal::Exhausted(self.exhaust(BudgetLimit::Pages)));
+		}
+
+		*host_pages += 1;
+		Ok(())
+	}
+
+	/// Carries over the usage of an earlier run, before this one starts
+	pub fn restore(&self, usage: &BudgetUsage) {
+		self.pages.store(usage.pages, Ordering::SeqCst);
+		self.bytes.store(usage.bytes, Ordering::SeqCst);
+		self.earlier.store(usage.elapsed_ms, Ordering::SeqCst);
+		self.hosts.clear();
+		for (host, pages) in &usage.hosts {
+			self.hosts.insert(host.clone(), *pages);
+		}
+	}
+
+	/// Usage so far, earlier runs included
+	pub fn usage(&self) -> BudgetUsage {
+		BudgetUsage {
+			pages: self.pages(),
+			bytes: self.bytes(),
+			elapsed_ms: self.elapsed().as_millis() as u64,
+			hosts: self.hosts.iter().map(|entry| (entry.key().clone(), *entry.value())).collect(),
+		}
+	}
+
+	/// Counts `count` downloaded bytes, refusing (and counting nothing) if
+	/// they do not fit in the byte budget
+	pub fn take_bytes(&self, count: u64) -> bool {
+		let max_bytes = self.budget.max_bytes;
+		let taken = self
+			.bytes
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |bytes| {
+				let total = bytes.saturating_add(count);
+				(max_bytes == 0 || total <= max_bytes).then_some(total)
+			})
+			.is_ok();
+
+		if !taken {
+			self.exhaust(BudgetLimit::Bytes);
+		}
+		taken
+	}
+
+	/// Time left before the wall-clock budget runs out (None if unlimited)
+	pub fn time_left(&self) -> Option<Duration> {
+		(self.budget.max_duration_secs > 0).then(|| {
+			Duration::from_secs(self.budget.max_duration_secs).saturating_sub(self.elapsed())
+		})
+	}
+
+	/// Time spent crawling, earlier runs included
+	fn elapsed(&self) -> Duration {
+		let current = self.started.lock().map_or(Duration::ZERO, |started| started.elapsed());
+		Duration::from_millis(self.earlier.load(Ordering::SeqCst)) + current
+	}
+
+	/// Marks the time budget as used up
+	pub fn expire(&self) -> BudgetLimit {
+		self.exhaust(BudgetLimit::Duration)
+	}
+
+	/// Budget that ran out first, if any
+	pub fn exhausted(&self) -> Option<BudgetLimit> {
+		*self.exhausted.lock()
+	}
+
+	/// Pages reserved so far
+	pub fn pages(&self) -> usize {
+		self.pages.load(Ordering::SeqCst)
+	}
+
+	/// Body bytes downloaded so far
+	pub fn bytes(&self) -> u64 {
+		self.bytes.load(Ordering::SeqCst)
+	}
+
+	/// Records `limit` as used up, returning the first budget that ran out
+	fn exhaust(&self, limit: BudgetLimit) -> BudgetLimit {
+		*self.exhausted.lock().get_or_insert(limit)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_page_budgets() {
+		let budget = BudgetTracker::new(CrawlBudget {
+			max_pages: 3,
+			max_pages_per_host: 2,
+			..Default::default()
+		});
+
+		assert_eq!(budget.start_page("a.com"), Ok(()));
+		assert_eq!(budget.start_page("a.com"), Ok(()));
+		assert_eq!(budget.start_page("a.com"), Err(Refusal::HostFull));
+		assert_eq!(budget.exhausted(), None);
+
+		assert_eq!(budget.start_page("b.com"), Ok(()));
+		assert_eq!(budget.start_page("c.com"), Err(Refusal::Exhausted(BudgetLimit::Pages)));
+		assert_eq!(budget.pages(), 3);
+		assert_eq!(budget.exhausted(), Some(BudgetLimit::Pages));
+	}
+
+	#[test]
+	fn test_byte_budget_is_never_exceeded() {
+		let budget = BudgetTracker::new(CrawlBudget {
+			max_bytes: 100,
+			..Default::default()
+		});
+
+		assert!(budget.take_bytes(60));
+		assert!(!budget.take_bytes(50));
+		assert_eq!(budget.bytes(), 60);
+		assert_eq!(budget.exhausted(), Some(BudgetLimit::Bytes));
+
+		// The first budget to run out is the one reported
+		budget.expire();
+		assert_eq!(budget.exhausted(), Some(BudgetLimit::Bytes));
+		assert_eq!(budget.start_page("a.com"), Err(Refusal::Exhausted(BudgetLimit::Bytes)));
+	}
+
+	#[test]
+	fn test_restored_usage_counts_against_budget() {
+		let first = BudgetTracker::new(CrawlBudget::default());
+		first.start_page("a.com").unwrap();
+		first.start_page("a.com").unwrap();
+		first.take_bytes(40);
+		let usage = first.usage();
+		assert_eq!(usage.pages, 2);
+		assert_eq!(usage.hosts["a.com"], 2);
+
+		let resumed = BudgetTracker::new(CrawlBudget {
+			max_pages: 3,
+			max_bytes: 50,
+			max_duration_secs: 60,
+			max_pages_per_host: 2,
+		});
+		resumed.restore(&BudgetUsage { elapsed_ms: 45_000, ..usage });
+		resumed.start_clock();
+
+		assert_eq!(resumed.start_page("a.com"), Err(Refusal::HostFull));
+		assert!(!resumed.take_bytes(20));
+		assert!(resumed.time_left().unwrap() <= Duration::from_secs(15));
+		assert_eq!(resumed.start_page("b.com"), Err(Refusal::Exhausted(BudgetLimit::Bytes)));
+		assert_eq!(resumed.usage().pages, 2);
+	}
+
+	#[test]
+	fn test_time_budget_runs_from_crawl_start() {
+		let budget = BudgetTracker::new(CrawlBudget {
+			max_duration_secs: 1,
+			..Default::default()
+		});
+		std::thread::sleep(Duration::from_millis(50));
+		assert_eq!(budget.time_left(), Some(Duration::from_secs(1)));
+
+		budget.start_clock();
+		std::thread::sleep(Duration::from_millis(50));
+		assert!(budget.time_left().unwrap() < Duration::from_secs(1));
+	}
+
+	#[test]
+	fn test_unlimited_budget() {
+		let budget = BudgetTracker::new(CrawlBudget::default());
+		assert!(!CrawlBudget::default().is_limited());
+		for _ in 0..1000 {
+			assert_eq!(budget.start_page("a.com"), Ok(()));
+		}
+		assert!(budget.take_bytes(u64::MAX / 2));
+		assert_eq!(budget.time_left(), None);
+	}
+}
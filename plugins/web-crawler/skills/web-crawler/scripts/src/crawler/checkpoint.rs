@@ -1,34 +1,62 @@
 //! Checkpoint system for resumable crawls
 
-use crate::{CrawlStats, PageResult};
+use crate::crawler::budget::BudgetUsage;
+use crate::{CrawlStats, CrawlerConfig, PageResult};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// URL waiting in the crawl frontier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingUrl {
+	pub url: String,
+	pub depth: usize,
+	/// Index of the seed the URL was reached from
+	#[serde(default)]
+	pub seed: usize,
+	/// Priority score it was queued with
+	#[serde(default)]
+	pub score: f64,
+	/// URL as discovered, when it differs from its canonical form in `url`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fetch_url: Option<String>,
+}
 
 /// Checkpoint data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Checkpoint {
 	/// URLs that have been visited
 	pub visited: HashSet<String>,
+	/// URLs queued or in flight when the checkpoint was taken
+	#[serde(default)]
+	pub pending: Vec<PendingUrl>,
 	/// Partial results collected so far
 	pub results: Vec<PageResult>,
 	/// Current stats
 	pub stats: CrawlStats,
+	/// Crawl budget used so far
+	#[serde(default)]
+	pub budget: BudgetUsage,
 	/// Timestamp of checkpoint
 	pub timestamp: DateTime<Utc>,
 	/// Base URL of the crawl
 	pub base_url: String,
 	/// Crawl configuration hash (to detect config changes)
 	pub config_hash: u64,
+	/// Disk store epoch sealed by this checkpoint; with a disk store,
+	/// `visited` is empty and `pending` only lists in-flight URLs
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub store_epoch: Option<u64>,
 }
 
 impl Checkpoint {
 	/// Creates a new checkpoint from current crawl state
 	pub fn new(
 		visited: HashSet<String>,
+		pending: Vec<PendingUrl>,
 		results: Vec<PageResult>,
 		stats: CrawlStats,
 		base_url: String,
@@ -36,16 +64,19 @@ impl Checkpoint {
 	) -> Self {
 		Self {
 			visited,
+			pending,
 			results,
 			stats,
+			budget: BudgetUsage::default(),
 			timestamp: Utc::now(),
 			base_url,
 			config_hash,
+			store_epoch: None,
 		}
 	}
 
 	/// Saves checkpoint to disk
-	pub fn save(&self, output_dir: &PathBuf) -> Result<()> {
+	pub fn save(&self, output_dir: &Path) -> Result<()> {
 		let checkpoint_path = Self::checkpoint_path(output_dir);
 
 		// Create directory if it doesn't exist
@@ -53,14 +84,18 @@ impl Checkpoint {
 			fs::create_dir_all(parent)?;
 		}
 
+		// Write to a temporary file first so an interrupted save never
+		// leaves a truncated checkpoint behind
 		let json = serde_json::to_string_pretty(self)?;
-		fs::write(checkpoint_path, json)?;
+		let tmp_path = checkpoint_path.with_extension("json.tmp");
+		fs::write(&tmp_path, json)?;
+		fs::rename(tmp_path, checkpoint_path)?;
 
 		Ok(())
 	}
 
 	/// Loads checkpoint from disk
-	pub fn load(output_dir: &PathBuf) -> Result<Self> {
+	pub fn load(output_dir: &Path) -> Result<Self> {
 		let checkpoint_path = Self::checkpoint_path(output_dir);
 		let json = fs::read_to_string(checkpoint_path)?;
 		let checkpoint: Checkpoint = serde_json::from_str(&json)?;
@@ -68,12 +103,12 @@ impl Checkpoint {
 	}
 
 	/// Checks if a checkpoint exists
-	pub fn exists(output_dir: &PathBuf) -> bool {
+	pub fn exists(output_dir: &Path) -> bool {
 		Self::checkpoint_path(output_dir).exists()
 	}
 
 	/// Deletes checkpoint file
-	pub fn delete(output_dir: &PathBuf) -> Result<()> {
+	pub fn delete(output_dir: &Path) -> Result<()> {
 		let checkpoint_path = Self::checkpoint_path(output_dir);
 		if checkpoint_path.exists() {
 			fs::remove_file(checkpoint_path)?;
@@ -82,7 +117,7 @@ impl Checkpoint {
 	}
 
 	/// Returns the checkpoint file path
-	fn checkpoint_path(output_dir: &PathBuf) -> PathBuf {
+	fn checkpoint_path(output_dir: &Path) -> PathBuf {
 		output_dir.join("checkpoint.json")
 	}
 
@@ -130,16 +165,17 @@ impl CheckpointManager {
 	pub fn save(
 		&mut self,
 		visited: HashSet<String>,
+		pending: Vec<PendingUrl>,
 		results: Vec<PageResult>,
 		stats: CrawlStats,
+		budget: BudgetUsage,
+		store_epoch: Option<u64>,
 	) -> Result<()> {
-		let checkpoint = Checkpoint::new(
-			visited,
-			results,
-			stats,
-			self.base_url.clone(),
-			self.config_hash,
-		);
+		let checkpoint = Checkpoint {
+			budget,
+			store_epoch,
+			..Checkpoint::new(visited, pending, results, stats, self.base_url.clone(), self.config_hash)
+		};
 
 		checkpoint.save(&self.output_dir)?;
 		self.last_save = Some(Utc::now());
@@ -174,3 +210,112 @@ impl CheckpointManager {
 		Checkpoint::delete(&self.output_dir)
 	}
 }
+
+/// Hashes the config fields that affect crawl scope
+///
+/// Uses FNV-1a rather than `DefaultHasher`, whose output may change between
+/// Rust releases and would invalidate checkpoints on disk.
+pub fn config_hash(config: &CrawlerConfig) -> u64 {
+	let scope = [
+		config.base_url.clone(),
+		config.allowed_domain.clone().unwrap_or_default(),
+		serde_json::to_string(&config.seeds).unwrap_or_default(),
+		config.max_depth.to_string(),
+		config.use_sitemap.to_string(),
+		config.max_sitemap_urls.to_string(),
+		config.respect_robots_txt.to_string(),
+		config.map_only.to_string(),
+		config.exclude_patterns.join("\u{1f}"),
+		config.include_patterns.join("\u{1f}"),
+		// Normalization decides which URLs count as visited
+		serde_json::to_string(&config.url_normalization).unwrap_or_default(),
+		// Skipping links on duplicates changes which URLs get queued
+		serde_json::to_string(&config.dedup).unwrap_or_default(),
+		// Disk store checkpoints only make sense with the store
+		config.store.enabled.to_string(),
+	];
+
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for field in scope {
+		for byte in field.bytes().chain(std::iter::once(0u8)) {
+			hash ^= byte as u64;
+			hash = hash.wrapping_mul(0x100000001b3);
+		}
+	}
+	hash
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_config_hash_tracks_scope_fields() {
+		let config = CrawlerConfig {
+			base_url: "https://example.com".to_string(),
+			..Default::default()
+		};
+		let hash = config_hash(&config);
+
+		// Same scope, different speed settings
+		let faster = CrawlerConfig {
+			max_workers: 50,
+			rate_limit: 10.0,
+			timeout: 5,
+			..config.clone()
+		};
+		assert_eq!(config_hash(&faster), hash);
+
+		let deeper = CrawlerConfig {
+			max_depth: config.max_depth + 1,
+			..config.clone()
+		};
+		assert_ne!(config_hash(&deeper), hash);
+
+		let filtered = CrawlerConfig {
+			exclude_patterns: vec![r"\.pdf$".to_string()],
+			..config
+		};
+		assert_ne!(config_hash(&filtered), hash);
+	}
+
+	#[test]
+	fn test_save_and_load_with_pending() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut manager = CheckpointManager::new(dir.path().to_path_buf(), "https://example.com".to_string(), 42, 30);
+
+		assert!(manager.should_save());
+		manager
+			.save(
+				HashSet::from(["https://example.com".to_string()]),
+				vec![PendingUrl { url: "https://example.com/next".to_string(), depth: 1, seed: 0, score: 0.0, fetch_url: None }],
+				vec![],
+				CrawlStats::new(),
+				BudgetUsage { pages: 1, bytes: 512, ..Default::default() },
+				None,
+			)
+			.unwrap();
+		assert!(!manager.should_save());
+
+		let checkpoint = manager.try_load().unwrap();
+		assert!(checkpoint.visited.contains("https://example.com"));
+		assert_eq!(checkpoint.pending[0].url, "https://example.com/next");
+		assert_eq!(checkpoint.pending[0].depth, 1);
+		assert_eq!(checkpoint.budget.bytes, 512);
+
+		manager.clear().unwrap();
+		assert!(manager.try_load().is_none());
+	}
+
+	#[test]
+	fn test_mismatched_config_is_ignored() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut manager = CheckpointManager::new(dir.path().to_path_buf(), "https://example.com".to_string(), 1, 30);
+		manager
+			.save(HashSet::new(), vec![], vec![], CrawlStats::new(), BudgetUsage::default(), None)
+			.unwrap();
+
+		let other = CheckpointManager::new(dir.path().to_path_buf(), "https://example.com".to_string(), 2, 30);
+		assert!(other.try_load().is_none());
+	}
+}
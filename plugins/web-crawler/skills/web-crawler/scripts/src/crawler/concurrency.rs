@@ -0,0 +1,391 @@
+//! Adaptive concurrency
+//!
+//! An AIMD controller for the number of requests in flight across all
+//! hosts. Latencies and overload signals (429, 503 and timeouts) are
+//! gathered in windows of `window` requests. A healthy window in which
+//! every allowed slot was busy adds one slot; too many overload signals
+//! halve the requests actually in flight, as TCP does with its congestion
+//! window. A p95 latency over target holds concurrency where it is.
+//!
+//! Per-host caps bound how many requests can run at all: with one host
+//! capped at 10, a limit of 50 is never busy. Slots are counted busy
+//! against that capacity, and growth stops at it.
+//!
+//! Concurrency is halved at most once per window, and requests started
+//! before the last back-off are left out: they were sent at the old limit,
+//! so their 429s say nothing about the new one.
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Decisions kept in the crawl stats (the oldest go first)
+const MAX_DECISIONS: usize = 100;
+
+/// Bounds and targets of adaptive concurrency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AdaptiveConfig {
+	/// Adjust concurrency during the crawl, starting from `max_workers`
+	pub enabled: bool,
+
+	/// Lowest concurrency a back-off goes down to
+	pub min_workers: usize,
+
+	/// Highest concurrency growth goes up to
+	pub max_workers: usize,
+
+	/// p95 latency, in milliseconds, above which concurrency stops growing
+	pub target_p95_ms: u64,
+
+	/// Share of requests answered 429/503 or timed out that triggers a back-off
+	pub max_error_rate: f64,
+
+	/// Requests per decision
+	pub window: usize,
+}
+
+impl Default for AdaptiveConfig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			min_workers: 1,
+			max_workers: 100,
+			target_p95_ms: 2000,
+			max_error_rate: 0.05,
+			window: 20,
+		}
+	}
+}
+
+impl AdaptiveConfig {
+	fn bounds(&self) -> (usize, usize) {
+		let min = self.min_workers.max(1);
+		(min, self.max_workers.max(min))
+	}
+}
+
+/// Why concurrency changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdjustReason {
+	/// Latency and error rate were healthy with every slot busy
+	Healthy,
+	/// Too many 429/503 responses or timeouts
+	Overloaded,
+}
+
+impl std::fmt::Display for AdjustReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let label = match self {
+			Self::Healthy => "healthy",
+			Self::Overloaded => "overloaded",
+		};
+		f.write_str(label)
+	}
+}
+
+/// One change of the concurrency limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyDecision {
+	pub at: DateTime<Utc>,
+	pub from: usize,
+	pub to: usize,
+	pub reason: AdjustReason,
+	/// p95 latency of the window, in milliseconds
+	pub p95_ms: u64,
+	/// Share of the window's requests that signalled overload
+	pub error_rate: f64,
+}
+
+/// What adaptive concurrency did during a crawl
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyStats {
+	pub initial: usize,
+	/// Limit when the stats were taken
+	pub current: usize,
+	pub lowest: usize,
+	pub highest: usize,
+	pub increases: usize,
+	pub decreases: usize,
+	/// Most recent decisions
+	pub decisions: Vec<ConcurrencyDecision>,
+}
+
+impl std::fmt::Display for ConcurrencyStats {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} -> {} (lowest {}, highest {}; {} increases, {} back-offs)",
+			self.initial, self.current, self.lowest, self.highest, self.increases, self.decreases
+		)
+	}
+}
+
+/// Concurrency limit driven by response latency and overload signals
+pub struct ConcurrencyController {
+	config: AdaptiveConfig,
+	state: Mutex<ControllerState>,
+}
+
+struct ControllerState {
+	limit: usize,
+	latencies: Vec<Duration>,
+	overloaded: usize,
+	/// Every allowed slot was in use at some point of the window
+	saturated: bool,
+	/// Concurrency was halved during the window, so a burst of overload
+	/// signals waits for the window to fill before backing off again
+	backed_off: bool,
+	last_decrease: Option<Instant>,
+	stats: ConcurrencyStats,
+}
+
+impl ConcurrencyController {
+	/// Starts at `start`, clamped to the configured bounds
+	pub fn new(config: AdaptiveConfig, start: usize) -> Self {
+		let (min, max) = config.bounds();
+		let limit = start.clamp(min, max);
+		Self {
+			state: Mutex::new(ControllerState {
+				limit,
+				latencies: Vec::with_capacity(config.window),
+				overloaded: 0,
+				saturated: false,
+				backed_off: false,
+				last_decrease: None,
+				stats: ConcurrencyStats {
+					initial: limit,
+					current: limit,
+					lowest: limit,
+					highest: limit,
+					..Default::default()
+				},
+			}),
+			config,
+		}
+	}
+
+	/// Current limit on requests in flight
+	pub fn limit(&self) -> usize {
+		self.state.lock().limit
+	}
+
+	/// Upper bound of the limit
+	pub fn max_workers(&self) -> usize {
+		self.config.bounds().1
+	}
+
+	/// Records a request sent at `started` that took `latency`; `in_flight`
+	/// counts the requests running when it finished, itself included, and
+	/// `capacity` the most the hosts with jobs could run (`None` if
+	/// uncapped). Returns the new limit if it changed.
+	pub fn record(
+		&self,
+		started: Instant,
+		latency: Duration,
+		overloaded: bool,
+		in_flight: usize,
+		capacity: Option<usize>,
+	) -> Option<usize> {
+		let mut state = self.state.lock();
+		if state.last_decrease.is_some_and(|decreased| started < decreased) {
+			return None;
+		}
+
+		state.latencies.push(latency);
+		if overloaded {
+			state.overloaded += 1;
+		}
+		if in_flight >= capacity.map_or(state.limit, |capacity| state.limit.min(capacity)) {
+			state.saturated = true;
+		}
+
+		// A burst of overload signals backs off before the window is full,
+		// unless the window already did
+		let window = self.config.window.max(1);
+		let too_many_errors = state.overloaded as f64 > self.config.max_error_rate * window as f64;
+		if state.latencies.len() < window && (!too_many_errors || state.backed_off) {
+			return None;
+		}
+
+		let error_rate = state.overloaded as f64 / state.latencies.len() as f64;
+		let p95 = percentile(&mut state.latencies, 0.95);
+		let healthy = p95 <= Duration::from_millis(self.config.target_p95_ms) && state.saturated;
+		let window_full = state.latencies.len() >= window;
+		state.latencies.clear();
+		state.overloaded = 0;
+		state.saturated = false;
+		if window_full {
+			state.backed_off = false;
+		}
+
+		let (min, max) = self.config.bounds();
+		let ceiling = capacity.map_or(max, |capacity| capacity.min(max));
+		let from = state.limit;
+		let (to, reason) = if too_many_errors {
+			((in_flight.min(from) / 2).max(min), AdjustReason::Overloaded)
+		} else if healthy && from < ceiling {
+			(from + 1, AdjustReason::Healthy)
+		} else {
+			return None;
+		};
+		if to == from {
+			return None;
+		}
+
+		state.limit = to;
+		if to < from {
+			state.backed_off = true;
+			state.last_decrease = Some(Instant::now());
+		}
+		let stats = &mut state.stats;
+		stats.current = to;
+		stats.lowest = stats.lowest.min(to);
+		stats.highest = stats.highest.max(to);
+		if to > from {
+			stats.increases += 1;
+		} else {
+			stats.decreases += 1;
+		}
+		if stats.decisions.len() == MAX_DECISIONS {
+			stats.decisions.remove(0);
+		}
+		stats.decisions.push(ConcurrencyDecision {
+			at: Utc::now(),
+			from,
+			to,
+			reason,
+			p95_ms: p95.as_millis() as u64,
+			error_rate,
+		});
+
+		Some(to)
+	}
+
+	pub fn stats(&self) -> ConcurrencyStats {
+		self.state.lock().stats.clone()
+	}
+}
+
+/// Nearest-rank percentile (`fraction` of 1.0 is the maximum)
+fn percentile(samples: &mut [Duration], fraction: f64) -> Duration {
+	if samples.is_empty() {
+		return Duration::ZERO;
+	}
+	samples.sort_unstable();
+	let rank = ((samples.len() as f64 * fraction).ceil() as usize).clamp(1, samples.len());
+	samples[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn controller(start: usize) -> ConcurrencyController {
+		ConcurrencyController::new(
+			AdaptiveConfig {
+				enabled: true,
+				min_workers: 2,
+				max_workers: 8,
+				max_error_rate: 0.1,
+				window: 10,
+				..Default::default()
+			},
+			start,
+		)
+	}
+
+	const FAST: Duration = Duration::from_millis(50);
+
+	#[test]
+	fn test_grows_while_healthy_and_busy() {
+		let controller = controller(4);
+		for _ in 0..9 {
+			assert_eq!(controller.record(Instant::now(), FAST, false, 4, None), None);
+		}
+		assert_eq!(controller.record(Instant::now(), FAST, false, 4, None), Some(5));
+
+		// Slots left idle: no reason to grow
+		for _ in 0..10 {
+			assert_eq!(controller.record(Instant::now(), FAST, false, 1, None), None);
+		}
+
+		// Slow responses hold the limit
+		for _ in 0..10 {
+			assert_eq!(controller.record(Instant::now(), Duration::from_secs(5), false, 5, None), None);
+		}
+
+		// Capped at the maximum
+		for _ in 0..100 {
+			controller.record(Instant::now(), FAST, false, 8, None);
+		}
+		assert_eq!(controller.limit(), 8);
+		assert_eq!(controller.stats().increases, 4);
+	}
+
+	#[test]
+	fn test_overload_halves_requests_in_flight() {
+		let controller = controller(8);
+		let sent = Instant::now() - Duration::from_secs(1);
+		assert_eq!(controller.record(sent, FAST, true, 6, None), None);
+		// Second 429 in a window of 10 passes the 10% threshold right away
+		assert_eq!(controller.record(sent, FAST, true, 6, None), Some(3));
+
+		// Requests sent at the old limit are left out
+		for _ in 0..20 {
+			assert_eq!(controller.record(sent, FAST, true, 6, None), None);
+		}
+
+		// Once backed off, the window has to fill before the next back-off
+		for _ in 0..9 {
+			assert_eq!(controller.record(Instant::now(), FAST, true, 3, None), None);
+		}
+		assert_eq!(controller.record(Instant::now(), FAST, true, 3, None), Some(2));
+
+		let stats = controller.stats();
+		assert_eq!((stats.initial, stats.current, stats.lowest, stats.decreases), (8, 2, 2, 2));
+		assert_eq!(stats.decisions[0].reason, AdjustReason::Overloaded);
+		assert_eq!((stats.decisions[0].from, stats.decisions[0].to), (8, 3));
+	}
+
+	#[test]
+	fn test_per_host_capacity_bounds_growth() {
+		// One host capped at 5: a limit of 8 is busy with 5 running
+		let controller = controller(8);
+		for _ in 0..10 {
+			assert_eq!(controller.record(Instant::now(), FAST, false, 5, Some(5)), None);
+		}
+		assert_eq!(controller.limit(), 8);
+
+		// Backed off below the cap, it grows back to the cap and no further
+		let sent = Instant::now();
+		controller.record(sent, FAST, true, 5, Some(5));
+		assert_eq!(controller.record(sent, FAST, true, 5, Some(5)), Some(2));
+		for _ in 0..100 {
+			let in_flight = controller.limit();
+			controller.record(Instant::now(), FAST, false, in_flight, Some(5));
+		}
+		assert_eq!(controller.limit(), 5);
+		assert_eq!(controller.stats().highest, 8);
+
+		// A second host with jobs raises the capacity
+		for _ in 0..10 {
+			controller.record(Instant::now(), FAST, false, 5, Some(10));
+		}
+		assert_eq!(controller.limit(), 6);
+	}
+
+	#[test]
+	fn test_start_is_clamped_and_percentile() {
+		assert_eq!(controller(50).limit(), 8);
+		assert_eq!(controller(0).limit(), 2);
+
+		let mut samples: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+		assert_eq!(percentile(&mut samples, 0.95), Duration::from_millis(19));
+		assert_eq!(percentile(&mut [], 0.95), Duration::ZERO);
+	}
+}
@@ -0,0 +1,414 @@
+//! Exact and near-duplicate content detection
+//!
+//! Each page's visible text gets two fingerprints: a hash of the normalized
+//! text for exact matches, and a 64-bit SimHash of its word shingles for near
+//! matches. The first page seen with some content is the canonical one; later
+//! pages within `max_distance` bits of it point back to it.
+
+use crate::PageResult;
+use parking_lot::Mutex;
+use scraper::{Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Words per shingle fed to SimHash
+const SHINGLE_SIZE: usize = 3;
+
+/// Pages with fewer words are only compared exactly: their SimHash is too
+/// coarse to tell short pages apart
+const MIN_NEAR_WORDS: usize = 10;
+
+/// The SimHash is split into this many bands for lookup. Two hashes within
+/// `BANDS - 1` bits of each other share at least one band exactly.
+const BANDS: usize = 4;
+
+/// Elements whose text is not part of the page content
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// Configuration for duplicate detection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DedupConfig {
+	/// Detect duplicate pages
+	pub enabled: bool,
+
+	/// Largest SimHash distance (in bits, at most 3) counted as a near
+	/// duplicate; 0 only matches identical text
+	pub max_distance: u32,
+
+	/// Queue links found on duplicate pages
+	pub expand_duplicates: bool,
+}
+
+impl Default for DedupConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			max_distance: 3,
+			expand_duplicates: true,
+		}
+	}
+}
+
+/// How closely a page matches its canonical page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKind {
+	/// Same text after whitespace and case normalization
+	Exact,
+	/// SimHash within `max_distance` bits
+	Near,
+}
+
+/// Match found for a duplicate page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+	/// URL of the page first seen with this content
+	pub canonical: String,
+	pub kind: DuplicateKind,
+	/// Differing SimHash bits (0 for exact duplicates)
+	pub distance: u32,
+}
+
+/// Content fingerprints of one page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentFingerprint {
+	/// Hash of the normalized text
+	pub hash: u64,
+	/// SimHash of the word shingles
+	pub simhash: u64,
+	/// Number of words in the text
+	pub words: usize,
+}
+
+impl ContentFingerprint {
+	/// Fingerprints text, ignoring case, punctuation and whitespace
+	pub fn from_text(text: &str) -> Self {
+		let words: Vec<String> = text
+			.split(|c: char| !c.is_alphanumeric())
+			.filter(|word| !word.is_empty())
+			.map(str::to_lowercase)
+			.collect();
+
+		Self {
+			hash: mix(fnv1a(words.join(" ").as_bytes())),
+			simhash: simhash(&words),
+			words: words.len(),
+		}
+	}
+
+	/// Rebuilds a fingerprint from the hex values stored on a `PageResult`
+	pub fn from_result(page: &PageResult) -> Option<Self> {
+		Some(Self {
+			hash: u64::from_str_radix(page.content_hash.as_deref()?, 16).ok()?,
+			simhash: u64::from_str_radix(page.simhash.as_deref()?, 16).ok()?,
+			words: page.word_count.unwrap_or(MIN_NEAR_WORDS),
+		})
+	}
+
+	pub fn hash_hex(&self) -> String {
+		format!("{:016x}", self.hash)
+	}
+
+	pub fn simhash_hex(&self) -> String {
+		format!("{:016x}", self.simhash)
+	}
+}
+
+/// Index of the content seen so far, shared by all workers
+pub struct Deduplicator {
+	config: DedupConfig,
+	state: Mutex<DedupState>,
+}
+
+#[derive(Default)]
+struct DedupState {
+	/// Exact hash to canonical URL
+	exact: HashMap<u64, String>,
+	/// Canonical pages with their SimHash
+	pages: Vec<(u64, String)>,
+	/// Band value to indexes into `pages`, one map per band
+	bands: [HashMap<u16, Vec<usize>>; BANDS],
+}
+
+impl Deduplicator {
+	pub fn new(config: DedupConfig) -> Self {
+		Self {
+			config,
+			state: Mutex::new(DedupState::default()),
+		}
+	}
+
+	/// Returns the page `url` duplicates, or registers it as canonical
+	pub fn check(&self, url: &str, fingerprint: &ContentFingerprint) -> Option<Duplicate> {
+		let mut state = self.state.lock();
+
+		if let Some(canonical) = state.exact.get(&fingerprint.hash) {
+			return Some(Duplicate {
+				canonical: canonical.clone(),
+				kind: DuplicateKind::Exact,
+				distance: 0,
+			});
+		}
+
+		if let Some(duplicate) = self.find_near(&state, fingerprint) {
+			return Some(duplicate);
+		}
+
+		Self::insert(&mut state, url, fingerprint);
+		None
+	}
+
+	/// Registers a canonical page without checking it (used on resume)
+	pub fn register(&self, url: &str, fingerprint: &ContentFingerprint) {
+		let mut state = self.state.lock();
+		if !state.exact.contains_key(&fingerprint.hash) {
+			Self::insert(&mut state, url, fingerprint);
+		}
+	}
+
+	fn find_near(&self, state: &DedupState, fingerprint: &ContentFingerprint) -> Option<Duplicate> {
+		let max_distance = self.config.max_distance.min(BANDS as u32 - 1);
+		if max_distance == 0 || fingerprint.words < MIN_NEAR_WORDS {
+			return None;
+		}
+
+		let mut best: Option<(u32, usize)> = None;
+		for (band, index) in state.bands.iter().enumerate() {
+			let candidates = match index.get(&band_value(fingerprint.simhash, band)) {
+				Some(candidates) => candidates,
+				None => continue,
+			};
+
+			for &candidate in candidates {
+				let distance = (state.pages[candidate].0 ^ fingerprint.simhash).count_ones();
+				if distance <= max_distance && best.is_none_or(|(d, _)| distance < d) {
+					best = Some((distance, candidate));
+				}
+			}
+		}
+
+		best.map(|(distance, candidate)| Duplicate {
+			canonical: state.pages[candidate].1.clone(),
+			kind: DuplicateKind::Near,
+			distance,
+		})
+	}
+
+	fn insert(state: &mut DedupState, url: &str, fingerprint: &ContentFingerprint) {
+		state.exact.insert(fingerprint.hash, url.to_string());
+
+		if fingerprint.words >= MIN_NEAR_WORDS {
+			let index = state.pages.len();
+			state.pages.push((fingerprint.simhash, url.to_string()));
+			for band in 0..BANDS {
+				let value = band_value(fingerprint.simhash, band);
+				state.bands[band].entry(value).or_default().push(index);
+			}
+		}
+	}
+}
+
+/// Visible text of an HTML document's body, without scripts and styles
+pub fn visible_text(html: &str) -> String {
+	let document = Html::parse_document(html);
+	let body_selector = Selector::parse("body").unwrap();
+	let root = document
+		.select(&body_selector)
+		.next()
+		.unwrap_or_else(|| document.root_element());
+
+	let mut text = String::new();
+	for node in root.descendants() {
+		let content = match node.value() {
+			Node::Text(content) => content,
+			_ => continue,
+		};
+
+		let hidden = node.ancestors().any(|ancestor| match ancestor.value() {
+			Node::Element(element) => SKIPPED_ELEMENTS.contains(&element.name()),
+			_ => false,
+		});
+		if !hidden {
+			text.push_str(content);
+			text.push(' ');
+		}
+	}
+	text
+}
+
+/// Drops duplicate pages, listing their URLs on the canonical page instead
+pub fn collapse_duplicates(results: &[PageResult]) -> Vec<PageResult> {
+	let mut collapsed: Vec<PageResult> = Vec::with_capacity(results.len());
+	let mut positions: HashMap<&str, usize> = HashMap::new();
+
+	for page in results.iter().filter(|page| page.duplicate_of.is_none()) {
+		positions.insert(page.url.as_str(), collapsed.len());
+		collapsed.push(page.clone());
+	}
+
+	for page in results {
+		if let Some(canonical) = &page.duplicate_of {
+			match positions.get(canonical.as_str()) {
+				Some(&position) => collapsed[position].duplicates.push(page.url.clone()),
+				// Canonical page missing (e.g. filtered out): keep the duplicate
+				None => collapsed.push(page.clone()),
+			}
+		}
+	}
+
+	collapsed
+}
+
+/// 64-bit SimHash over word shingles
+fn simhash(words: &[String]) -> u64 {
+	let mut weights = [0i32; 64];
+
+	let shingles: Vec<String> = if words.len() < SHINGLE_SIZE {
+		words.to_vec()
+	} else {
+		words.windows(SHINGLE_SIZE).map(|window| window.join(" ")).collect()
+	};
+
+	for shingle in &shingles {
+		let hash = mix(fnv1a(shingle.as_bytes()));
+		for (bit, weight) in weights.iter_mut().enumerate() {
+			if hash & (1 << bit) != 0 {
+				*weight += 1;
+			} else {
+				*weight -= 1;
+			}
+		}
+	}
+
+	weights
+		.iter()
+		.enumerate()
+		.filter(|(_, weight)| **weight > 0)
+		.fold(0, |simhash, (bit, _)| simhash | (1 << bit))
+}
+
+/// 16-bit slice of a SimHash used as a lookup key
+fn band_value(simhash: u64, band: usize) -> u16 {
+	(simhash >> (band * 16)) as u16
+}
+
+/// FNV-1a, stable across runs and platforms
+fn fnv1a(bytes: &[u8]) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for byte in bytes {
+		hash ^= *byte as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	hash
+}
+
+/// Finalizer spreading FNV output across all bits (from SplitMix64)
+fn mix(mut hash: u64) -> u64 {
+	hash ^= hash >> 30;
+	hash = hash.wrapping_mul(0xbf58476d1ce4e5b9);
+	hash ^= hash >> 27;
+	hash = hash.wrapping_mul(0x94d049bb133111eb);
+	hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ARTICLE: &str = "Rust is a multi-paradigm systems programming language focused on \
+		safety, especially safe concurrency. It is syntactically similar to C++ but \
+		guarantees memory safety by using a borrow checker to validate references. \
+		Rust achieves memory safety without garbage collection, and reference counting \
+		is optional. The language grew out of a personal project begun in 2006 and was \
+		later sponsored by Mozilla, which officially announced it in 2010.";
+
+	#[test]
+	fn test_exact_duplicates_ignore_formatting() {
+		let dedup = Deduplicator::new(DedupConfig::default());
+		let original = ContentFingerprint::from_text("Hello,   World! Welcome to the site.");
+		let reformatted = ContentFingerprint::from_text("hello world\nwelcome to the SITE");
+
+		assert_eq!(dedup.check("https://example.com/a", &original), None);
+		assert_eq!(
+			dedup.check("https://example.com/a?print=1", &reformatted),
+			Some(Duplicate {
+				canonical: "https://example.com/a".to_string(),
+				kind: DuplicateKind::Exact,
+				distance: 0,
+			})
+		);
+	}
+
+	#[test]
+	fn test_near_duplicates() {
+		let dedup = Deduplicator::new(DedupConfig::default());
+		let near = format!("{} Session 8f3a2c.", ARTICLE);
+
+		assert_eq!(dedup.check("https://example.com/rust", &ContentFingerprint::from_text(ARTICLE)), None);
+
+		let duplicate = dedup
+			.check("https://example.com/rust?sid=8f3a2c", &ContentFingerprint::from_text(&near))
+			.unwrap();
+		assert_eq!(duplicate.canonical, "https://example.com/rust");
+		assert_eq!(duplicate.kind, DuplicateKind::Near);
+		assert!(duplicate.distance <= 3);
+	}
+
+	#[test]
+	fn test_different_pages_are_kept() {
+		let dedup = Deduplicator::new(DedupConfig::default());
+		let other = "Python is a high-level, general-purpose programming language. Its design \
+			philosophy emphasizes code readability with the use of significant indentation. \
+			Python is dynamically typed and garbage-collected.";
+
+		assert_eq!(dedup.check("https://example.com/rust", &ContentFingerprint::from_text(ARTICLE)), None);
+		assert_eq!(dedup.check("https://example.com/python", &ContentFingerprint::from_text(other)), None);
+	}
+
+	#[test]
+	fn test_distance_zero_disables_near_matching() {
+		let dedup = Deduplicator::new(DedupConfig {
+			max_distance: 0,
+			..DedupConfig::default()
+		});
+		let near = format!("{} Session 8f3a2c.", ARTICLE);
+
+		dedup.check("https://example.com/rust", &ContentFingerprint::from_text(ARTICLE));
+		assert_eq!(dedup.check("https://example.com/rust?sid=1", &ContentFingerprint::from_text(&near)), None);
+	}
+
+	#[test]
+	fn test_visible_text_skips_scripts() {
+		let html = "<html><head><title>T</title><style>p{}</style></head>\
+			<body><p>Hello <b>world</b></p><script>var x = 1;</script></body></html>";
+
+		let text = visible_text(html);
+		assert!(text.contains("Hello"));
+		assert!(text.contains("world"));
+		assert!(!text.contains("var x"));
+		assert!(!text.contains("T "));
+	}
+
+	#[test]
+	fn test_collapse_duplicates() {
+		let page = |url: &str, duplicate_of: Option<&str>| PageResult {
+			url: url.to_string(),
+			duplicate_of: duplicate_of.map(str::to_string),
+			..Default::default()
+		};
+		let results = vec![
+			page("https://example.com/a", None),
+			page("https://example.com/a?print=1", Some("https://example.com/a")),
+			page("https://example.com/b", None),
+			page("https://example.com/c?sid=1", Some("https://example.com/missing")),
+		];
+
+		let collapsed = collapse_duplicates(&results);
+
+		assert_eq!(collapsed.len(), 3);
+		assert_eq!(collapsed[0].duplicates, vec!["https://example.com/a?print=1".to_string()]);
+		assert!(collapsed[1].duplicates.is_empty());
+		assert_eq!(collapsed[2].url, "https://example.com/c?sid=1");
+	}
+}
@@ -1,167 +1,278 @@
 //! Crawling engine with concurrent worker pool
 
-use crate::crawler::robots::RobotsChecker;
+use crate::crawler::body::{self, BodyError};
+use crate::crawler::budget::{BudgetLimit, BudgetTracker, Refusal};
+use crate::crawler::checkpoint::{self, CheckpointManager, PendingUrl};
+use crate::crawler::concurrency::ConcurrencyController;
+use crate::crawler::dedup::{self, ContentFingerprint, Deduplicator};
+use crate::crawler::frontier::{self, CrawlJob, CrawlStrategy, HostQueues};
+use crate::crawler::http_cache::{CacheEntry, HttpCache};
+use crate::crawler::priority::{PriorityScorer, ScoreInput};
+use crate::crawler::robots::{self, RobotsChecker};
 use crate::crawler::rate_limiter::RateLimiter;
+use crate::crawler::retry;
+use crate::crawler::store::{DiskStore, PendingSet, ReferrerIndex, Upsert, VisitedSet};
 use crate::utils::filters::UrlFilter;
-use crate::{CrawlerConfig, PageResult, CrawlStats, CrawlResults};
+use crate::utils::url::UrlNormalizer;
+use crate::{
+    CrawlerConfig, PageResult, CrawlStats, CrawlResults, DiscoverySource, ErrorKind, LinkElement,
+    MappedUrl, PageChange, RedirectHop, RedirectIssue, RobotsDirectives, Seed,
+};
 use crate::parser::html::HtmlParser;
-use crate::parser::sitemap::SitemapParser;
+use crate::parser::sitemap::{SitemapEntry, SitemapParser};
+use crate::services::content_filter::FilterStats;
+use crate::services::ServiceContainer;
 use anyhow::Result;
 use chrono::Utc;
-use dashmap::DashMap;
-use parking_lot::Mutex;
+use dashmap::{DashMap, DashSet};
+use parking_lot::{Mutex, RwLock};
+use reqwest::header::HeaderMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
 use url::Url;
 
-#[derive(Clone)]
-struct CrawlJob {
-    url: String,
-    depth: usize,
-}
+const USER_AGENT: &str = "rcrawler/0.1.0";
+
+/// Inbound pages remembered per URL in the referrer index
+const MAX_REFERRERS: usize = 50;
+
+/// Redirect chains with at least this many hops are flagged in reports
+const LONG_REDIRECT_CHAIN: usize = 3;
+
+/// How long requests already running may take to finish once the time
+/// budget runs out
+const BUDGET_GRACE: Duration = Duration::from_secs(2);
 
 pub struct CrawlEngine {
     config: CrawlerConfig,
+    seeds: Arc<Vec<Seed>>,
     client: reqwest::Client,
     parser: HtmlParser,
     robots_checker: Option<RobotsChecker>,
     url_filter: UrlFilter,
+    normalizer: UrlNormalizer,
     rate_limiter: RateLimiter,
-    visited: Arc<DashMap<String, ()>>,
+    frontier: Arc<HostQueues>,
+    scorer: Option<Arc<PriorityScorer>>,
+    concurrency: Option<Arc<ConcurrencyController>>,
+    services: ServiceContainer,
+    store: Option<Arc<DiskStore>>,
+    visited: Arc<VisitedSet>,
+    url_map: Arc<DashMap<String, MappedUrl>>,
+    referrers: Arc<ReferrerIndex>,
+    dedup: Arc<Deduplicator>,
+    http_cache: Option<Arc<HttpCache>>,
+    budget: Arc<BudgetTracker>,
+    pending: Arc<PendingSet>,
+    /// URLs refused because their host used up its page budget, so links
+    /// to them are not queued again
+    host_refused: Arc<DashSet<String>>,
+    in_flight: Arc<DashMap<String, PendingUrl>>,
+    /// Held shared while a URL moves between pending, in flight and done,
+    /// and exclusively while a checkpoint reads those, so it sees each URL
+    /// in exactly one of them
+    checkpoint_lock: Arc<RwLock<()>>,
+    checkpoints: Arc<Mutex<CheckpointManager>>,
     results: Arc<Mutex<Vec<PageResult>>>,
     stats: Arc<Mutex<CrawlStats>>,
+    /// Jobs pushed onto the frontier and not yet finished: queued or being
+    /// processed. The crawl is done when it drops to zero.
     active_jobs: Arc<std::sync::atomic::AtomicUsize>,
     shutdown: Arc<std::sync::atomic::AtomicBool>,
+    interrupted: Arc<std::sync::atomic::AtomicBool>,
 }
 
 impl CrawlEngine {
-    pub fn new(config: CrawlerConfig) -> Result<Self> {
+    pub fn new(config: CrawlerConfig, services: ServiceContainer) -> Result<Self> {
         let client = reqwest::Client::builder()
             .timeout(Duration::from_secs(config.timeout))
-            .user_agent("rcrawler/0.1.0")
+            .user_agent(USER_AGENT)
             .gzip(true)
+            // Redirects are followed by hand so each hop can be recorded and scoped
+            .redirect(reqwest::redirect::Policy::none())
             .build()?;
 
         // Create robots checker if enabled
         let robots_checker = if config.respect_robots_txt {
-            Some(RobotsChecker::new(config.timeout, "rcrawler/0.1.0".to_string()))
+            Some(RobotsChecker::new(config.timeout, USER_AGENT.to_string()))
         } else {
             None
         };
 
-        // Create URL filter
+        // Create URL normalizer and filter (patterns match canonical URLs)
+        let normalizer = UrlNormalizer::new(config.url_normalization.clone());
         let url_filter = UrlFilter::new(&config.exclude_patterns, &config.include_patterns);
 
-        // Create rate limiter
+        // Create per-host rate limiter and politeness queues
         let rate_limiter = RateLimiter::new(config.rate_limit);
+        let mut frontier = HostQueues::new(config.strategy, rate_limiter.clone(), config.max_per_host);
+        let scorer = (config.strategy == CrawlStrategy::Priority)
+            .then(|| Arc::new(PriorityScorer::new(config.priority.clone())));
+
+        // Adaptive concurrency caps running jobs below the worker count,
+        // starting at no more than the seeds' hosts can run
+        let seeds = config.seeds();
+        let concurrency = config.adaptive.enabled.then(|| {
+            let start = match config.max_per_host {
+                0 => config.max_workers,
+                per_host => {
+                    let hosts: HashSet<String> = seeds.iter().map(|seed| frontier::host_key(&seed.url)).collect();
+                    config.max_workers.min(per_host * hosts.len())
+                }
+            };
+            let controller = ConcurrencyController::new(config.adaptive.clone(), start);
+            frontier.set_max_active(controller.limit());
+            Arc::new(controller)
+        });
+
+        // Create content index for duplicate detection
+        let dedup = Deduplicator::new(config.dedup.clone());
+
+        let active_jobs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let shutdown = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // Open the disk store; the frontier spills its overflow there
+        let store = if config.store.enabled {
+            let store = DiskStore::open(&config.output_dir, &config.store)?;
+            // Spilled jobs that cannot be read back never finish on their own
+            let on_lost = {
+                let (active_jobs, shutdown) = (Arc::clone(&active_jobs), Arc::clone(&shutdown));
+                move |lost| {
+                    let drained = finish_jobs(&active_jobs, lost);
+                    if drained {
+                        shutdown.store(true, std::sync::atomic::Ordering::SeqCst);
+                    }
+                    drained
+                }
+            };
+            frontier = frontier.with_spill(store.queue_tree(), config.store.max_queued_in_memory, on_lost);
+            Some(Arc::new(store))
+        } else {
+            None
+        };
+
+        // Load the previous crawl's validators for conditional requests
+        let http_cache = if config.incremental {
+            Some(Arc::new(HttpCache::open(&config.output_dir, store.as_deref())?))
+        } else {
+            None
+        };
+
+        // Start counting against the crawl budget
+        let budget = BudgetTracker::new(config.budget.clone());
+
+        // Create checkpoint manager
+        let checkpoints = CheckpointManager::new(
+            config.output_dir.clone(),
+            config.base_url.clone(),
+            checkpoint::config_hash(&config),
+            config.checkpoint_interval,
+        );
 
         Ok(Self {
+            seeds: Arc::new(seeds),
             config,
             client,
             parser: HtmlParser::new(),
             robots_checker,
             url_filter,
+            normalizer,
             rate_limiter,
-            visited: Arc::new(DashMap::new()),
+            frontier: Arc::new(frontier),
+            scorer,
+            concurrency,
+            services,
+            visited: Arc::new(VisitedSet::new(store.clone())),
+            pending: Arc::new(PendingSet::new(store.clone())),
+            referrers: Arc::new(ReferrerIndex::new(store.clone())),
+            store,
+            url_map: Arc::new(DashMap::new()),
+            dedup: Arc::new(dedup),
+            http_cache,
+            budget: Arc::new(budget),
+            host_refused: Arc::new(DashSet::new()),
+            in_flight: Arc::new(DashMap::new()),
+            checkpoint_lock: Arc::new(RwLock::new(())),
+            checkpoints: Arc::new(Mutex::new(checkpoints)),
             results: Arc::new(Mutex::new(Vec::new())),
             stats: Arc::new(Mutex::new(CrawlStats::new())),
-            active_jobs: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
-            shutdown: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            active_jobs,
+            shutdown,
+            interrupted: Arc::new(std::sync::atomic::AtomicBool::new(false)),
         })
     }
 
+    /// Stops the crawl early: workers finish their in-flight requests, the
+    /// remaining frontier is checkpointed and `crawl` returns partial results
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, std::sync::atomic::Ordering::SeqCst);
+        self.shutdown.store(true, std::sync::atomic::Ordering::SeqCst);
+        self.frontier.close();
+    }
+
+    /// Stops the crawl once a budget runs out, the same way it stops when the
+    /// frontier is drained (the remaining frontier is checkpointed)
+    fn stop_for_budget(&self, limit: BudgetLimit) {
+        if !self.shutdown.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            eprintln!("Crawl {} budget exhausted, stopping", limit);
+        }
+        self.frontier.close();
+    }
+
+    /// Counts a job as finished. Jobs it queued were counted before it
+    /// finished, so the count only reaches zero once nothing is queued or
+    /// running, and the last job to finish ends the crawl.
+    fn finish_job(&self) {
+        if finish_jobs(&self.active_jobs, 1) {
+            self.drained();
+        }
+    }
+
+    /// Ends a crawl whose frontier is drained
+    fn drained(&self) {
+        self.shutdown.store(true, std::sync::atomic::Ordering::SeqCst);
+        self.frontier.close();
+    }
+
     pub async fn crawl(&self) -> Result<CrawlResults> {
-        let (tx, rx) = mpsc::channel::<CrawlJob>(10000);
-        let rx = Arc::new(tokio::sync::Mutex::new(rx));
-
-        // Try to fetch sitemap URLs first if enabled
-        if self.config.use_sitemap {
-            if let Some(domain) = &self.config.allowed_domain {
-                println!("Fetching sitemap URLs...");
-                let sitemap_parser = SitemapParser::new(self.config.timeout, self.config.max_sitemap_urls);
-
-                match sitemap_parser.fetch_sitemap_urls(domain).await {
-                    Ok(urls) if !urls.is_empty() => {
-                        println!("Adding {} URLs from sitemap", urls.len());
-                        for url in urls {
-                            if !self.visited.contains_key(&url) {
-                                self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                                tx.send(CrawlJob {
-                                    url,
-                                    depth: 1, // Sitemap URLs start at depth 1
-                                }).await?;
-                            }
-                        }
-                    }
-                    Ok(_) => {
-                        println!("No sitemap URLs found, falling back to base URL");
-                        // Fallback to base URL
-                        self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                        tx.send(CrawlJob {
-                            url: self.config.base_url.clone(),
-                            depth: 0,
-                        }).await?;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to fetch sitemap: {}", e);
-                        // Fallback to base URL
-                        self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                        tx.send(CrawlJob {
-                            url: self.config.base_url.clone(),
-                            depth: 0,
-                        }).await?;
-                    }
+        self.budget.start_clock();
+
+        if let Some(cache) = &self.http_cache {
+            println!("Incremental crawl: {} pages known from the previous crawl", cache.len());
+        }
+
+        // Restore the frontier from a checkpoint, or seed it
+        if !self.restore_checkpoint() {
+            if let Some(store) = &self.store {
+                if let Err(e) = store.clear() {
+                    eprintln!("Failed to clear crawl store: {}", e);
                 }
-            } else {
-                // No domain specified, use base URL
-                self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                tx.send(CrawlJob {
-                    url: self.config.base_url.clone(),
-                    depth: 0,
-                }).await?;
             }
-        } else {
-            // Sitemap disabled, use base URL
-            self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            tx.send(CrawlJob {
-                url: self.config.base_url.clone(),
-                depth: 0,
-            }).await?;
+            self.seed_frontier().await;
+        }
+
+        // Nothing was queued, so no job will ever finish the crawl
+        if self.active_jobs.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+            self.drained();
         }
 
-        // Spawn workers
+        // Spawn workers (enough for the highest adaptive concurrency)
+        let workers = self
+            .concurrency
+            .as_ref()
+            .map_or(self.config.max_workers, |controller| controller.max_workers());
         let mut handles = Vec::new();
-        for _ in 0..self.config.max_workers {
+        for _ in 0..workers {
             let engine = self.clone();
-            let tx_clone = tx.clone();
-            let rx_clone = Arc::clone(&rx);
 
             let handle = tokio::spawn(async move {
-                loop {
-                    // Check shutdown flag
-                    if engine.shutdown.load(std::sync::atomic::Ordering::SeqCst) {
-                        break;
-                    }
-
-                    let job = {
-                        let mut rx = rx_clone.lock().await;
-                        // Use try_recv with timeout to allow checking shutdown flag
-                        tokio::select! {
-                            job = rx.recv() => job,
-                            _ = tokio::time::sleep(Duration::from_millis(100)) => {
-                                continue;
-                            }
-                        }
-                    };
-
-                    match job {
-                        Some(job) => {
-                            if let Err(e) = engine.process_job(job, &tx_clone).await {
-                                eprintln!("Error processing job: {}", e);
-                            }
-                        }
-                        None => break,
+                // The frontier only hands out jobs for hosts that are ready,
+                // and returns None once the crawl shuts down
+                while let Some(job) = engine.frontier.next().await {
+                    let job = TakenJob { engine: &engine, job };
+                    if let Err(e) = engine.process_job(&job.job).await {
+                        eprintln!("Error processing job: {}", e);
                     }
                 }
             });
@@ -173,6 +284,7 @@ impl CrawlEngine {
         let stats_clone = Arc::clone(&self.stats);
         let active_jobs_clone = Arc::clone(&self.active_jobs);
         let shutdown_clone = Arc::clone(&self.shutdown);
+        let concurrency_clone = self.concurrency.clone();
         tokio::spawn(async move {
             let mut ticker = tokio::time::interval(Duration::from_secs(5));
             loop {
@@ -185,53 +297,82 @@ impl CrawlEngine {
                 let stats = stats_clone.lock();
                 let active = active_jobs_clone.load(std::sync::atomic::Ordering::SeqCst);
 
+                let concurrency = concurrency_clone.as_ref().map_or(String::new(), |controller| {
+                    let decisions = controller.stats();
+                    format!(
+                        " | Concurrency: {} (+{}/-{})",
+                        decisions.current, decisions.increases, decisions.decreases
+                    )
+                });
+
                 eprintln!(
-                    "[Progress] Pages: {}/{} | Active jobs: {} | Errors: {}",
+                    "[Progress] Pages: {}/{} | Active jobs: {} | Errors: {}{}",
                     stats.pages_crawled,
                     stats.pages_found,
                     active,
-                    stats.errors
+                    stats.errors,
+                    concurrency
                 );
             }
         });
 
-        // Spawn monitoring task that signals shutdown when all jobs are done
-        let active_jobs = Arc::clone(&self.active_jobs);
-        let shutdown = Arc::clone(&self.shutdown);
-        tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(Duration::from_millis(500));
-            loop {
-                ticker.tick().await;
-
-                let remaining = active_jobs.load(std::sync::atomic::Ordering::SeqCst);
-
-                if remaining == 0 {
-                    // Grace period - wait 2 seconds to catch late jobs
-                    tokio::time::sleep(Duration::from_secs(2)).await;
-
-                    // Check again after grace period
-                    let after_grace = active_jobs.load(std::sync::atomic::Ordering::SeqCst);
+        // Spawn checkpoint task
+        if self.config.checkpoint_interval > 0 {
+            let engine = self.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(Duration::from_secs(1));
+                loop {
+                    ticker.tick().await;
 
-                    if after_grace == 0 {
-                        // All jobs done, signal shutdown
-                        shutdown.store(true, std::sync::atomic::Ordering::SeqCst);
+                    if engine.shutdown.load(std::sync::atomic::Ordering::SeqCst) {
                         break;
                     }
+
+                    if engine.checkpoints.lock().should_save() {
+                        engine.save_checkpoint();
+                    }
+                }
+            });
+        }
+
+        // Wait for all workers. Once the time budget runs out no new request
+        // starts, and running ones get a grace period to finish before they
+        // are cut off (their URLs stay in flight and go into the checkpoint)
+        let aborts: Vec<_> = handles.iter().map(|handle| handle.abort_handle()).collect();
+        let mut workers = futures_util::future::join_all(handles);
+        if let Some(time_left) = self.budget.time_left() {
+            if tokio::time::timeout(time_left, &mut workers).await.is_err() {
+                self.stop_for_budget(self.budget.expire());
+                if tokio::time::timeout(BUDGET_GRACE, &mut workers).await.is_err() {
+                    aborts.iter().for_each(tokio::task::AbortHandle::abort);
                 }
             }
-        });
+        }
+        workers.await;
 
-        // Drop our reference to sender (monitoring task still has one)
-        drop(tx);
+        // Budget usage goes into the final checkpoint too
+        {
+            let mut stats = self.stats.lock();
+            stats.stopped_by = self.budget.exhausted();
+            stats.bytes_downloaded = self.budget.bytes();
+            stats.concurrency = self.concurrency.as_ref().map(|controller| controller.stats());
+        }
 
-        // Wait for all workers
-        for handle in handles {
-            let _ = handle.await;
+        // Keep a checkpoint only if the frontier was not drained
+        if self.config.checkpoint_interval > 0 {
+            if self.pending.is_empty() && self.in_flight.is_empty() {
+                if let Err(e) = self.checkpoints.lock().clear() {
+                    eprintln!("Failed to clear checkpoint: {}", e);
+                }
+            } else {
+                self.save_checkpoint();
+            }
         }
 
         // Finalize stats
         {
             let mut stats = self.stats.lock();
+            stats.interrupted = self.interrupted.load(std::sync::atomic::Ordering::SeqCst);
             stats.end_time = Some(Utc::now());
             stats.duration = Some(
                 stats.end_time.unwrap()
@@ -240,144 +381,2353 @@ impl CrawlEngine {
             );
         }
 
-        let results = self.results.lock().clone();
+        self.save_http_cache();
+
+        let results = self.take_results();
         let stats = self.stats.lock().clone();
 
-        Ok(CrawlResults { stats, results })
+        let mut map: Vec<MappedUrl> = self.url_map.iter().map(|e| e.value().clone()).collect();
+        map.sort_by(|a, b| a.depth.cmp(&b.depth).then_with(|| a.url.cmp(&b.url)));
+
+        Ok(CrawlResults { stats, results, map })
     }
 
-    async fn process_job(&self, job: CrawlJob, tx: &mpsc::Sender<CrawlJob>) -> Result<()> {
-        // CRITICAL: Decrement active_jobs IMMEDIATELY (Go pattern line 392)
-        self.active_jobs.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    /// Queues the starting URLs of every seed (sitemap URLs or the seed URL)
+    async fn seed_frontier(&self) {
+        for (index, seed) in self.seeds.iter().enumerate() {
+            // Try to fetch sitemap URLs first if enabled
+            let sitemap_urls = self.fetch_sitemap_urls(seed).await;
 
-        // Check if already visited
-        if self.visited.contains_key(&job.url) {
+            self.record_mapped(&seed.url, DiscoverySource::Seed, 0, index);
+
+            // Load the seed host's robots.txt so its Crawl-delay applies from the first request
+            self.robots_allows(&seed.url).await;
+
+            if self.config.map_only {
+                // Map mode lists sitemap URLs without fetching them and only
+                // walks links from the seed URL
+                println!("Mapping {} URLs from sitemap", sitemap_urls.len());
+                for entry in &sitemap_urls {
+                    self.record_mapped(&entry.url, DiscoverySource::Sitemap, 1, index);
+                }
+                self.enqueue(seed.url.clone(), 0, index, None, self.score(&seed.url, "", None));
+            } else if sitemap_urls.is_empty() {
+                self.enqueue(seed.url.clone(), 0, index, None, self.score(&seed.url, "", None));
+            } else {
+                println!("Adding {} URLs from sitemap", sitemap_urls.len());
+                for entry in sitemap_urls {
+                    // Sitemap URLs start at depth 1
+                    let score = self.score(&entry.url, "", entry.priority);
+                    self.enqueue(entry.url, 1, index, None, score);
+                }
+            }
+        }
+    }
+
+    /// Fetches sitemap URLs for a seed's domain (empty if disabled or none found)
+    async fn fetch_sitemap_urls(&self, seed: &Seed) -> Vec<SitemapEntry> {
+        if !self.config.use_sitemap {
+            return Vec::new();
+        }
+
+        // No domain specified, use the seed URL
+        let domain = match &seed.allowed_domain {
+            Some(domain) => domain,
+            None => return Vec::new(),
+        };
+
+        println!("Fetching sitemap URLs...");
+        let sitemap_parser = SitemapParser::new(self.config.timeout, self.config.max_sitemap_urls);
+
+        // Sitemaps declared in robots.txt take the place of the common locations
+        let robots_url = format!("https://{}/", domain);
+        let declared = match &self.robots_checker {
+            Some(checker) => checker.fetch_rules(&robots_url).await,
+            None => {
+                RobotsChecker::new(self.config.timeout, USER_AGENT.to_string())
+                    .fetch_rules(&robots_url)
+                    .await
+            }
+        }
+        .map(|rules| rules.sitemaps.clone())
+        .unwrap_or_default();
+
+        match sitemap_parser.fetch_sitemap_urls(domain, &declared).await {
+            Ok(urls) if !urls.is_empty() => urls,
+            Ok(_) => {
+                println!("No sitemap URLs found, falling back to base URL");
+                Vec::new()
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch sitemap: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Restores crawl state from a matching checkpoint and queues its
+    /// frontier, returning whether there was one
+    fn restore_checkpoint(&self) -> bool {
+        if !self.config.resume {
+            return false;
+        }
+
+        let checkpoint = match self.checkpoints.lock().try_load() {
+            Some(checkpoint) => checkpoint,
+            None => {
+                println!("No checkpoint to resume from, starting fresh");
+                return false;
+            }
+        };
+
+        // The disk store already holds the frontier, as of the sealed epoch
+        if let (Some(store), Some(epoch)) = (&self.store, checkpoint.store_epoch) {
+            if let Err(e) = store.rollback(epoch, &checkpoint.pending) {
+                eprintln!("Warning: Failed to restore crawl store, starting fresh: {}", e);
+                return false;
+            }
+        }
+        self.visited.restore(checkpoint.visited);
+
+        // Pages crawled before the interruption stay canonical for their
+        // content, and count as found again by an incremental crawl
+        let register = |page: &PageResult| {
+            if let Some(cache) = self.http_cache.as_ref().filter(|_| page.change.is_some()) {
+                cache.record(page);
+            }
+            if page.duplicate_of.is_none() {
+                if let Some(fingerprint) = ContentFingerprint::from_result(page) {
+                    self.dedup.register(&page.url, &fingerprint);
+                }
+            }
+        };
+        // With a disk store, results are kept there rather than in the checkpoint
+        match &self.store {
+            Some(store) => store.results().for_each(|page| register(&page)),
+            None => {
+                checkpoint.results.iter().for_each(register);
+                *self.results.lock() = checkpoint.results;
+            }
+        }
+
+        let mut stats = self.stats.lock();
+        *stats = checkpoint.stats;
+        stats.end_time = None;
+        stats.duration = None;
+        stats.stopped_by = None;
+        drop(stats);
+        // What the interrupted run used still counts against the budget
+        self.budget.restore(&checkpoint.budget);
+
+        let pending = match &self.store {
+            // Streamed from disk: they are pending already, so only queued
+            Some(store) => {
+                let mut count = 0;
+                for url in store.pending_urls() {
+                    self.push_job(&url, None);
+                    count += 1;
+                }
+                count
+            }
+            None => {
+                let count = checkpoint.pending.len();
+                for url in checkpoint.pending {
+                    self.enqueue(url.url, url.depth, url.seed, None, url.score);
+                }
+                count
+            }
+        };
+        println!(
+            "Resuming from checkpoint: {} URLs visited, {} pending",
+            self.visited.len(),
+            pending
+        );
+
+        true
+    }
+
+    /// Saves visited URLs, the pending frontier, results and stats to disk
+    ///
+    /// With a disk store, visited and pending URLs and results are already on
+    /// disk: the store's epoch is sealed and only in-flight URLs are listed.
+    fn save_checkpoint(&self) {
+        // No job may start or finish while the state is read
+        let snapshot = self.checkpoint_lock.write();
+        let (visited, pending, store_epoch) = match &self.store {
+            Some(store) => {
+                let epoch = match store.seal() {
+                    Ok(epoch) => epoch,
+                    Err(e) => {
+                        eprintln!("Failed to save checkpoint: {}", e);
+                        return;
+                    }
+                };
+                let in_flight = self.in_flight.iter().map(|entry| entry.value().clone()).collect();
+                (HashSet::new(), in_flight, Some(epoch))
+            }
+            None => {
+                // In-flight URLs are marked visited but have no result yet
+                let visited: HashSet<String> = self
+                    .visited
+                    .urls()
+                    .into_iter()
+                    .filter(|url| !self.in_flight.contains_key(url))
+                    .collect();
+
+                let mut pending = self.pending.urls();
+                pending.extend(self.in_flight.iter().map(|entry| entry.value().clone()));
+                (visited, pending, None)
+            }
+        };
+
+        let results = match &self.store {
+            Some(_) => Vec::new(),
+            None => self.results.lock().clone(),
+        };
+        let stats = self.stats.lock().clone();
+
+        let budget = self.budget.usage();
+        drop(snapshot);
+
+        if let Err(e) = self.checkpoints.lock().save(visited, pending, results, stats, budget, store_epoch) {
+            eprintln!("Failed to save checkpoint: {}", e);
+        }
+    }
+
+    /// Counts the previous crawl's pages that were not found again and saves
+    /// the HTTP cache. Interrupted or budget-limited crawls keep every
+    /// previous page.
+    fn save_http_cache(&self) {
+        let cache = match &self.http_cache {
+            Some(cache) => cache,
+            None => return,
+        };
+
+        let partial = self.interrupted.load(std::sync::atomic::Ordering::SeqCst)
+            || self.budget.exhausted().is_some()
+            || self.stats.lock().host_budget_skips > 0;
+        let removed = if partial { 0 } else { cache.removed() };
+        self.stats.lock().removed_pages = removed;
+
+        if let Err(e) = cache.save(!partial) {
+            eprintln!("Failed to save HTTP cache: {}", e);
+        }
+    }
+
+    /// Queues a job under its canonical URL, counting it as active before it
+    /// is pushed. The URL is still fetched as given, since servers need not
+    /// treat it like its canonical form. A URL that is already queued only has
+    /// its depth lowered and its score raised (and stays with the seed that
+    /// queued it first).
+    fn enqueue(&self, url: String, depth: usize, seed: usize, referrer: Option<&str>, score: f64) {
+        let key = self.normalizer.normalize(&url);
+        if self.visited.contains(&key) || self.host_refused.contains(&key) {
+            return;
+        }
+
+        let fetch_url = (key != url).then_some(url);
+        let pending = PendingUrl { url: key, depth, seed, score, fetch_url };
+        match self.pending.upsert(pending.clone()) {
+            Upsert::New => self.push_job(&pending, referrer),
+            Upsert::Raised => self.frontier.reprioritize(&pending.url, score),
+            Upsert::Kept => {}
+        }
+    }
+
+    /// Pushes a pending URL onto the frontier, counting it as active first
+    fn push_job(&self, pending: &PendingUrl, referrer: Option<&str>) {
+        // Counted before it can be handed out, so it is never finished uncounted
+        self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        self.frontier.push(CrawlJob {
+            url: pending.url.clone(),
+            depth: pending.depth,
+            referrer: referrer.map(str::to_string),
+            seed: pending.seed,
+            score: pending.score,
+            fetch_url: pending.fetch_url.clone(),
+        });
+    }
+
+    /// Priority score of a URL about to be queued (0 unless the priority
+    /// strategy is used)
+    fn score(&self, url: &str, anchor_text: &str, sitemap_priority: Option<f32>) -> f64 {
+        let scorer = match &self.scorer {
+            Some(scorer) => scorer,
+            None => return 0.0,
+        };
+
+        scorer.score(&ScoreInput {
+            url,
+            anchor_text,
+            sitemap_priority,
+            inbound_links: self.referrers.get(url).len(),
+        })
+    }
+
+    /// Checks robots.txt (when respected), applying the host's Crawl-delay
+    /// to its rate limit the first time its rules are seen
+    async fn robots_allows(&self, url: &str) -> bool {
+        let checker = match &self.robots_checker {
+            Some(checker) => checker,
+            None => return true,
+        };
+
+        match checker.fetch_rules(url).await {
+            Some(rules) => {
+                if let Some(delay) = rules.crawl_delay() {
+                    self.rate_limiter.set_crawl_delay(&frontier::host_key(url), delay);
+                }
+                rules.is_allowed(USER_AGENT, url)
+            }
+            None => true,
+        }
+    }
+
+    /// Checks the domain restriction of a seed
+    fn is_in_scope(&self, url: &str, seed: usize) -> bool {
+        let allowed_domain = match self.seeds.get(seed).and_then(|seed| seed.allowed_domain.as_ref()) {
+            Some(domain) => domain,
+            None => return true,
+        };
+
+        match Url::parse(url).ok().as_ref().and_then(|u| u.domain()) {
+            Some(domain) => {
+                domain == allowed_domain || domain.ends_with(&format!(".{}", allowed_domain))
+            }
+            None => true,
+        }
+    }
+
+    /// Seed a discovered URL is crawled under: the one it was found from if
+    /// it is in that seed's scope, or else the first seed whose scope it is in
+    fn seed_for(&self, url: &str, found_from: usize) -> Option<usize> {
+        if self.is_in_scope(url, found_from) {
+            return Some(found_from);
+        }
+        (0..self.seeds.len()).find(|&seed| self.is_in_scope(url, seed))
+    }
+
+    /// Records a URL in the map-only inventory, keeping its shallowest discovery
+    fn record_mapped(&self, url: &str, source: DiscoverySource, depth: usize, seed: usize) {
+        if !self.config.map_only || !self.is_in_scope(url, seed) {
+            return;
+        }
+
+        let url = self.normalizer.normalize(url);
+        self.url_map
+            .entry(url.clone())
+            .and_modify(|entry| {
+                if depth < entry.depth {
+                    entry.depth = depth;
+                    entry.source = source;
+                }
+            })
+            .or_insert(MappedUrl { url, source, depth });
+    }
+
+    /// Adds `referrer` to the inbound pages of `url`, returning whether `url`
+    /// was seen for the first time
+    fn record_referrer(&self, url: &str, referrer: &str) -> bool {
+        self.referrers.record(url, referrer, MAX_REFERRERS)
+    }
+
+    async fn process_job(&self, job: &CrawlJob) -> Result<()> {
+        // The frontier took a rate token for the job's host; jobs that send no
+        // request give it back so the host's next page is not held up
+        if !self.admit(job) {
+            self.rate_limiter.refund(&frontier::host_key(&job.url));
             return Ok(());
         }
 
-        // Mark as visited
-        self.visited.insert(job.url.clone(), ());
+        // Run at the shallowest depth the job was queued with. It stays
+        // pending until it is in flight, so no checkpoint can miss it.
+        let depth = self.pending.get(&job.url).map_or(job.depth, |queued| queued.depth);
+        let job = CrawlJob { depth, ..job.clone() };
 
-        // Update stats
+        // Track in-flight jobs so a checkpoint taken meanwhile re-queues them,
+        // then mark as visited
         {
-            let mut stats = self.stats.lock();
-            stats.pages_found += 1;
+            let _transition = self.checkpoint_lock.read();
+            self.in_flight.insert(job.url.clone(), job.to_pending());
+            self.visited.insert(job.to_pending());
+            self.pending.remove(&job.url);
         }
 
-        // Skip if domain restriction
-        if let Some(ref allowed_domain) = self.config.allowed_domain {
-            if let Ok(url) = Url::parse(&job.url) {
-                if let Some(domain) = url.domain() {
-                    if domain != allowed_domain && !domain.ends_with(&format!(".{}", allowed_domain)) {
-                        let mut stats = self.stats.lock();
-                        stats.external_links += 1;
-                        return Ok(());
-                    }
+        // Storing the result (or requeuing the job) takes it out of flight
+        self.visit(&job).await
+    }
+
+    /// Whether a job dequeued from the frontier should be fetched. Jobs that
+    /// are not are either dropped, or left in the frontier for the checkpoint
+    /// when the crawl is interrupted or out of budget.
+    fn admit(&self, job: &CrawlJob) -> bool {
+        if self.interrupted.load(std::sync::atomic::Ordering::SeqCst) || self.budget.exhausted().is_some() {
+            return false;
+        }
+
+        // Check if already visited
+        if self.visited.contains(&job.url) {
+            self.pending.remove(&job.url);
+            return false;
+        }
+
+        // Out of scope URLs (sitemap entries for other domains) are never
+        // fetched, nor marked visited
+        if !self.is_in_scope(&job.url, job.seed) {
+            self.pending.remove(&job.url);
+            self.stats.lock().external_links += 1;
+            return false;
+        }
+
+        // Reserve the page in the budget before any request is sent
+        match self.budget.start_page(&frontier::host_key(&job.url)) {
+            Ok(()) => true,
+            Err(Refusal::HostFull) => {
+                self.pending.remove(&job.url);
+                if self.host_refused.insert(job.url.clone()) {
+                    self.stats.lock().host_budget_skips += 1;
                 }
+                false
+            }
+            Err(Refusal::Exhausted(limit)) => {
+                self.stop_for_budget(limit);
+                false
             }
         }
+    }
+
+    /// Crawls a newly visited URL, stores its result and queues its links
+    async fn visit(&self, job: &CrawlJob) -> Result<()> {
+        // Update stats
+        {
+            let mut stats = self.stats.lock();
+            stats.pages_found += 1;
+        }
 
         // Crawl page
-        match self.crawl_page(&job.url, job.depth).await {
-            Ok(result) => {
-                // Queue discovered links if depth allows
-                if job.depth < self.config.max_depth {
-                    for link in &result.links {
-                        // Skip if already visited
-                        if self.visited.contains_key(link) {
-                            continue;
-                        }
+        match self.crawl_page(job).await {
+            Ok(result) if result.redirect_issue == Some(RedirectIssue::OutOfScope) => {
+                // Kept for its redirect chain, but the target is not crawled
+                self.store_result(job, result, |stats| stats.external_links += 1);
+            }
+            Ok(mut result) => {
+                // Queue discovered links if depth allows (map mode still
+                // records links found at the last level)
+                let expand = (job.depth < self.config.max_depth || self.config.map_only)
+                    && (result.duplicate_of.is_none() || self.config.dedup.expand_duplicates)
+                    && !result.robots.nofollow;
 
-                        // Check URL filter (exclude patterns)
-                        if !self.url_filter.should_crawl(link) {
-                            continue;
-                        }
+                for link in result.links.iter().filter(|link| link.is_navigable()) {
+                    // Keyed by its canonical form, but requested as written
+                    let href = &self.normalizer.normalize(&link.href);
+
+                    // Check URL filter (exclude patterns), against the canonical
+                    // form so `/photo.jpg?utm_source=x` is caught by `\.jpg$`
+                    if !self.url_filter.should_crawl(href) {
+                        continue;
+                    }
 
-                        // Check robots.txt if enabled
-                        if let Some(ref checker) = self.robots_checker {
-                            if !checker.is_allowed(link).await {
-                                continue;
+                    // Index inbound links at every depth, so failures name all their referrers
+                    let first_seen = self.record_referrer(href, &job.url);
+
+                    // Links into another seed's scope are crawled under that
+                    // seed; links outside every scope are never queued
+                    let seed = match self.seed_for(href, job.seed) {
+                        Some(seed) => seed,
+                        None => {
+                            if first_seen {
+                                self.stats.lock().external_links += 1;
                             }
+                            continue;
                         }
+                    };
+
+                    if !expand {
+                        continue;
+                    }
+
+                    // Check robots.txt if enabled
+                    if !self.robots_allows(&link.href).await {
+                        continue;
+                    }
 
-                        // CRITICAL: Increment BEFORE sending (Go pattern lines 342, 352, 408)
-                        self.active_jobs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    self.record_mapped(href, DiscoverySource::Link, job.depth + 1, seed);
 
-                        if tx.send(CrawlJob {
-                            url: link.clone(),
-                            depth: job.depth + 1,
-                        }).await.is_err() {
-                            // Channel closed, decrement back
-                            self.active_jobs.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-                            break;
-                        }
+                    // Skip if already visited or too deep
+                    if self.visited.contains(href) || job.depth >= self.config.max_depth {
+                        continue;
                     }
+
+                    let score = self.score(href, &link.text, None);
+                    self.enqueue(link.href.clone(), job.depth + 1, seed, Some(&job.url), score);
+                }
+
+                // Links are stored in canonical form
+                for link in &mut result.links {
+                    link.href = self.normalizer.normalize(&link.href);
                 }
 
-                // Store result
-                self.results.lock().push(result);
+                let duplicate = result.duplicate_of.is_some();
+                let change = result.change;
 
-                // Update stats
-                let mut stats = self.stats.lock();
-                stats.pages_crawled += 1;
+                // Store result and update stats
+                self.store_result(job, result, |stats| {
+                    stats.pages_crawled += 1;
+                    if duplicate {
+                        stats.duplicates += 1;
+                    }
+                    match change {
+                        Some(PageChange::New) => stats.new_pages += 1,
+                        Some(PageChange::Changed) => stats.changed_pages += 1,
+                        Some(PageChange::Unchanged) => stats.unchanged_pages += 1,
+                        None => {}
+                    }
+                });
             }
-            Err(e) => {
-                eprintln!("Error crawling {}: {}", job.url, e);
-                let mut stats = self.stats.lock();
-                stats.errors += 1;
+            Err(CrawlFailure::BudgetExhausted) => {
+                // Cut off by the byte budget rather than its own size limit
+                {
+                    let _transition = self.checkpoint_lock.read();
+                    self.visited.remove(&job.url);
+                    self.requeue(job);
+                    self.in_flight.remove(&job.url);
+                }
+                self.stop_for_budget(BudgetLimit::Bytes);
+            }
+            Err(CrawlFailure::Page(failure)) => {
+                eprintln!("Error crawling {}: {}", job.url, failure.message);
+
+                // Failed pages are kept so reports can list broken links
+                self.store_result(job, failure.into_result(job), |stats| stats.errors += 1);
             }
         }
 
         Ok(())
     }
 
-    async fn crawl_page(&self, url: &str, depth: usize) -> Result<PageResult> {
-        // Wait for rate limiter before making request
-        self.rate_limiter.wait().await;
+    /// Puts a job that was taken off the frontier back, for the checkpoint
+    fn requeue(&self, job: &CrawlJob) {
+        self.pending.insert(job.to_pending());
+    }
 
-        let response = self.client.get(url).send().await?;
+    /// Adds a page result, tagged with the seed it was reached from, counts
+    /// it in the stats with `count` and ends the job's flight, all in one
+    /// step for checkpoints. With a disk store, the result is only saved
+    /// there, which also checkpoints it.
+    fn store_result(&self, job: &CrawlJob, mut result: PageResult, count: impl FnOnce(&mut CrawlStats)) {
+        result.seed = self.seeds.get(job.seed).map(|seed| seed.url.clone());
+        let _transition = self.checkpoint_lock.read();
+        match &self.store {
+            Some(store) => {
+                if let Err(e) = store.add_result(&result) {
+                    eprintln!("Warning: Failed to save result for {}: {}", result.url, e);
+                }
+            }
+            None => self.results.lock().push(result),
+        }
+        count(&mut self.stats.lock());
+        self.in_flight.remove(&job.url);
+    }
 
-        let status_code = response.status().as_u16();
-        let content_type = response
-            .headers()
-            .get("content-type")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("unknown")
-            .to_string();
+    /// Takes the page results for the outputs, read back from the disk store
+    /// if there is one, and names the inbound pages of every failed URL
+    fn take_results(&self) -> Vec<PageResult> {
+        let mut results = match &self.store {
+            Some(store) => store.results().collect(),
+            None => std::mem::take(&mut *self.results.lock()),
+        };
+        for result in results.iter_mut().filter(|r| r.error.is_some()) {
+            result.linked_from = self.referrers.get(&result.url);
+        }
+        results
+    }
 
-        let html = response.text().await?;
-        let title = self.parser.parse_title(&html);
+    /// Sends a GET request, retrying transient failures per the retry policy.
+    /// Returns the last response along with the number of attempts made.
+    async fn fetch(&self, url: &str, headers: &HeaderMap) -> Result<(reqwest::Response, u32), PageError> {
+        let policy = &self.config.retry;
+        let mut attempt = 1;
 
-        let base_url = url::Url::parse(url)?;
-        let links = self.parser.parse_links(&html, &base_url)?;
+        loop {
+            let started = std::time::Instant::now();
+            let sent = self.client.get(url).headers(headers.clone()).send().await;
+            self.observe_request(started, &sent);
 
-        Ok(PageResult {
-            url: url.to_string(),
-            title,
-            status_code,
-            depth,
-            links,
-            error: None,
-            crawled_at: Utc::now(),
-            content_type,
-        })
+            let delay = match sent {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    if !policy.can_retry(attempt) || !policy.is_retryable_status(status) {
+                        return Ok((response, attempt));
+                    }
+
+                    // Servers may say when to come back on 429/503; one that
+                    // asks for too long a wait gets its answer kept
+                    let retry_after = match status {
+                        429 | 503 => retry::retry_after(response.headers()),
+                        _ => None,
+                    };
+                    match policy.delay(attempt, retry_after) {
+                        Some(delay) => delay,
+                        None => return Ok((response, attempt)),
+                    }
+                }
+                Err(e) => {
+                    if !policy.can_retry(attempt) || !policy.is_retryable_error(&e) {
+                        return Err(PageError::from_request(&e, attempt));
+                    }
+                    policy.backoff(attempt)
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+            // Retries still count against the host's rate limit
+            self.rate_limiter.wait(&frontier::host_key(url)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Feeds a request's latency and outcome to the adaptive concurrency
+    /// controller: 429, 503 and timeouts are overload signals
+    fn observe_request(&self, started: std::time::Instant, sent: &reqwest::Result<reqwest::Response>) {
+        let controller = match &self.concurrency {
+            Some(controller) => controller,
+            None => return,
+        };
+
+        let overloaded = match sent {
+            Ok(response) => matches!(response.status().as_u16(), 429 | 503),
+            Err(e) => e.is_timeout(),
+        };
+        let latency = started.elapsed();
+        let (running, capacity) = (self.frontier.running(), self.frontier.capacity());
+        if let Some(limit) = controller.record(started, latency, overloaded, running, capacity) {
+            self.frontier.set_max_active(limit);
+        }
+    }
+
+    /// Fetches a URL, following redirects one hop at a time so each hop is
+    /// recorded. Loops and chains longer than `max_redirects` are errors; a
+    /// hop leaving the allowed domain ends the chain without following it.
+    /// `conditional` headers are only sent with the first request.
+    async fn fetch_following(&self, job: &CrawlJob, conditional: HeaderMap) -> Result<Fetched, PageError> {
+        let url = job.request_url();
+        let mut chain = RedirectChain::default();
+        let mut current = url.to_string();
+        let mut attempts = 0;
+        let mut headers = conditional;
+
+        loop {
+            let fetched = self.fetch(&current, &headers).await;
+            headers.clear();
+            let (response, hop_attempts) = match fetched {
+                Ok(fetched) => fetched,
+                Err(error) => return Err(error.with_redirects(chain)),
+            };
+            attempts += hop_attempts;
+
+            let location = match response.headers().get(reqwest::header::LOCATION) {
+                Some(location) if response.status().is_redirection() => {
+                    location.to_str().ok().map(str::to_string)
+                }
+                _ => None,
+            };
+            let target = location.as_deref().and_then(|location| {
+                let mut target = Url::parse(&current).ok()?.join(location.trim()).ok()?;
+                target.set_fragment(None);
+                Some(target.to_string())
+            });
+
+            // Hops are requested as sent: normalizing them could undo a
+            // `/docs` -> `/docs/` redirect and loop
+            let (location, target) = match (location, target) {
+                (Some(location), Some(target)) => (location, target),
+                // Not a redirect (or one without a usable Location): the chain ends here
+                _ => {
+                    let final_url = self.normalizer.normalize(&current);
+                    return Ok(Fetched { response, attempts, chain: chain.finish(final_url) });
+                }
+            };
+
+            chain.hops.push(RedirectHop {
+                url: current,
+                status: response.status().as_u16(),
+                location,
+            });
+
+            let issue = if chain.hops.iter().any(|hop| hop.url == target) {
+                Some(RedirectIssue::Loop)
+            } else if chain.hops.len() > self.config.max_redirects {
+                Some(RedirectIssue::TooManyRedirects)
+            } else {
+                None
+            };
+            if let Some(issue) = issue {
+                let status_code = response.status().as_u16();
+                chain.final_url = Some(target);
+                chain.issue = Some(issue);
+                return Err(PageError {
+                    kind: ErrorKind::Redirect,
+                    message: format!("{}: {}", capitalize(&issue.to_string()), chain.describe()),
+                    status_code,
+                    content_type: "unknown".to_string(),
+                    attempts,
+                    redirects: chain,
+                });
+            }
+
+            if !self.is_in_scope(&target, job.seed) {
+                chain.final_url = Some(self.normalizer.normalize(&target));
+                chain.issue = Some(RedirectIssue::OutOfScope);
+                return Ok(Fetched { response, attempts, chain });
+            }
+
+            // Every hop is a request against its host's rate limit
+            self.rate_limiter.wait(&frontier::host_key(&target)).await;
+            current = target;
+        }
+    }
+
+    /// Fetches and parses a page. Transport failures, redirect loops and
+    /// non-2xx responses come back as a `PageError`; running out of byte
+    /// budget mid-download is not a failure of the page.
+    async fn crawl_page(&self, job: &CrawlJob) -> Result<PageResult, CrawlFailure> {
+        // Only ask whether the page changed when a 304 can be answered from the cache
+        let cached = self
+            .http_cache
+            .as_ref()
+            .and_then(|cache| cache.replayable(&job.url, self.config.convert_markdown));
+        let conditional = cached.as_ref().map(CacheEntry::conditional_headers).unwrap_or_default();
+        let Fetched { response, attempts, chain } = self.fetch_following(job, conditional).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED && chain.hops.is_empty() {
+            if let Some(cached) = cached {
+                return Ok(self.replay_cached(job, cached.page, attempts));
+            }
+        }
+
+        if chain.issue == Some(RedirectIssue::OutOfScope) {
+            return Ok(chain.into_result(job, response.status().as_u16(), attempts));
+        }
+
+        if let Some(final_url) = chain.final_url.as_ref().filter(|url| **url != job.url) {
+            // The target may have been crawled already, directly or through another redirect
+            for hop in chain.hops.iter().skip(1) {
+                let url = self.normalizer.normalize(&hop.url);
+                self.visited.insert(PendingUrl { url, fetch_url: None, ..job.to_pending() });
+            }
+            let first_visit = self.visited.insert(PendingUrl {
+                url: final_url.clone(),
+                fetch_url: None,
+                ..job.to_pending()
+            });
+            self.record_mapped(final_url, DiscoverySource::Redirect, job.depth, job.seed);
+
+            if !first_visit {
+                return Ok(chain.into_result(job, response.status().as_u16(), attempts));
+            }
+        }
+
+        match self.read_page(job, response, attempts).await {
+            Ok(mut page) => {
+                chain.apply(&mut page);
+                if let Some(cache) = &self.http_cache {
+                    page.change = Some(cache.classify(&page));
+                    cache.record(&page);
+                }
+                Ok(page)
+            }
+            Err(CrawlFailure::Page(error)) => Err(CrawlFailure::Page(error.with_redirects(chain))),
+            Err(CrawlFailure::BudgetExhausted) => Err(CrawlFailure::BudgetExhausted),
+        }
+    }
+
+    /// Result for a page the server reported as not modified, rebuilt from
+    /// the HTTP cache (its links are queued as if it had been downloaded)
+    fn replay_cached(&self, job: &CrawlJob, cached: PageResult, attempts: u32) -> PageResult {
+        // Unchanged pages still take part in duplicate detection
+        let duplicate = ContentFingerprint::from_result(&cached)
+            .filter(|_| self.config.dedup.enabled)
+            .and_then(|fingerprint| self.dedup.check(&job.url, &fingerprint));
+
+        let page = PageResult {
+            depth: job.depth,
+            referrer: job.referrer.clone(),
+            linked_from: Vec::new(),
+            crawled_at: Utc::now(),
+            attempts,
+            duplicate_kind: duplicate.as_ref().map(|d| d.kind),
+            duplicate_of: duplicate.map(|d| d.canonical),
+            duplicates: Vec::new(),
+            change: Some(PageChange::Unchanged),
+            ..cached
+        };
+        if let Some(cache) = &self.http_cache {
+            cache.record(&page);
+        }
+        page
+    }
+
+    /// Reads and parses the response for a page
+    async fn read_page(
+        &self,
+        job: &CrawlJob,
+        response: reqwest::Response,
+        attempts: u32,
+    ) -> Result<PageResult, CrawlFailure> {
+        let (url, depth) = (job.url.as_str(), job.depth);
+        // Links resolve against the URL actually fetched, which after a
+        // redirect (or for `/docs/` keyed as `/docs`) differs from `url`
+        let base_url = response.url().clone();
+
+        let status_code = response.status().as_u16();
+        let header_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let content_type = header_type.clone().unwrap_or_else(|| "unknown".to_string());
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+
+        // X-Robots-Tag may be repeated, and scoped to a single crawler
+        let mut directives = RobotsDirectives::default();
+        if self.config.respect_robots_txt {
+            for value in response.headers().get_all("x-robots-tag") {
+                if let Ok(value) = value.to_str() {
+                    directives.add_header(value, robots::product_token(USER_AGENT));
+                }
+            }
+        }
+
+        if !response.status().is_success() {
+            return Err(PageError {
+                kind: ErrorKind::HttpStatus,
+                message: format!("HTTP {}", response.status()),
+                status_code,
+                content_type,
+                attempts,
+                redirects: RedirectChain::default(),
+            }
+            .into());
+        }
+
+        // Unwanted content types are recorded without downloading the body
+        if !body::is_allowed_type(header_type.as_deref(), &self.config.allowed_content_types) {
+            return Ok(PageResult {
+                url: url.to_string(),
+                status_code,
+                depth,
+                referrer: job.referrer.clone(),
+                crawled_at: Utc::now(),
+                attempts,
+                content_type,
+                etag,
+                last_modified,
+                ..Default::default()
+            });
+        }
+
+        let body = body::read_limited(response, self.config.max_body_bytes, &self.budget)
+            .await
+            .map_err(|e| match e {
+                BodyError::BudgetExhausted => CrawlFailure::BudgetExhausted,
+                BodyError::TooLarge { limit } => CrawlFailure::Page(PageError {
+                    kind: ErrorKind::TooLarge,
+                    message: format!("Body larger than {} bytes", limit),
+                    status_code,
+                    content_type: content_type.clone(),
+                    attempts,
+                    redirects: RedirectChain::default(),
+                }),
+                BodyError::Request(e) => CrawlFailure::Page(PageError {
+                    status_code,
+                    content_type: content_type.clone(),
+                    ..PageError::from_request(&e, attempts)
+                }),
+            })?;
+        let decoded = body::decode(&body, header_type.as_deref(), tld(&base_url));
+        let html = decoded.text;
+        let title = self.parser.parse_title(&html);
+
+        let links = self
+            .parser
+            .parse_links(&html, &base_url)
+            .map_err(|e| PageError {
+                kind: ErrorKind::BodyDecode,
+                message: format!("Failed to parse links: {}", e),
+                status_code,
+                content_type: content_type.clone(),
+                attempts,
+                redirects: RedirectChain::default(),
+            })?;
+
+        let canonical_url = links
+            .iter()
+            .find(|link| link.element == LinkElement::Link && link.has_rel("canonical"))
+            .map(|link| self.normalizer.normalize(&link.href));
+
+        if self.config.respect_robots_txt {
+            for content in self.parser.parse_meta_robots(&html, robots::product_token(USER_AGENT)) {
+                directives.add(&content);
+            }
+        }
+
+        // Post-process while the HTML is still in memory (noindex pages are
+        // kept out of content outputs)
+        let content = if content_type.contains("html")
+            && !self.config.map_only
+            && !directives.noindex
+        {
+            self.process_content(&html, url)
+        } else {
+            ProcessedContent::default()
+        };
+
+        let duplicate = content
+            .fingerprint
+            .as_ref()
+            .filter(|_| self.config.dedup.enabled)
+            .and_then(|fingerprint| self.dedup.check(url, fingerprint));
+
+        Ok(PageResult {
+            url: url.to_string(),
+            title,
+            status_code,
+            depth,
+            seed: None,
+            links,
+            canonical_url,
+            robots: directives,
+            error: None,
+            error_kind: None,
+            referrer: job.referrer.clone(),
+            linked_from: Vec::new(),
+            final_url: None,
+            redirects: Vec::new(),
+            redirect_issue: None,
+            crawled_at: Utc::now(),
+            attempts,
+            content_type,
+            encoding: Some(decoded.encoding.name().to_string()),
+            etag,
+            last_modified,
+            change: None,
+            markdown: content.markdown,
+            word_count: content.word_count,
+            filter_stats: content.filter_stats,
+            content_hash: content.fingerprint.map(|f| f.hash_hex()),
+            simhash: content.fingerprint.map(|f| f.simhash_hex()),
+            duplicate_kind: duplicate.as_ref().map(|d| d.kind),
+            duplicate_of: duplicate.map(|d| d.canonical),
+            duplicates: Vec::new(),
+        })
+    }
+
+    /// Runs page HTML through the content filter and Markdown services, and
+    /// fingerprints the filtered text
+    fn process_content(&self, html: &str, url: &str) -> ProcessedContent {
+        let (filtered, filter_stats) = match self.services.content_filter.filter(html) {
+            Ok((filtered, stats)) => (filtered, Some(stats)),
+            Err(e) => {
+                eprintln!("Content filter failed for {}: {}", url, e);
+                (html.to_string(), None)
+            }
+        };
+
+        // Pages without text would all share one hash and pass for duplicates
+        let fingerprint = Some(ContentFingerprint::from_text(&dedup::visible_text(&filtered)))
+            .filter(|fingerprint| fingerprint.words > 0);
+
+        match self.services.markdown.convert(&filtered, url) {
+            Ok(output) => ProcessedContent {
+                markdown: self.config.convert_markdown.then_some(output.content),
+                word_count: Some(output.word_count),
+                filter_stats,
+                fingerprint,
+            },
+            Err(e) => {
+                eprintln!("Markdown conversion failed for {}: {}", url, e);
+                ProcessedContent {
+                    filter_stats,
+                    fingerprint,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+/// Counts `count` jobs as finished, returning whether none are left
+fn finish_jobs(active_jobs: &std::sync::atomic::AtomicUsize, count: usize) -> bool {
+    active_jobs.fetch_sub(count, std::sync::atomic::Ordering::SeqCst) == count
+}
+
+/// A job handed out by the frontier. Dropping it completes the job, so a
+/// worker that panics or is aborted still frees its host slot and lets the
+/// crawl finish.
+struct TakenJob<'a> {
+    engine: &'a CrawlEngine,
+    job: CrawlJob,
+}
+
+impl Drop for TakenJob<'_> {
+    fn drop(&mut self) {
+        self.engine.frontier.complete(&self.job);
+        self.engine.finish_job();
     }
 }
 
+/// Output of the content pipeline for one page
+#[derive(Debug, Default)]
+struct ProcessedContent {
+    markdown: Option<String>,
+    word_count: Option<usize>,
+    filter_stats: Option<FilterStats>,
+    fingerprint: Option<ContentFingerprint>,
+}
+
+/// Response at the end of a redirect chain
+struct Fetched {
+    response: reqwest::Response,
+    /// Requests made across all hops, retries included
+    attempts: u32,
+    chain: RedirectChain,
+}
+
+/// Redirects followed while fetching a page
+#[derive(Debug, Default)]
+struct RedirectChain {
+    /// Hops with the URLs as requested
+    hops: Vec<RedirectHop>,
+    /// Canonical URL the chain ended at (None when there was no redirect)
+    final_url: Option<String>,
+    issue: Option<RedirectIssue>,
+}
+
+impl RedirectChain {
+    /// Ends the chain at `url` (canonical form), flagging it if it took many hops
+    fn finish(mut self, url: String) -> Self {
+        if !self.hops.is_empty() {
+            self.final_url = Some(url);
+            if self.hops.len() >= LONG_REDIRECT_CHAIN {
+                self.issue = Some(RedirectIssue::LongChain);
+            }
+        }
+        self
+    }
+
+    /// The chain as `a -> b -> c`
+    fn describe(&self) -> String {
+        let mut urls: Vec<&str> = self.hops.iter().map(|hop| hop.url.as_str()).collect();
+        urls.extend(self.final_url.as_deref());
+        urls.join(" -> ")
+    }
+
+    /// Copies the chain onto a page result
+    fn apply(self, page: &mut PageResult) {
+        page.final_url = self.final_url;
+        page.redirects = self.hops;
+        page.redirect_issue = self.issue;
+    }
+
+    /// Result for a page that is only recorded for its redirects (target out
+    /// of scope or already crawled)
+    fn into_result(self, job: &CrawlJob, status_code: u16, attempts: u32) -> PageResult {
+        let mut page = PageResult {
+            url: job.url.clone(),
+            status_code,
+            depth: job.depth,
+            referrer: job.referrer.clone(),
+            crawled_at: Utc::now(),
+            attempts,
+            ..Default::default()
+        };
+        self.apply(&mut page);
+        page
+    }
+}
+
+/// A page that could not be crawled
+#[derive(Debug)]
+struct PageError {
+    kind: ErrorKind,
+    message: String,
+    status_code: u16,
+    content_type: String,
+    attempts: u32,
+    redirects: RedirectChain,
+}
+
+impl PageError {
+    fn from_request(error: &reqwest::Error, attempts: u32) -> Self {
+        let message = match attempts {
+            1 => error_chain(error),
+            _ => format!("{} (after {} attempts)", error_chain(error), attempts),
+        };
+
+        Self {
+            kind: classify_error(error),
+            message,
+            status_code: error.status().map_or(0, |s| s.as_u16()),
+            content_type: "unknown".to_string(),
+            attempts,
+            redirects: RedirectChain::default(),
+        }
+    }
+
+    /// Attaches the redirects followed before the failure
+    fn with_redirects(self, redirects: RedirectChain) -> Self {
+        Self { redirects, ..self }
+    }
+
+    /// Result entry recording the failure for `job`
+    fn into_result(self, job: &CrawlJob) -> PageResult {
+        let mut page = PageResult {
+            url: job.url.clone(),
+            status_code: self.status_code,
+            depth: job.depth,
+            error: Some(self.message),
+            error_kind: Some(self.kind),
+            referrer: job.referrer.clone(),
+            crawled_at: Utc::now(),
+            attempts: self.attempts,
+            content_type: self.content_type,
+            ..Default::default()
+        };
+        self.redirects.apply(&mut page);
+        page
+    }
+}
+
+/// Why a page could not be crawled
+#[derive(Debug)]
+enum CrawlFailure {
+    /// The page failed, and is recorded as such
+    Page(PageError),
+    /// The crawl's byte budget ran out mid-download; the page is requeued
+    /// rather than recorded
+    BudgetExhausted,
+}
+
+impl From<PageError> for CrawlFailure {
+    fn from(error: PageError) -> Self {
+        Self::Page(error)
+    }
+}
+
+/// Sorts a request error into an `ErrorKind`, looking through its causes
+/// for DNS and TLS failures that reqwest reports as connect errors
+fn classify_error(error: &reqwest::Error) -> ErrorKind {
+    if error.is_timeout() {
+        return ErrorKind::Timeout;
+    }
+    if error.is_body() || error.is_decode() {
+        return ErrorKind::BodyDecode;
+    }
+    if error.is_status() {
+        return ErrorKind::HttpStatus;
+    }
+
+    let chain = error_chain(error).to_lowercase();
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        ErrorKind::Dns
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") {
+        ErrorKind::Tls
+    } else if error.is_connect()
+        || chain.contains("connection reset")
+        || chain.contains("connection closed")
+    {
+        ErrorKind::Connection
+    } else {
+        ErrorKind::Request
+    }
+}
+
+/// Top-level domain of a URL's host, a hint for charset detection
+fn tld(url: &Url) -> Option<&str> {
+    url.domain()?.rsplit('.').next()
+}
+
+/// `text` with its first letter uppercased
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Error message followed by its causes, since reqwest's own message
+/// rarely says what went wrong
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
 impl Clone for CrawlEngine {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
+            seeds: Arc::clone(&self.seeds),
             client: self.client.clone(),
             parser: HtmlParser::new(),
             robots_checker: self.robots_checker.clone(),
             url_filter: self.url_filter.clone(),
+            normalizer: self.normalizer.clone(),
             rate_limiter: self.rate_limiter.clone(),
+            frontier: Arc::clone(&self.frontier),
+            scorer: self.scorer.clone(),
+            concurrency: self.concurrency.clone(),
+            services: self.services.clone(),
+            store: self.store.clone(),
             visited: Arc::clone(&self.visited),
+            url_map: Arc::clone(&self.url_map),
+            referrers: Arc::clone(&self.referrers),
+            dedup: Arc::clone(&self.dedup),
+            http_cache: self.http_cache.clone(),
+            budget: Arc::clone(&self.budget),
+            pending: Arc::clone(&self.pending),
+            host_refused: Arc::clone(&self.host_refused),
+            in_flight: Arc::clone(&self.in_flight),
+            checkpoint_lock: Arc::clone(&self.checkpoint_lock),
+            checkpoints: Arc::clone(&self.checkpoints),
             results: Arc::clone(&self.results),
             stats: Arc::clone(&self.stats),
             active_jobs: Arc::clone(&self.active_jobs),
             shutdown: Arc::clone(&self.shutdown),
+            interrupted: Arc::clone(&self.interrupted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::budget::CrawlBudget;
+    use crate::crawler::store::StoreConfig;
+
+    /// Configuration of test crawls: robots.txt, sitemaps, checkpoints and
+    /// Markdown are off unless a test turns them on
+    fn test_config(base_url: impl Into<String>) -> CrawlerConfig {
+        CrawlerConfig {
+            base_url: base_url.into(),
+            respect_robots_txt: false,
+            use_sitemap: false,
+            checkpoint_interval: 0,
+            convert_markdown: false,
+            ..Default::default()
+        }
+    }
+
+    fn test_engine(config: CrawlerConfig) -> CrawlEngine {
+        CrawlEngine::new(config, ServiceContainer::new()).unwrap()
+    }
+
+    const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+    /// Answers a raw HTTP request with a raw response, or `None` to leave
+    /// the connection hanging
+    type Handler = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+    /// Route table of a local test server: handlers by request path
+    #[derive(Default)]
+    struct Routes(Vec<(String, Handler)>);
+
+    impl Routes {
+        /// Canned responses by request path
+        fn canned(responses: &[(&str, &'static str)]) -> Self {
+            responses.iter().fold(Self::default(), |routes, &(path, response)| {
+                routes.route(path, move |_| Some(response.to_string()))
+            })
+        }
+
+        /// `pages` pages linked as a binary tree (`/0` links to `/1` and
+        /// `/2`, and so on)
+        fn tree(pages: usize) -> Self {
+            (0..pages).fold(Self::default(), |routes, page| {
+                let links: String = [2 * page + 1, 2 * page + 2]
+                    .iter()
+                    .filter(|child| **child < pages)
+                    .map(|child| format!("<a href=\"/{}\">{}</a>", child, child))
+                    .collect();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+                     <html><body>{}</body></html>",
+                    links
+                );
+                routes.route(&format!("/{}", page), move |_| Some(response.clone()))
+            })
+        }
+
+        fn route(mut self, path: &str, handler: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+            self.0.push((path.to_string(), Arc::new(handler)));
+            self
+        }
+
+        /// Serves the routes on a local port until the test ends, returning
+        /// the base URL. Paths without a route get a 404.
+        async fn serve(self) -> String {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let routes = Arc::new(self.0);
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let routes = Arc::clone(&routes);
+                    tokio::spawn(async move {
+                        let mut buf = [0u8; 1024];
+                        let read = socket.read(&mut buf).await.unwrap_or(0);
+                        let request = String::from_utf8_lossy(&buf[..read]);
+                        let path = request.split_whitespace().nth(1).unwrap_or("/");
+                        let response = match routes.iter().find(|(route, _)| route.as_str() == path) {
+                            Some((_, handler)) => handler(&request),
+                            None => Some(NOT_FOUND.to_string()),
+                        };
+                        match response {
+                            Some(response) => {
+                                let _ = socket.write_all(response.as_bytes()).await;
+                            }
+                            // Held open, unanswered, until the test ends
+                            None => std::future::pending::<()>().await,
+                        }
+                    });
+                }
+            });
+            format!("http://{}", addr)
+        }
+    }
+
+    #[test]
+    fn test_process_content_stores_markdown() {
+        let engine = test_engine(CrawlerConfig { convert_markdown: true, ..test_config("https://example.com") });
+        let html = r#"<html><head><title>Doc</title></head>
+            <body><nav>Menu</nav><p>Hello crawler world</p></body></html>"#;
+
+        let content = engine.process_content(html, "https://example.com/doc");
+
+        assert!(content.markdown.unwrap().contains("Hello crawler world"));
+        assert!(content.word_count.unwrap() >= 3);
+        assert_eq!(content.filter_stats.unwrap().original_size, html.len());
+        assert!(content.fingerprint.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pages_without_text_are_not_duplicates() {
+        const HOME: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/gallery\">gallery</a><a href=\"/blank\">blank</a></body></html>";
+        const GALLERY: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><img src=\"/cat.png\"><img src=\"/dog.png\"></body></html>";
+        const BLANK: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body></body></html>";
+        static ROUTES: &[(&str, &str)] = &[("/", HOME), ("/gallery", GALLERY), ("/blank", BLANK)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(test_config(format!("{}/", base)));
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 3);
+        assert_eq!(results.stats.duplicates, 0);
+        assert!(results.results.iter().all(|page| page.duplicate_of.is_none()));
+        let blank = results.results.iter().find(|page| page.url.ends_with("/blank")).unwrap();
+        assert_eq!(blank.content_hash, None);
+    }
+
+    #[test]
+    fn test_record_mapped_dedups_canonical_urls() {
+        let engine = test_engine(CrawlerConfig {
+            allowed_domain: Some("example.com".to_string()),
+            map_only: true,
+            ..test_config("https://example.com")
+        });
+
+        engine.record_mapped("https://example.com/docs", DiscoverySource::Sitemap, 1, 0);
+        engine.record_mapped("https://EXAMPLE.com:443/docs#intro", DiscoverySource::Link, 2, 0);
+
+        assert_eq!(engine.url_map.len(), 1);
+        let entry = engine.url_map.get("https://example.com/docs").unwrap();
+        assert_eq!(entry.source, DiscoverySource::Sitemap);
+        assert_eq!(entry.depth, 1);
+    }
+
+    #[test]
+    fn test_record_mapped_keeps_shallowest_discovery() {
+        let engine = test_engine(CrawlerConfig {
+            allowed_domain: Some("example.com".to_string()),
+            map_only: true,
+            ..test_config("https://example.com")
+        });
+
+        engine.record_mapped("https://example.com/a", DiscoverySource::Link, 2, 0);
+        engine.record_mapped("https://example.com/a", DiscoverySource::Redirect, 1, 0);
+
+        let entry = engine.url_map.get("https://example.com/a").unwrap();
+        assert_eq!(entry.source, DiscoverySource::Redirect);
+        assert_eq!(entry.depth, 1);
+    }
+
+    #[test]
+    fn test_record_mapped_skips_out_of_scope() {
+        let engine = test_engine(CrawlerConfig {
+            allowed_domain: Some("example.com".to_string()),
+            map_only: true,
+            ..test_config("https://example.com")
+        });
+
+        engine.record_mapped("https://other.org/page", DiscoverySource::Link, 1, 0);
+        engine.record_mapped("https://blog.example.com/post", DiscoverySource::Link, 1, 0);
+
+        assert_eq!(engine.url_map.len(), 1);
+        assert!(engine.url_map.contains_key("https://blog.example.com/post"));
+    }
+
+    #[test]
+    fn test_record_mapped_ignored_outside_map_mode() {
+        let engine = test_engine(test_config("https://example.com"));
+
+        engine.record_mapped("https://example.com/a", DiscoverySource::Link, 1, 0);
+
+        assert!(engine.url_map.is_empty());
+    }
+
+    #[test]
+    fn test_each_seed_keeps_its_own_scope() {
+        let engine = test_engine(CrawlerConfig {
+            seeds: vec![
+                Seed::new("https://example.com", None),
+                Seed::new("https://docs.example.org/guide", None),
+            ],
+            ..test_config("https://example.com")
+        });
+
+        assert!(engine.is_in_scope("https://blog.example.com/post", 0));
+        assert!(!engine.is_in_scope("https://docs.example.org/api", 0));
+        assert!(engine.is_in_scope("https://docs.example.org/api", 1));
+        assert!(!engine.is_in_scope("https://example.com/", 1));
+
+        // Both seeds feed the same frontier, and results name their seed
+        engine.enqueue("https://example.com/".to_string(), 0, 0, None, 0.0);
+        engine.enqueue("https://docs.example.org/guide".to_string(), 0, 1, None, 0.0);
+        assert_eq!(engine.frontier.len(), 2);
+        assert_eq!(engine.pending.get("https://docs.example.org/guide").unwrap().seed, 1);
+
+        let job = CrawlJob { url: "https://docs.example.org/guide".to_string(), depth: 0, referrer: None, seed: 1, score: 0.0, fetch_url: None };
+        engine.store_result(&job, PageResult { url: job.url.clone(), ..Default::default() }, |_| {});
+        assert_eq!(engine.results.lock()[0].seed.as_deref(), Some("https://docs.example.org/guide"));
+    }
+
+    #[tokio::test]
+    async fn test_links_into_another_seed_go_to_that_seed() {
+        static ROUTES: &[(&str, &str)] = &[(
+            "/",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"https://docs.example.org/guide\">Guide</a>\
+             <a href=\"https://other.org/x\">Other</a></body></html>",
+        )];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            seeds: vec![
+                Seed::new(format!("{}/", base), Some("example.com".to_string())),
+                Seed::new("https://docs.example.org/", None),
+            ],
+            ..test_config(format!("{}/", base))
+        });
+
+        // The local page is in the first seed's scope (IP hosts always are)
+        let job = CrawlJob { url: format!("{}/", base), depth: 0, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+        engine.process_job(&job).await.unwrap();
+
+        let guide = "https://docs.example.org/guide";
+        assert_eq!(engine.pending.get(guide).unwrap().seed, 1);
+        assert!(!engine.visited.contains(guide));
+        assert!(!engine.pending.contains("https://other.org/x"));
+        assert!(!engine.visited.contains("https://other.org/x"));
+        assert_eq!(engine.stats.lock().external_links, 1);
+
+        // Out of scope jobs are dropped without being marked visited
+        let stray = CrawlJob { url: guide.to_string(), depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+        engine.process_job(&stray).await.unwrap();
+        assert!(!engine.visited.contains(guide));
+    }
+
+    #[tokio::test]
+    async fn test_skipped_job_gives_back_its_rate_token() {
+        let engine = test_engine(CrawlerConfig { rate_limit: 0.1, ..test_config("https://example.com") });
+        let job = CrawlJob { url: "https://example.com/a".to_string(), depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+        engine.visited.insert(job.to_pending());
+
+        // The frontier spent the host's token on a job that turns out visited
+        engine.rate_limiter.try_acquire("example.com").unwrap();
+        engine.process_job(&job).await.unwrap();
+        assert!(engine.rate_limiter.try_acquire("example.com").is_ok());
+    }
+
+    fn queued(url: &str, depth: usize) -> PendingUrl {
+        PendingUrl { url: url.to_string(), depth, seed: 0, score: 0.0, fetch_url: None }
+    }
+
+    #[test]
+    fn test_job_finishing_mid_snapshot_waits_for_checkpoint() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let engine = test_engine(CrawlerConfig {
+            output_dir: output_dir.path().to_path_buf(),
+            resume: true,
+            ..test_config("https://example.com")
+        });
+        let job = CrawlJob { url: "https://example.com/a".to_string(), depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+        engine.in_flight.insert(job.url.clone(), job.to_pending());
+        engine.visited.insert(job.to_pending());
+
+        // The job finishes while a checkpoint is reading the crawl state
+        let snapshot = engine.checkpoint_lock.write();
+        let finishing = {
+            let (engine, job) = (engine.clone(), job.clone());
+            std::thread::spawn(move || {
+                engine.store_result(&job, PageResult { url: job.url.clone(), ..Default::default() }, |stats| {
+                    stats.pages_crawled += 1
+                })
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!finishing.is_finished());
+        assert!(engine.results.lock().is_empty());
+        assert!(engine.in_flight.contains_key(&job.url));
+        drop(snapshot);
+        finishing.join().unwrap();
+
+        // Its result and its end of flight land in the same checkpoint
+        assert!(engine.in_flight.is_empty());
+        engine.save_checkpoint();
+        let checkpoint = engine.checkpoints.lock().try_load().unwrap();
+        assert!(checkpoint.visited.contains(&job.url));
+        assert!(checkpoint.pending.is_empty());
+        assert_eq!(checkpoint.results.len(), 1);
+        assert_eq!(checkpoint.stats.pages_crawled, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip_keeps_frontier() {
+        for disk_store in [false, true] {
+            let output_dir = tempfile::tempdir().unwrap();
+            let config = CrawlerConfig {
+                output_dir: output_dir.path().to_path_buf(),
+                resume: true,
+                store: StoreConfig { enabled: disk_store, ..Default::default() },
+                ..test_config("https://example.com")
+            };
+
+            let engine = test_engine(config.clone());
+            engine.in_flight.insert("https://example.com/busy".to_string(), queued("https://example.com/busy", 1));
+            engine.visited.insert(queued("https://example.com", 0));
+            engine.visited.insert(queued("https://example.com/busy", 1));
+            engine.pending.insert(queued("https://example.com/queued", 2));
+            let home = CrawlJob { url: "https://example.com".to_string(), depth: 0, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+            engine.store_result(&home, PageResult { url: home.url.clone(), ..Default::default() }, |_| {});
+            // The disk store keeps results out of memory
+            assert_eq!(engine.results.lock().len(), usize::from(!disk_store));
+            engine.save_checkpoint();
+            drop(engine);
+
+            let resumed = test_engine(config);
+            assert!(resumed.restore_checkpoint());
+
+            assert_eq!(resumed.frontier.len(), 2);
+            assert_eq!(resumed.pending.get("https://example.com/busy"), Some(queued("https://example.com/busy", 1)));
+            assert_eq!(resumed.pending.get("https://example.com/queued"), Some(queued("https://example.com/queued", 2)));
+            // The in-flight URL must be crawled again, so it is not restored as visited
+            assert!(resumed.visited.contains("https://example.com"));
+            assert!(!resumed.visited.contains("https://example.com/busy"));
+            assert_eq!(resumed.take_results().len(), 1);
+            // The disk store checkpoints results itself
+            let checkpoint = resumed.checkpoints.lock().try_load().unwrap();
+            assert_eq!(checkpoint.results.len(), usize::from(!disk_store));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_jobs_stay_pending() {
+        let engine = test_engine(test_config("https://example.com"));
+        let url = "https://example.com/later".to_string();
+        engine.pending.insert(queued(&url, 1));
+
+        engine.interrupt();
+        engine
+            .process_job(&CrawlJob { url: url.clone(), depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None })
+            .await
+            .unwrap();
+
+        assert!(engine.shutdown.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(engine.pending.contains(&url));
+        assert!(!engine.visited.contains(&url));
+    }
+
+    #[tokio::test]
+    async fn test_tiny_crawl_finishes_without_idle_wait() {
+        let base = Routes::tree(1).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            max_depth: 32,
+            max_workers: 4,
+            rate_limit: 100_000.0,
+            max_per_host: 0,
+            ..test_config(format!("{}/0", base))
+        });
+
+        let started = std::time::Instant::now();
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 1);
+        // Done as soon as the only page is, not after a polling grace period
+        assert!(started.elapsed() < Duration::from_secs(1), "took {:?}", started.elapsed());
+        assert_eq!(engine.active_jobs.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_huge_crawl_visits_every_page_once() {
+        const PAGES: usize = 1000;
+        let base = Routes::tree(PAGES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            max_depth: 32,
+            max_workers: 32,
+            rate_limit: 100_000.0,
+            max_per_host: 0,
+            ..test_config(format!("{}/0", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        let urls: HashSet<&str> = results.results.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(results.results.len(), PAGES);
+        assert_eq!(urls.len(), PAGES);
+        assert_eq!(results.stats.pages_crawled, PAGES);
+        assert_eq!(results.stats.errors, 0);
+        assert_eq!(engine.active_jobs.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert!(engine.pending.is_empty() && engine.in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_worker_still_finishes_its_job() {
+        let engine = test_engine(test_config("https://example.com"));
+        engine.enqueue("https://example.com/boom".to_string(), 0, 0, None, 0.0);
+
+        let worker = {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                let job = engine.frontier.next().await.unwrap();
+                let _job = TakenJob { engine: &engine, job };
+                panic!("worker died mid-job");
+            })
+        };
+
+        assert!(worker.await.is_err());
+        assert_eq!(engine.frontier.running(), 0);
+        assert_eq!(engine.active_jobs.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert!(engine.shutdown.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    fn job_for(url: String) -> CrawlJob {
+        CrawlJob { url, depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None }
+    }
+
+    /// The page failure that crawling `job` ends in
+    async fn page_failure(engine: &CrawlEngine, job: &CrawlJob) -> PageError {
+        match engine.crawl_page(job).await {
+            Err(CrawlFailure::Page(failure)) => failure,
+            Err(CrawlFailure::BudgetExhausted) => panic!("byte budget ran out crawling {}", job.url),
+            Ok(page) => panic!("{} was crawled", page.url),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_chain_is_recorded() {
+        static ROUTES: &[(&str, &str)] = &[
+            ("/old", "HTTP/1.1 301 Moved Permanently\r\nlocation: /older\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"),
+            ("/older", "HTTP/1.1 302 Found\r\nlocation: new#section\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"),
+            ("/new", "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: 43\r\nconnection: close\r\n\r\n<html><body><a href=\"x\">X</a></body></html>"),
+        ];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(test_config("https://example.com"));
+
+        let page = engine.crawl_page(&job_for(format!("{}/old", base))).await.unwrap();
+
+        assert_eq!(page.status_code, 200);
+        assert_eq!(page.final_url, Some(format!("{}/new", base)));
+        assert_eq!(
+            page.redirects,
+            vec![
+                RedirectHop { url: format!("{}/old", base), status: 301, location: "/older".to_string() },
+                RedirectHop { url: format!("{}/older", base), status: 302, location: "new#section".to_string() },
+            ]
+        );
+        assert_eq!(page.redirect_issue, None);
+        // Links resolve against the final URL
+        assert_eq!(page.links[0].href, format!("{}/x", base));
+        assert!(engine.visited.contains(&format!("{}/new", base)));
+
+        // A second chain ending at the same page is not crawled again
+        let again = engine.crawl_page(&job_for(format!("{}/older", base))).await.unwrap();
+        assert_eq!(again.final_url, Some(format!("{}/new", base)));
+        assert!(again.links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_fails() {
+        static ROUTES: &[(&str, &str)] = &[
+            ("/a", "HTTP/1.1 302 Found\r\nlocation: /b\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"),
+            ("/b", "HTTP/1.1 302 Found\r\nlocation: /a\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"),
+        ];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(test_config("https://example.com"));
+        let job = job_for(format!("{}/a", base));
+
+        let failure = page_failure(&engine, &job).await;
+        assert_eq!(failure.kind, ErrorKind::Redirect);
+        assert!(failure.message.starts_with("Redirect loop: "));
+
+        let page = failure.into_result(&job);
+        assert_eq!(page.redirect_issue, Some(RedirectIssue::Loop));
+        assert_eq!(page.redirects.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_out_of_scope_is_not_followed() {
+        static ROUTES: &[(&str, &str)] = &[
+            ("/away", "HTTP/1.1 301 Moved Permanently\r\nlocation: https://elsewhere.invalid/\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"),
+        ];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            allowed_domain: Some("example.com".to_string()),
+            ..test_config("https://example.com")
+        });
+
+        let page = engine.crawl_page(&job_for(format!("{}/away", base))).await.unwrap();
+
+        assert_eq!(page.status_code, 301);
+        assert_eq!(page.final_url.as_deref(), Some("https://elsewhere.invalid/"));
+        assert_eq!(page.redirect_issue, Some(RedirectIssue::OutOfScope));
+    }
+
+    #[tokio::test]
+    async fn test_http_error_status_becomes_failed_result() {
+        let engine = test_engine(test_config("https://example.com"));
+        let url = format!("{}/missing", Routes::default().serve().await);
+        let job = CrawlJob {
+            url: url.clone(),
+            depth: 2,
+            referrer: Some("https://example.com/".to_string()),
+            seed: 0,
+            score: 0.0,
+            fetch_url: None,
+        };
+
+        let failure = page_failure(&engine, &job).await;
+        assert_eq!(failure.kind, ErrorKind::HttpStatus);
+        assert_eq!(failure.message, "HTTP 404 Not Found");
+
+        let result = failure.into_result(&job);
+        assert_eq!(result.url, url);
+        assert_eq!(result.status_code, 404);
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.referrer.as_deref(), Some("https://example.com/"));
+        assert_eq!(result.error_kind, Some(ErrorKind::HttpStatus));
+    }
+
+    #[tokio::test]
+    async fn test_canonical_and_robots_directives() {
+        // crawl_page itself never fetches robots.txt
+        let engine = test_engine(CrawlerConfig {
+            respect_robots_txt: true,
+            convert_markdown: true,
+            ..test_config("https://example.com")
+        });
+        static ROUTES: &[(&str, &str)] = &[(
+            "/print",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\
+             x-robots-tag: otherbot: noindex\r\nx-robots-tag: rcrawler: nofollow\r\n\
+             content-length: 153\r\nconnection: close\r\n\r\n\
+             <html><head><link rel=\"canonical\" href=\"/guide/#top\">\
+             <meta name=\"robots\" content=\"noindex\"></head>\
+             <body><p>Print view</p><a href=\"/a\">A</a></body></html>",
+        )];
+        let base = Routes::canned(ROUTES).serve().await;
+        let job = CrawlJob { url: format!("{}/print", base), depth: 0, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+
+        let result = engine.crawl_page(&job).await.unwrap();
+
+        let canonical = format!("{}/guide", base);
+        assert_eq!(result.canonical_url.as_deref(), Some(canonical.as_str()));
+        assert_eq!(result.canonical_elsewhere(), Some(canonical.as_str()));
+        assert_eq!(result.robots, RobotsDirectives { noindex: true, nofollow: true });
+        // noindex pages get no content
+        assert!(result.markdown.is_none());
+        assert!(result.content_hash.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_body_over_limit_is_aborted() {
+        static ROUTES: &[(&str, &str)] = &[
+            (
+                "/declared",
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: 40\r\nconnection: close\r\n\r\n\
+                 <html><body>0123456789012</body></html>",
+            ),
+            (
+                "/streamed",
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+                 <html><body>0123456789012</body></html>",
+            ),
+            ("/short", "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n<p>short</p>"),
+        ];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig { max_body_bytes: 16, ..test_config("https://example.com") });
+
+        // Declared length over the limit
+        let failure = page_failure(&engine, &job_for(format!("{}/declared", base))).await;
+        assert_eq!(failure.kind, ErrorKind::TooLarge);
+        assert_eq!(failure.status_code, 200);
+
+        // No length: caught while streaming
+        let failure = page_failure(&engine, &job_for(format!("{}/streamed", base))).await;
+        assert_eq!(failure.kind, ErrorKind::TooLarge);
+
+        assert!(engine.crawl_page(&job_for(format!("{}/short", base))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_page_budget_stops_crawl() {
+        const PAGE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/a\">a</a><a href=\"/b\">b</a><a href=\"/c\">c</a></body></html>";
+        static ROUTES: &[(&str, &str)] = &[("/", PAGE), ("/a", PAGE), ("/b", PAGE), ("/c", PAGE)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_pages: 2, ..Default::default() },
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.stats.stopped_by, Some(BudgetLimit::Pages));
+        assert!(!results.stats.interrupted);
+        assert!(results.stats.bytes_downloaded > 0);
+        // What was not fetched stays in the frontier for a resumed crawl
+        assert_eq!(engine.pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_crawl_keeps_budget_used_so_far() {
+        const PAGE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/a\">a</a><a href=\"/b\">b</a><a href=\"/c\">c</a></body></html>";
+        static ROUTES: &[(&str, &str)] = &[("/", PAGE), ("/a", PAGE), ("/b", PAGE), ("/c", PAGE)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = CrawlerConfig {
+            output_dir: output_dir.path().to_path_buf(),
+            resume: true,
+            checkpoint_interval: 3600,
+            budget: CrawlBudget { max_pages: 2, ..Default::default() },
+            ..test_config(format!("{}/", base))
+        };
+
+        let first = test_engine(config.clone()).crawl().await.unwrap();
+        assert_eq!(first.stats.stopped_by, Some(BudgetLimit::Pages));
+
+        // The first run used up the page budget, so nothing more is fetched
+        let resumed = test_engine(config);
+        let results = resumed.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.stats.stopped_by, Some(BudgetLimit::Pages));
+        assert_eq!(results.stats.bytes_downloaded, first.stats.bytes_downloaded);
+        assert_eq!(resumed.budget.pages(), 2);
+        assert_eq!(resumed.pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_host_page_budget_skips_host() {
+        const PAGE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/a\">a</a><a href=\"/b\">b</a></body></html>";
+        static ROUTES: &[(&str, &str)] = &[("/", PAGE), ("/a", PAGE), ("/b", PAGE)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_pages_per_host: 2, ..Default::default() },
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.stats.stopped_by, None);
+        assert_eq!(results.stats.host_budget_skips, 1);
+    }
+
+    #[tokio::test]
+    async fn test_host_page_budget_refuses_each_url_once() {
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_pages_per_host: 1, ..Default::default() },
+            ..test_config("https://example.com")
+        });
+        engine.budget.start_page("example.com").unwrap();
+
+        let url = "https://example.com/more".to_string();
+        engine.enqueue(url.clone(), 1, 0, None, 0.0);
+        let job = CrawlJob { url: url.clone(), depth: 1, referrer: None, seed: 0, score: 0.0, fetch_url: None };
+        engine.process_job(&job).await.unwrap();
+
+        // Links found later to the refused URL are not queued again
+        engine.enqueue(url.clone(), 1, 0, None, 0.0);
+        assert!(!engine.pending.contains(&url));
+        engine.process_job(&job).await.unwrap();
+        assert_eq!(engine.stats.lock().host_budget_skips, 1);
+    }
+
+    #[tokio::test]
+    async fn test_patterns_match_canonical_links() {
+        static ROUTES: &[(&str, &str)] = &[
+            (
+                "/",
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+                 <html><body><a href=\"/photo.jpg?utm_source=feed\">P</a><a href=\"/page#top\">N</a></body></html>",
+            ),
+            ("/page", "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n<html></html>"),
+            ("/photo.jpg", "HTTP/1.1 200 OK\r\ncontent-type: image/jpeg\r\nconnection: close\r\n\r\n"),
+        ];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            exclude_patterns: vec![r"\.jpg$".to_string()],
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        let mut crawled: Vec<String> = results.results.iter().map(|page| page.url.clone()).collect();
+        crawled.sort();
+        assert_eq!(crawled, vec![format!("{}/", base), format!("{}/page", base)]);
+    }
+
+    #[tokio::test]
+    async fn test_byte_budget_requeues_cut_off_page() {
+        static ROUTES: &[(&str, &str)] = &[(
+            "/page",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body>0123456789012345678901234567890123456789</body></html>",
+        )];
+        let url = format!("{}/page", Routes::canned(ROUTES).serve().await);
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_bytes: 32, ..Default::default() },
+            ..test_config(url.clone())
+        });
+        engine.visited.insert(queued(&url, 0));
+
+        engine.visit(&job_for(url.clone())).await.unwrap();
+
+        assert!(engine.results.lock().is_empty());
+        assert!(!engine.visited.contains(&url));
+        assert!(engine.pending.contains(&url));
+        assert_eq!(engine.budget.exhausted(), Some(BudgetLimit::Bytes));
+        assert!(engine.shutdown.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_time_budget_cuts_off_slow_requests() {
+        // Accepts connections but never answers
+        let url = format!("{}/", Routes::default().route("/", |_| None).serve().await);
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_duration_secs: 1, ..Default::default() },
+            ..test_config(url.clone())
+        });
+
+        let started = std::time::Instant::now();
+        let results = engine.crawl().await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(1) + BUDGET_GRACE + Duration::from_secs(2));
+        assert_eq!(results.stats.stopped_by, Some(BudgetLimit::Duration));
+        assert!(results.results.is_empty());
+        assert!(engine.in_flight.contains_key(&url));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_time_budget_lets_running_requests_finish() {
+        // Answers after the budget runs out, but within the grace period
+        let url = format!(
+            "{}/",
+            Routes::default()
+                .route("/", |_| {
+                    std::thread::sleep(Duration::from_millis(1500));
+                    Some(NOT_FOUND.to_string())
+                })
+                .serve()
+                .await
+        );
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_duration_secs: 1, ..Default::default() },
+            ..test_config(url.clone())
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.stats.stopped_by, Some(BudgetLimit::Duration));
+        assert_eq!(results.results.len(), 1);
+        assert!(engine.in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_time_budget_starts_with_the_crawl() {
+        static ROUTES: &[(&str, &str)] = &[("/", NOT_FOUND)];
+        let url = format!("{}/", Routes::canned(ROUTES).serve().await);
+        let engine = test_engine(CrawlerConfig {
+            budget: CrawlBudget { max_duration_secs: 1, ..Default::default() },
+            ..test_config(url)
+        });
+
+        // Time before the crawl starts does not count against the budget
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.stats.stopped_by, None);
+        assert_eq!(results.results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_priority_strategy_crawls_relevant_pages_first() {
+        const HOME: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/blog/2019/old\">Old news</a><a href=\"/about\">About</a>\
+             <a href=\"/plans\">Pricing</a></body></html>";
+        const PAGE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n<p>page</p>";
+        static ROUTES: &[(&str, &str)] = &[("/", HOME), ("/blog/2019/old", PAGE), ("/about", PAGE), ("/plans", PAGE)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            max_workers: 1,
+            rate_limit: 1000.0,
+            strategy: CrawlStrategy::Priority,
+            priority: crate::crawler::priority::PriorityConfig {
+                keywords: vec!["pricing".to_string()],
+                ..Default::default()
+            },
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        let order: Vec<&str> = results
+            .results
+            .iter()
+            .map(|page| page.url.trim_start_matches(base.as_str()))
+            .collect();
+        // Keyword in the anchor text first, the deepest path last
+        assert_eq!(order, vec!["/", "/plans", "/about", "/blog/2019/old"]);
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_crawl() {
+        const PAGE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/\">home</a><a href=\"/a\">a</a><a href=\"/b\">b</a><a href=\"/c\">c</a></body></html>";
+        static ROUTES: &[(&str, &str)] = &[("/", PAGE), ("/a", PAGE), ("/b", PAGE), ("/c", PAGE)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let engine = test_engine(CrawlerConfig {
+            output_dir: output_dir.path().to_path_buf(),
+            // Every job but one waits on disk
+            store: StoreConfig { enabled: true, max_queued_in_memory: 1, ..Default::default() },
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 4);
+        assert_eq!(engine.visited.len(), 4);
+        assert!(engine.pending.is_empty());
+        assert!(engine.frontier.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency_backs_off_when_throttled() {
+        const HOME: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/a\">a</a><a href=\"/b\">b</a><a href=\"/c\">c</a><a href=\"/d\">d</a></body></html>";
+        const THROTTLED: &str = "HTTP/1.1 429 Too Many Requests\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        static ROUTES: &[(&str, &str)] =
+            &[("/", HOME), ("/a", THROTTLED), ("/b", THROTTLED), ("/c", THROTTLED), ("/d", THROTTLED)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig {
+            max_workers: 8,
+            rate_limit: 1000.0,
+            max_per_host: 0,
+            retry: crate::crawler::retry::RetryPolicy::none(),
+            adaptive: crate::crawler::concurrency::AdaptiveConfig {
+                enabled: true,
+                min_workers: 2,
+                max_workers: 16,
+                window: 10,
+                ..Default::default()
+            },
+            ..test_config(format!("{}/", base))
+        });
+        assert_eq!(engine.frontier.running(), 0);
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 5);
+        let concurrency = results.stats.concurrency.unwrap();
+        assert_eq!(concurrency.initial, 8);
+        // The throttled requests were all sent before the back-off, so only
+        // the first of them counts
+        assert_eq!(concurrency.decreases, 1);
+        assert_eq!(concurrency.current, 2);
+        assert_eq!(
+            concurrency.decisions[0].reason,
+            crate::crawler::concurrency::AdjustReason::Overloaded
+        );
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency_stays_within_per_host_cap() {
+        const THROTTLED: &str = "HTTP/1.1 429 Too Many Requests\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let links: String = (0..12).map(|page| format!("<a href=\"/{}\">{}</a>", page, page)).collect();
+        let home = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n<html><body>{}</body></html>",
+            links
+        );
+        let routes = (0..12).fold(Routes::default().route("/", move |_| Some(home.clone())), |routes, page| {
+            routes.route(&format!("/{}", page), |_| Some(THROTTLED.to_string()))
+        });
+        let base = routes.serve().await;
+        let profile = crate::config::profiles::get_profile("fast").unwrap();
+        let engine = test_engine(CrawlerConfig {
+            max_workers: profile.max_workers,
+            rate_limit: 1000.0,
+            max_per_host: profile.max_per_host,
+            retry: crate::crawler::retry::RetryPolicy::none(),
+            adaptive: profile.adaptive.clone(),
+            ..test_config(format!("{}/", base))
+        });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 13);
+        let concurrency = results.stats.concurrency.unwrap();
+        // One host takes at most `max_per_host` requests, not the 50 workers
+        assert_eq!(concurrency.initial, profile.max_per_host);
+        assert_eq!(concurrency.highest, profile.max_per_host);
+        assert_eq!(concurrency.decreases, 1);
+        assert_eq!(concurrency.current, profile.adaptive.min_workers);
+    }
+
+    #[tokio::test]
+    async fn test_unwanted_content_type_is_not_downloaded() {
+        // The body would be over the limit if it were read
+        static ROUTES: &[(&str, &str)] = &[(
+            "/doc.pdf",
+            "HTTP/1.1 200 OK\r\ncontent-type: application/pdf\r\ncontent-length: 40\r\nconnection: close\r\n\r\n\
+             %PDF-1.4 0123456789012345678901234567890",
+        )];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig { max_body_bytes: 16, ..test_config("https://example.com") });
+
+        let page = engine.crawl_page(&job_for(format!("{}/doc.pdf", base))).await.unwrap();
+
+        assert_eq!(page.status_code, 200);
+        assert_eq!(page.content_type, "application/pdf");
+        assert!(page.links.is_empty());
+        assert!(page.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_recrawl_uses_validators() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // The page, or a 304 for requests sending its ETag
+        let conditional = Arc::new(AtomicBool::new(false));
+        let seen = Arc::clone(&conditional);
+        let routes = Routes::default().route("/doc", move |request| {
+            if request.to_lowercase().contains("if-none-match: \"v1\"") {
+                seen.store(true, Ordering::SeqCst);
+                return Some("HTTP/1.1 304 Not Modified\r\netag: \"v1\"\r\nconnection: close\r\n\r\n".to_string());
+            }
+            Some(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\netag: \"v1\"\r\ncontent-length: 56\r\n\
+                 connection: close\r\n\r\n<html><body><p>Docs</p><a href=\"/next\">N</a></body></html>"
+                    .to_string(),
+            )
+        });
+        let url = format!("{}/doc", routes.serve().await);
+
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = CrawlerConfig {
+            incremental: true,
+            output_dir: output_dir.path().to_path_buf(),
+            ..test_config(url.clone())
+        };
+
+        let first = test_engine(config.clone());
+        let page = first.crawl_page(&job_for(url.clone())).await.unwrap();
+        assert_eq!(page.change, Some(PageChange::New));
+        assert_eq!(page.etag.as_deref(), Some("\"v1\""));
+        first.http_cache.as_ref().unwrap().save(true).unwrap();
+
+        let second = test_engine(config);
+        let replayed = second.crawl_page(&job_for(url.clone())).await.unwrap();
+        assert!(conditional.load(Ordering::SeqCst), "second request was not conditional");
+        assert_eq!(replayed.change, Some(PageChange::Unchanged));
+        assert_eq!(replayed.status_code, 200);
+        assert_eq!(replayed.content_hash, page.content_hash);
+        // Links of the unchanged page are still there to be followed
+        assert_eq!(replayed.links, page.links);
+    }
+
+    #[tokio::test]
+    async fn test_page_the_cache_cannot_replay_is_fetched_in_full() {
+        // Answers 304 to any conditional request, whatever the validators
+        let routes = Routes::default().route("/doc", |request| {
+            if request.to_lowercase().contains("if-none-match") {
+                return Some("HTTP/1.1 304 Not Modified\r\netag: \"v1\"\r\nconnection: close\r\n\r\n".to_string());
+            }
+            Some(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\netag: \"v1\"\r\ncontent-length: 37\r\n\
+                 connection: close\r\n\r\n<html><body><p>Docs</p></body></html>"
+                    .to_string(),
+            )
+        });
+        let url = format!("{}/doc", routes.serve().await);
+
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = CrawlerConfig {
+            incremental: true,
+            output_dir: output_dir.path().to_path_buf(),
+            ..test_config(url.clone())
+        };
+
+        let first = test_engine(config.clone());
+        first.crawl_page(&job_for(url.clone())).await.unwrap();
+        first.http_cache.as_ref().unwrap().save(true).unwrap();
+
+        // The cached page has no Markdown, so it cannot stand in for this crawl's
+        let second = test_engine(CrawlerConfig { convert_markdown: true, ..config });
+        let page = second.crawl_page(&job_for(url.clone())).await.unwrap();
+        assert_eq!(page.status_code, 200);
+        assert!(page.error.is_none());
+        assert!(page.markdown.as_deref().is_some_and(|markdown| markdown.contains("Docs")));
+    }
+
+    #[tokio::test]
+    async fn test_connection_refused_is_classified() {
+        let engine = test_engine(CrawlerConfig {
+            retry: crate::crawler::retry::RetryPolicy::none(),
+            ..test_config("https://example.com")
+        });
+
+        // Nothing listens on a port we just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let failure = engine.fetch(&url, &HeaderMap::new()).await.unwrap_err();
+        assert_eq!(failure.kind, ErrorKind::Connection);
+        assert_eq!(failure.attempts, 1);
+        assert_eq!(failure.status_code, 0);
+    }
+
+    #[test]
+    fn test_record_referrer_dedups_and_caps() {
+        let engine = test_engine(test_config("https://example.com"));
+        let target = "https://example.com/missing";
+
+        engine.record_referrer(target, "https://example.com/a");
+        engine.record_referrer(target, "https://example.com/a");
+        engine.record_referrer(target, "https://example.com/b");
+        assert_eq!(
+            engine.referrers.get(target),
+            vec!["https://example.com/a".to_string(), "https://example.com/b".to_string()]
+        );
+
+        for i in 0..100 {
+            engine.record_referrer(target, &format!("https://example.com/p{}", i));
         }
+        assert_eq!(engine.referrers.get(target).len(), MAX_REFERRERS);
+    }
+
+    #[test]
+    fn test_enqueue_dedups_url_variants() {
+        let engine = test_engine(test_config("https://example.com"));
+        engine.visited.insert(queued("https://example.com/done", 0));
+
+        engine.enqueue("https://example.com/page".to_string(), 2, 0, None, 0.0);
+        engine.enqueue("https://example.com/page/#top".to_string(), 1, 0, None, 0.0);
+        engine.enqueue("https://example.com/page?utm_source=feed".to_string(), 3, 0, None, 0.0);
+        engine.enqueue("https://example.com/done/".to_string(), 1, 0, None, 0.0);
+
+        assert_eq!(engine.frontier.len(), 1);
+        assert_eq!(engine.pending.get("https://example.com/page").unwrap().depth, 1);
+    }
+
+    #[tokio::test]
+    async fn test_links_are_fetched_as_written() {
+        const HOME: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body><a href=\"/docs/\">docs</a><a href=\"/docs/#intro\">intro</a></body></html>";
+        const DOCS: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><body>docs</body></html>";
+        // Only the trailing-slash form exists, as on many static hosts
+        static ROUTES: &[(&str, &str)] = &[("/", HOME), ("/docs/", DOCS)];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(test_config(format!("{}/", base)));
+
+        let results = engine.crawl().await.unwrap();
+
+        let docs = format!("{}/docs", base);
+        let page = results.results.iter().find(|page| page.url == docs).unwrap();
+        assert_eq!(page.status_code, 200);
+        assert!(page.redirects.is_empty());
+        assert_eq!(results.results.len(), 2);
+
+        let home = results.results.iter().find(|page| page.url != docs).unwrap();
+        assert!(home.links.iter().all(|link| link.href == docs));
+    }
+
+    #[test]
+    fn test_restore_checkpoint_requires_resume() {
+        let engine = test_engine(test_config("https://example.com"));
+        assert!(!engine.restore_checkpoint());
+    }
+
+    #[test]
+    fn test_process_content_without_markdown() {
+        let engine = test_engine(test_config("https://example.com"));
+        let html = "<html><body><p>Hello crawler world</p></body></html>";
+
+        let content = engine.process_content(html, "https://example.com");
+
+        assert!(content.markdown.is_none());
+        assert!(content.word_count.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_crawled_pages_go_through_services() {
+        static ROUTES: &[(&str, &str)] = &[(
+            "/",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\nconnection: close\r\n\r\n\
+             <html><head><title>Home</title></head><body><p>Hello crawler world</p></body></html>",
+        )];
+        let base = Routes::canned(ROUTES).serve().await;
+        let engine = test_engine(CrawlerConfig { convert_markdown: true, ..test_config(format!("{}/", base)) });
+
+        let results = engine.crawl().await.unwrap();
+
+        assert_eq!(results.results.len(), 1);
+        let page = &results.results[0];
+        assert!(page.markdown.as_deref().unwrap().contains("Hello crawler world"));
+        assert!(page.word_count.unwrap() >= 3);
+        assert!(page.filter_stats.is_some());
     }
 }
@@ -0,0 +1,722 @@
+//! Crawl frontier with per-host politeness queues
+//!
+//! Jobs are queued per host and handed out round-robin to whichever host
+//! is ready: under its concurrency cap and with a rate limit token
+//! available, while the crawl-wide cap on running jobs allows. Workers only
+//! sleep when no host is ready. Within a host, a
+//! `Frontier` strategy decides which job goes next: breadth-first,
+//! depth-first, or best-first by score.
+//!
+//! With a disk store, jobs beyond an in-memory limit wait in an on-disk
+//! tree, keyed so they come back out in roughly the strategy's order.
+
+use crate::crawler::checkpoint::PendingUrl;
+use crate::crawler::rate_limiter::RateLimiter;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// A URL waiting to be crawled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlJob {
+	pub url: String,
+	pub depth: usize,
+	/// Page the URL was discovered on
+	pub referrer: Option<String>,
+	/// Index of the seed the URL was reached from
+	pub seed: usize,
+	/// Priority score (higher goes first; only used by the priority strategy)
+	pub score: f64,
+	/// URL as discovered, when it differs from its canonical form in `url`
+	pub fetch_url: Option<String>,
+}
+
+impl CrawlJob {
+	/// The job as recorded in the pending set and checkpoints
+	pub fn to_pending(&self) -> PendingUrl {
+		PendingUrl {
+			url: self.url.clone(),
+			depth: self.depth,
+			seed: self.seed,
+			score: self.score,
+			fetch_url: self.fetch_url.clone(),
+		}
+	}
+
+	/// URL to request: the link as it was found, which the server may
+	/// expect verbatim even when it shares a canonical form with others
+	pub fn request_url(&self) -> &str {
+		self.fetch_url.as_deref().unwrap_or(&self.url)
+	}
+}
+
+/// Order in which the queued jobs of one host are handed out
+pub trait Frontier: Send {
+	fn push(&mut self, job: CrawlJob);
+
+	fn pop(&mut self) -> Option<CrawlJob>;
+
+	/// Raises the score of a queued job; strategies that ignore scores do nothing
+	fn reprioritize(&mut self, _url: &str, _score: f64) {}
+
+	fn len(&self) -> usize;
+
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// How the frontier orders jobs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlStrategy {
+	/// Shallowest first, in discovery order
+	#[default]
+	Bfs,
+	/// Most recently discovered first
+	Dfs,
+	/// Highest score first
+	Priority,
+}
+
+impl CrawlStrategy {
+	/// Empty job queue ordered by this strategy
+	pub fn queue(self) -> Box<dyn Frontier> {
+		match self {
+			Self::Bfs => Box::<BfsFrontier>::default(),
+			Self::Dfs => Box::<DfsFrontier>::default(),
+			Self::Priority => Box::<PriorityFrontier>::default(),
+		}
+	}
+
+	/// Key of a job spilled to disk; keys sort in the order jobs go out
+	fn spill_key(self, job: &CrawlJob, seq: u64) -> Vec<u8> {
+		let rank = match self {
+			Self::Bfs => job.depth as u64,
+			Self::Dfs => u64::MAX - seq,
+			// Score bits made to sort like the score, then inverted for highest first
+			Self::Priority => {
+				let bits = job.score.to_bits();
+				!(if bits >> 63 == 1 { !bits } else { bits | 1 << 63 })
+			}
+		};
+		[rank.to_be_bytes(), seq.to_be_bytes()].concat()
+	}
+}
+
+impl FromStr for CrawlStrategy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"bfs" => Ok(Self::Bfs),
+			"dfs" => Ok(Self::Dfs),
+			"priority" | "best-first" => Ok(Self::Priority),
+			_ => Err(format!("Unknown crawl strategy: {}", s)),
+		}
+	}
+}
+
+/// Breadth-first: lowest depth first, then first queued. Jobs finish out of
+/// order with many workers, so a plain FIFO would not stay level by level.
+#[derive(Default)]
+pub struct BfsFrontier {
+	jobs: BTreeMap<(usize, u64), CrawlJob>,
+	next_seq: u64,
+}
+
+impl Frontier for BfsFrontier {
+	fn push(&mut self, job: CrawlJob) {
+		self.jobs.insert((job.depth, self.next_seq), job);
+		self.next_seq += 1;
+	}
+
+	fn pop(&mut self) -> Option<CrawlJob> {
+		self.jobs.pop_first().map(|(_, job)| job)
+	}
+
+	fn len(&self) -> usize {
+		self.jobs.len()
+	}
+}
+
+/// Depth-first: the last job queued goes first
+#[derive(Default)]
+pub struct DfsFrontier {
+	jobs: Vec<CrawlJob>,
+}
+
+impl Frontier for DfsFrontier {
+	fn push(&mut self, job: CrawlJob) {
+		self.jobs.push(job);
+	}
+
+	fn pop(&mut self) -> Option<CrawlJob> {
+		self.jobs.pop()
+	}
+
+	fn len(&self) -> usize {
+		self.jobs.len()
+	}
+}
+
+/// Best-first: highest score first, ties in the order queued
+///
+/// Reprioritized jobs get a new heap entry; the old one is skipped when it
+/// comes up.
+#[derive(Default)]
+pub struct PriorityFrontier {
+	heap: BinaryHeap<Ranked>,
+	/// Queued jobs by URL, with the sequence number of their live heap entry
+	jobs: HashMap<String, (CrawlJob, u64)>,
+	next_seq: u64,
+}
+
+struct Ranked {
+	score: f64,
+	seq: u64,
+	url: String,
+}
+
+impl Ord for Ranked {
+	fn cmp(&self, other: &Self) -> CmpOrdering {
+		self.score
+			.total_cmp(&other.score)
+			.then_with(|| other.seq.cmp(&self.seq))
+	}
+}
+
+impl PartialOrd for Ranked {
+	fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl PartialEq for Ranked {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == CmpOrdering::Equal
+	}
+}
+
+impl Eq for Ranked {}
+
+impl PriorityFrontier {
+	fn rank(&mut self, url: String, score: f64) -> u64 {
+		let seq = self.next_seq;
+		self.next_seq += 1;
+		self.heap.push(Ranked { score, seq, url });
+		seq
+	}
+}
+
+impl Frontier for PriorityFrontier {
+	fn push(&mut self, job: CrawlJob) {
+		let seq = self.rank(job.url.clone(), job.score);
+		self.jobs.insert(job.url.clone(), (job, seq));
+	}
+
+	fn pop(&mut self) -> Option<CrawlJob> {
+		while let Some(ranked) = self.heap.pop() {
+			if self.jobs.get(&ranked.url).is_some_and(|(_, seq)| *seq == ranked.seq) {
+				return self.jobs.remove(&ranked.url).map(|(job, _)| job);
+			}
+		}
+		None
+	}
+
+	fn reprioritize(&mut self, url: &str, score: f64) {
+		match self.jobs.get(url) {
+			Some((job, _)) if score > job.score => {}
+			_ => return,
+		}
+		let seq = self.rank(url.to_string(), score);
+		if let Some((job, live)) = self.jobs.get_mut(url) {
+			job.score = score;
+			*live = seq;
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.jobs.len()
+	}
+}
+
+/// Per-host job queues shared by all workers
+pub struct HostQueues {
+	state: Mutex<FrontierState>,
+	strategy: CrawlStrategy,
+	spill: Option<Spill>,
+	rate_limiter: RateLimiter,
+	max_per_host: usize,
+	/// Cap on jobs running across all hosts (0 = none)
+	max_active: AtomicUsize,
+	notify: Notify,
+	closed: AtomicBool,
+}
+
+#[derive(Default)]
+struct FrontierState {
+	queues: HashMap<String, Box<dyn Frontier>>,
+	/// Hosts with queued jobs, in round-robin order
+	hosts: VecDeque<String>,
+	/// Requests currently running per host
+	active: HashMap<String, usize>,
+	/// Requests currently running across all hosts
+	running: usize,
+	/// Jobs in `queues`
+	queued: usize,
+	/// Jobs waiting on disk
+	spilled: usize,
+	/// Sequence number of the next spilled job
+	next_spill: u64,
+}
+
+/// Told how many queued jobs were lost from disk; returns whether nothing
+/// is left to run, which closes the frontier
+type OnLost = Box<dyn Fn(usize) -> bool + Send + Sync>;
+
+/// Where jobs beyond the in-memory limit go
+struct Spill {
+	tree: sled::Tree,
+	max_in_memory: usize,
+	on_lost: OnLost,
+}
+
+impl HostQueues {
+	/// Creates a frontier; `max_per_host` of 0 means no concurrency cap
+	pub fn new(strategy: CrawlStrategy, rate_limiter: RateLimiter, max_per_host: usize) -> Self {
+		Self {
+			state: Mutex::new(FrontierState::default()),
+			strategy,
+			spill: None,
+			rate_limiter,
+			max_per_host,
+			max_active: AtomicUsize::new(0),
+			notify: Notify::new(),
+			closed: AtomicBool::new(false),
+		}
+	}
+
+	/// Keeps at most `max_in_memory` jobs in memory, spilling the rest into
+	/// `tree` (which must start empty). Spilled jobs that cannot be read back
+	/// are reported to `on_lost`, since they will never be handed out.
+	pub fn with_spill(
+		mut self,
+		tree: sled::Tree,
+		max_in_memory: usize,
+		on_lost: impl Fn(usize) -> bool + Send + Sync + 'static,
+	) -> Self {
+		self.spill = Some(Spill {
+			tree,
+			max_in_memory: max_in_memory.max(1),
+			on_lost: Box::new(on_lost),
+		});
+		self
+	}
+
+	/// Queues a job with the other jobs for its host
+	pub fn push(&self, job: CrawlJob) {
+		{
+			let mut state = self.state.lock();
+			match &self.spill {
+				Some(spill) if state.queued >= spill.max_in_memory => self.spill_job(&mut state, spill, job),
+				_ => self.queue_job(&mut state, job),
+			}
+		}
+		self.notify.notify_one();
+	}
+
+	fn queue_job(&self, state: &mut FrontierState, job: CrawlJob) {
+		let host = host_key(&job.url);
+		let queue = state.queues.entry(host.clone()).or_insert_with(|| self.strategy.queue());
+		queue.push(job);
+		if queue.len() == 1 {
+			state.hosts.push_back(host);
+		}
+		state.queued += 1;
+	}
+
+	fn spill_job(&self, state: &mut FrontierState, spill: &Spill, job: CrawlJob) {
+		let key = self.strategy.spill_key(&job, state.next_spill);
+		let value = serde_json::to_vec(&job).expect("crawl job serializes");
+		match spill.tree.insert(key, value) {
+			Ok(_) => {
+				state.next_spill += 1;
+				state.spilled += 1;
+			}
+			Err(e) => {
+				eprintln!("Warning: Failed to spill crawl job to disk: {}", e);
+				self.queue_job(state, job);
+			}
+		}
+	}
+
+	/// Moves spilled jobs back into memory once it is half empty
+	fn refill(&self, state: &mut FrontierState) {
+		let spill = match &self.spill {
+			Some(spill) if state.spilled > 0 && state.queued <= spill.max_in_memory / 2 => spill,
+			_ => return,
+		};
+
+		let mut lost = 0;
+		while state.queued < spill.max_in_memory {
+			match spill.tree.pop_min() {
+				Ok(Some((_, value))) => {
+					state.spilled -= 1;
+					match serde_json::from_slice(&value) {
+						Ok(job) => self.queue_job(state, job),
+						Err(e) => {
+							eprintln!("Warning: Dropping unreadable spilled crawl job: {}", e);
+							lost += 1;
+						}
+					}
+				}
+				Ok(None) => {
+					lost += state.spilled;
+					state.spilled = 0;
+					break;
+				}
+				Err(e) => {
+					eprintln!("Warning: Failed to read spilled crawl jobs: {}", e);
+					break;
+				}
+			}
+		}
+
+		if lost > 0 && (spill.on_lost)(lost) {
+			self.close();
+		}
+	}
+
+	/// Raises the score of a job queued in memory (priority strategy only;
+	/// spilled jobs keep the score they were spilled with)
+	pub fn reprioritize(&self, url: &str, score: f64) {
+		if let Some(queue) = self.state.lock().queues.get_mut(&host_key(url)) {
+			queue.reprioritize(url, score);
+		}
+	}
+
+	/// Waits for the next job from a ready host, or `None` once closed
+	///
+	/// The job's host counts as active until `complete` is called.
+	pub async fn next(&self) -> Option<CrawlJob> {
+		loop {
+			if self.closed.load(Ordering::SeqCst) {
+				return None;
+			}
+
+			// Register for wakeups before looking, so a push in between is not missed
+			let notified = self.notify.notified();
+			tokio::pin!(notified);
+			notified.as_mut().enable();
+
+			match self.try_next() {
+				Ok(job) => return Some(job),
+				Err(Some(delay)) => {
+					tokio::select! {
+						_ = notified => {}
+						_ = tokio::time::sleep(delay) => {}
+					}
+				}
+				Err(None) => notified.await,
+			}
+		}
+	}
+
+	/// Releases the host slot taken by a job returned from `next`
+	pub fn complete(&self, job: &CrawlJob) {
+		let host = host_key(&job.url);
+		{
+			let mut state = self.state.lock();
+			state.running -= 1;
+			if let Some(active) = state.active.get_mut(&host) {
+				*active -= 1;
+				if *active == 0 {
+					state.active.remove(&host);
+				}
+			}
+		}
+		self.notify.notify_one();
+	}
+
+	/// Caps the jobs running across all hosts (0 removes the cap); waiting
+	/// workers recheck right away
+	pub fn set_max_active(&self, max_active: usize) {
+		self.max_active.store(max_active, Ordering::SeqCst);
+		self.notify.notify_waiters();
+	}
+
+	/// Number of jobs handed out and not completed yet
+	pub fn running(&self) -> usize {
+		self.state.lock().running
+	}
+
+	/// Most jobs the hosts with queued or running jobs could run at once
+	/// under their cap, or `None` without a cap. Hosts of spilled jobs are
+	/// not counted until the jobs come back into memory.
+	pub fn capacity(&self) -> Option<usize> {
+		if self.max_per_host == 0 {
+			return None;
+		}
+		let state = self.state.lock();
+		let idle = state.hosts.iter().filter(|host| !state.active.contains_key(*host)).count();
+		Some(self.max_per_host * (state.active.len() + idle))
+	}
+
+	/// Stops handing out jobs and wakes every waiting worker
+	pub fn close(&self) {
+		self.closed.store(true, Ordering::SeqCst);
+		self.notify.notify_waiters();
+	}
+
+	/// Number of queued jobs, in memory or on disk
+	pub fn len(&self) -> usize {
+		let state = self.state.lock();
+		state.queued + state.spilled
+	}
+
+	pub fn is_empty(&self) -> bool {
+		let state = self.state.lock();
+		state.queues.is_empty() && state.spilled == 0
+	}
+
+	/// Takes a job from the first ready host. Otherwise returns how long until
+	/// a rate limited host is ready, or `None` if every queued host is at its
+	/// concurrency cap (or nothing is queued).
+	fn try_next(&self) -> Result<CrawlJob, Option<Duration>> {
+		let mut state = self.state.lock();
+		let mut wait: Option<Duration> = None;
+		self.refill(&mut state);
+
+		// Crawl-wide cap reached: wait for a job to complete
+		let max_active = self.max_active.load(Ordering::SeqCst);
+		if max_active > 0 && state.running >= max_active {
+			return Err(None);
+		}
+
+		for _ in 0..state.hosts.len() {
+			let host = match state.hosts.pop_front() {
+				Some(host) => host,
+				None => break,
+			};
+
+			let active = state.active.get(&host).copied().unwrap_or(0);
+			if self.max_per_host > 0 && active >= self.max_per_host {
+				state.hosts.push_back(host);
+				continue;
+			}
+
+			if let Err(delay) = self.rate_limiter.try_acquire(&host) {
+				wait = Some(wait.map_or(delay, |w| w.min(delay)));
+				state.hosts.push_back(host);
+				continue;
+			}
+
+			let queue = state.queues.get_mut(&host).expect("queued host has a queue");
+			let job = queue.pop().expect("queued host has jobs");
+			if queue.is_empty() {
+				state.queues.remove(&host);
+			} else {
+				state.hosts.push_back(host.clone());
+			}
+			*state.active.entry(host).or_insert(0) += 1;
+			state.queued -= 1;
+			state.running += 1;
+
+			return Ok(job);
+		}
+
+		Err(wait)
+	}
+}
+
+/// Politeness key for a URL: its lowercased host
+pub(crate) fn host_key(url: &str) -> String {
+	url::Url::parse(url)
+		.ok()
+		.and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn job(url: &str) -> CrawlJob {
+		CrawlJob { url: url.to_string(), depth: 0, referrer: None, seed: 0, score: 0.0, fetch_url: None }
+	}
+
+	fn drain(mut queue: Box<dyn Frontier>, jobs: Vec<CrawlJob>) -> Vec<String> {
+		for job in jobs {
+			queue.push(job);
+		}
+		std::iter::from_fn(|| queue.pop()).map(|job| job.url).collect()
+	}
+
+	#[test]
+	fn test_bfs_goes_level_by_level() {
+		let at = |url: &str, depth| CrawlJob { depth, ..job(url) };
+		let order = drain(
+			CrawlStrategy::Bfs.queue(),
+			vec![at("/a/1", 2), at("/a", 1), at("/b/1", 2), at("/b", 1)],
+		);
+		assert_eq!(order, vec!["/a", "/b", "/a/1", "/b/1"]);
+	}
+
+	#[test]
+	fn test_dfs_takes_latest_first() {
+		let order = drain(CrawlStrategy::Dfs.queue(), vec![job("/a"), job("/b"), job("/c")]);
+		assert_eq!(order, vec!["/c", "/b", "/a"]);
+	}
+
+	#[test]
+	fn test_priority_takes_best_score_first() {
+		let scored = |url: &str, score| CrawlJob { score, ..job(url) };
+		let mut queue = CrawlStrategy::Priority.queue();
+		queue.push(scored("/low", 1.0));
+		queue.push(scored("/tie-first", 2.0));
+		queue.push(scored("/tie-second", 2.0));
+		queue.push(scored("/raised", 0.0));
+
+		queue.reprioritize("/raised", 5.0);
+		// Scores only go up
+		queue.reprioritize("/tie-first", 0.5);
+		assert_eq!(queue.len(), 4);
+
+		let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|job| job.url).collect();
+		assert_eq!(order, vec!["/raised", "/tie-first", "/tie-second", "/low"]);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn test_skips_rate_limited_host() {
+		let frontier = HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1.0), 0);
+		frontier.push(job("https://slow.com/1"));
+		frontier.push(job("https://slow.com/2"));
+		frontier.push(job("https://fast.com/1"));
+
+		assert_eq!(frontier.try_next().unwrap().url, "https://slow.com/1");
+		// slow.com is out of tokens, so fast.com goes next instead of blocking
+		assert_eq!(frontier.try_next().unwrap().url, "https://fast.com/1");
+
+		let delay = frontier.try_next().unwrap_err();
+		assert!(delay.is_some());
+		assert_eq!(frontier.len(), 1);
+	}
+
+	#[test]
+	fn test_per_host_concurrency_cap() {
+		let frontier = HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1000.0), 1);
+		frontier.push(job("https://a.com/1"));
+		frontier.push(job("https://a.com/2"));
+
+		let first = frontier.try_next().unwrap();
+		// Host is at its cap: nothing to wait for until a job completes
+		assert_eq!(frontier.try_next().unwrap_err(), None);
+
+		frontier.complete(&first);
+		std::thread::sleep(Duration::from_millis(5));
+		assert_eq!(frontier.try_next().unwrap().url, "https://a.com/2");
+		assert!(frontier.is_empty());
+	}
+
+	#[test]
+	fn test_spills_overflow_to_disk_in_order() {
+		let db = sled::Config::new().temporary(true).open().unwrap();
+		let frontier = HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1000.0), 0)
+			.with_spill(db.open_tree("queue").unwrap(), 2, |_| false);
+		let at = |url: &str, depth| CrawlJob { depth, ..job(url) };
+		frontier.push(at("https://a.com/1", 1));
+		frontier.push(at("https://a.com/2", 1));
+		// Memory is full: these wait on disk
+		frontier.push(at("https://a.com/3/deep", 2));
+		frontier.push(at("https://a.com/3", 1));
+		assert_eq!(frontier.len(), 4);
+
+		let mut order = Vec::new();
+		while !frontier.is_empty() {
+			match frontier.try_next() {
+				Ok(job) => order.push(job.url),
+				Err(_) => std::thread::sleep(Duration::from_millis(2)),
+			}
+		}
+		assert_eq!(order, vec!["https://a.com/1", "https://a.com/2", "https://a.com/3", "https://a.com/3/deep"]);
+		assert!(frontier.is_empty());
+
+		let scored = |score| CrawlJob { score, ..job("https://a.com/") };
+		let key = |score| CrawlStrategy::Priority.spill_key(&scored(score), 0);
+		assert!(key(2.0) < key(1.0) && key(1.0) < key(-1.0) && key(-1.0) < key(-2.0));
+	}
+
+	#[test]
+	fn test_lost_spilled_jobs_are_reported() {
+		let db = sled::Config::new().temporary(true).open().unwrap();
+		let tree = db.open_tree("queue").unwrap();
+		// Stands in for the crawl's count of unfinished jobs
+		let unfinished = std::sync::Arc::new(AtomicUsize::new(3));
+		let frontier = HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1000.0), 0).with_spill(tree.clone(), 1, {
+			let unfinished = std::sync::Arc::clone(&unfinished);
+			move |lost| unfinished.fetch_sub(lost, Ordering::SeqCst) == lost
+		});
+		frontier.push(job("https://a.com/1"));
+		frontier.push(job("https://a.com/2"));
+		frontier.push(job("https://a.com/3"));
+
+		// One spilled job is corrupted, the other vanishes
+		let keys: Vec<_> = tree.iter().keys().map(Result::unwrap).collect();
+		tree.insert(&keys[0], b"garbage".to_vec()).unwrap();
+		tree.remove(&keys[1]).unwrap();
+
+		let first = frontier.try_next().unwrap();
+		frontier.complete(&first);
+		unfinished.fetch_sub(1, Ordering::SeqCst);
+
+		assert!(frontier.try_next().is_err());
+		assert_eq!(unfinished.load(Ordering::SeqCst), 0);
+		assert!(frontier.is_empty());
+		assert!(frontier.closed.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn test_crawl_wide_cap() {
+		let frontier = HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1000.0), 0);
+		frontier.set_max_active(1);
+		frontier.push(job("https://a.com/"));
+		frontier.push(job("https://b.com/"));
+
+		let first = frontier.try_next().unwrap();
+		assert_eq!(frontier.running(), 1);
+		assert_eq!(frontier.try_next().unwrap_err(), None);
+
+		frontier.set_max_active(2);
+		assert_eq!(frontier.try_next().unwrap().url, "https://b.com/");
+		frontier.complete(&first);
+		assert_eq!(frontier.running(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_next_wakes_on_push_and_close() {
+		let frontier = std::sync::Arc::new(HostQueues::new(CrawlStrategy::Bfs, RateLimiter::new(1000.0), 0));
+
+		let waiter = {
+			let frontier = std::sync::Arc::clone(&frontier);
+			tokio::spawn(async move { frontier.next().await })
+		};
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		frontier.push(job("https://a.com/"));
+		assert_eq!(waiter.await.unwrap(), Some(job("https://a.com/")));
+
+		let waiter = {
+			let frontier = std::sync::Arc::clone(&frontier);
+			tokio::spawn(async move { frontier.next().await })
+		};
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		frontier.close();
+		assert_eq!(waiter.await.unwrap(), None);
+	}
+}
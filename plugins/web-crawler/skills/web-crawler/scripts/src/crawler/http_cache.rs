@@ -0,0 +1,373 @@
+//! HTTP cache for incremental re-crawls
+//!
+//! Remembers the validators (ETag, Last-Modified) and content hash of every
+//! page crawled, keyed by canonical URL, in `http-cache.json` next to the
+//! other outputs. The next crawl sends conditional requests, and a 304
+//! replays the stored page instead of downloading it again. With a disk
+//! store, the entries live in the store during the crawl rather than in
+//! memory.
+
+use crate::crawler::store::{checked, DiskStore};
+use crate::{PageChange, PageResult};
+use anyhow::Result;
+use parking_lot::Mutex;
+use reqwest::header::{HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const CACHE_FILE: &str = "http-cache.json";
+
+/// What was known about a page the last time it was crawled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub etag: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_modified: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub content_hash: Option<String>,
+
+	/// The page as last crawled, replayed when the server answers 304
+	pub page: PageResult,
+}
+
+impl CacheEntry {
+	fn new(page: &PageResult) -> Self {
+		Self {
+			etag: page.etag.clone(),
+			last_modified: page.last_modified.clone(),
+			content_hash: page.content_hash.clone(),
+			page: PageResult {
+				change: None,
+				..page.clone()
+			},
+		}
+	}
+
+	/// `If-None-Match` / `If-Modified-Since` headers asking whether the page changed
+	pub fn conditional_headers(&self) -> HeaderMap {
+		let mut headers = HeaderMap::new();
+		if let Some(etag) = self.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+			headers.insert(IF_NONE_MATCH, etag);
+		}
+		if let Some(date) = self.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+			headers.insert(IF_MODIFIED_SINCE, date);
+		}
+		headers
+	}
+
+	/// Checks whether `page` has the content this entry was recorded with
+	fn matches(&self, page: &PageResult) -> bool {
+		match (&self.content_hash, &page.content_hash) {
+			(Some(old), Some(new)) => old == new,
+			// No text to hash (non-HTML or noindex pages): trust the validators
+			_ => {
+				(self.etag.is_some() && self.etag == page.etag)
+					|| (self.last_modified.is_some() && self.last_modified == page.last_modified)
+			}
+		}
+	}
+}
+
+/// Pages from the previous crawl, and the ones recorded during this one
+pub struct HttpCache {
+	path: PathBuf,
+	entries: CacheEntries,
+}
+
+/// Cache entries by canonical URL, in memory or in the disk store
+enum CacheEntries {
+	Memory {
+		previous: HashMap<String, CacheEntry>,
+		current: Mutex<HashMap<String, CacheEntry>>,
+	},
+	/// Entries as JSON, so memory does not grow with the crawl
+	Disk { previous: sled::Tree, current: sled::Tree },
+}
+
+impl HttpCache {
+	/// Opens the cache in `output_dir`; a missing or unreadable file starts
+	/// empty. With a disk store, the file is streamed into the store.
+	pub fn open(output_dir: &Path, store: Option<&DiskStore>) -> Result<Self> {
+		let path = output_dir.join(CACHE_FILE);
+		let entries = match store {
+			Some(store) => {
+				let (previous, current) = store.http_cache_trees();
+				previous.clear()?;
+				current.clear()?;
+				if let Ok(file) = fs::File::open(&path) {
+					let mut json = serde_json::Deserializer::from_reader(BufReader::new(file));
+					if let Err(e) = json.deserialize_map(ImportEntries(&previous)) {
+						eprintln!("Warning: Ignoring unreadable HTTP cache: {}", e);
+						previous.clear()?;
+					}
+				}
+				CacheEntries::Disk { previous, current }
+			}
+			None => {
+				let previous = match fs::read_to_string(&path) {
+					Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+						eprintln!("Warning: Ignoring unreadable HTTP cache: {}", e);
+						HashMap::new()
+					}),
+					Err(_) => HashMap::new(),
+				};
+				CacheEntries::Memory {
+					previous,
+					current: Mutex::new(HashMap::new()),
+				}
+			}
+		};
+
+		Ok(Self { path, entries })
+	}
+
+	/// Number of pages known from the previous crawl
+	pub fn len(&self) -> usize {
+		match &self.entries {
+			CacheEntries::Memory { previous, .. } => previous.len(),
+			CacheEntries::Disk { previous, .. } => previous.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Previous crawl's entry for a URL
+	fn previous(&self, url: &str) -> Option<CacheEntry> {
+		match &self.entries {
+			CacheEntries::Memory { previous, .. } => previous.get(url).cloned(),
+			CacheEntries::Disk { previous, .. } => checked(previous.get(url))
+				.flatten()
+				.and_then(|value| serde_json::from_slice(&value).ok()),
+		}
+	}
+
+	/// Entry for a URL crawled before, if its page can stand in for a fresh
+	/// one: it was not reached through redirects, and has Markdown if
+	/// `markdown` is wanted
+	pub fn replayable(&self, url: &str, markdown: bool) -> Option<CacheEntry> {
+		self.previous(url)
+			.filter(|entry| entry.page.redirects.is_empty() && (!markdown || entry.page.markdown.is_some()))
+	}
+
+	/// Compares a freshly crawled page with the previous crawl
+	pub fn classify(&self, page: &PageResult) -> PageChange {
+		match self.previous(&page.url) {
+			None => PageChange::New,
+			Some(entry) if entry.matches(page) => PageChange::Unchanged,
+			Some(_) => PageChange::Changed,
+		}
+	}
+
+	/// Records a crawled page for the next crawl
+	pub fn record(&self, page: &PageResult) {
+		let entry = CacheEntry::new(page);
+		match &self.entries {
+			CacheEntries::Memory { current, .. } => {
+				current.lock().insert(page.url.clone(), entry);
+			}
+			CacheEntries::Disk { current, .. } => {
+				let value = serde_json::to_vec(&entry).expect("cache entry serializes");
+				checked(current.insert(page.url.as_bytes(), value));
+			}
+		}
+	}
+
+	/// Number of pages from the previous crawl not recorded in this one
+	pub fn removed(&self) -> usize {
+		match &self.entries {
+			CacheEntries::Memory { previous, current } => {
+				let current = current.lock();
+				previous.keys().filter(|url| !current.contains_key(*url)).count()
+			}
+			CacheEntries::Disk { previous, current } => previous
+				.iter()
+				.keys()
+				.filter_map(checked)
+				.filter(|url| !checked(current.contains_key(url)).unwrap_or(true))
+				.count(),
+		}
+	}
+
+	/// Writes this crawl's entries and the previous ones it did not replace,
+	/// leaving out previous pages not found again if `drop_removed`
+	pub fn save(&self, drop_removed: bool) -> Result<()> {
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		// Same write-then-rename as checkpoints, so a crash never truncates the cache
+		let tmp_path = self.path.with_extension("json.tmp");
+		let mut out = BufWriter::new(fs::File::create(&tmp_path)?);
+		match &self.entries {
+			CacheEntries::Memory { previous, current } => {
+				let current = current.lock();
+				let entries: BTreeMap<&String, &CacheEntry> = previous
+					.iter()
+					.filter(|(url, _)| !drop_removed || current.contains_key(*url))
+					.chain(current.iter())
+					.collect();
+				serde_json::to_writer(&mut out, &entries)?;
+			}
+			CacheEntries::Disk { previous, current } => {
+				// Both trees are sorted by URL: merge them, this crawl's entry first
+				let mut previous = previous.iter().peekable();
+				let mut current = current.iter().peekable();
+				let mut first = true;
+				out.write_all(b"{")?;
+				loop {
+					let next = match (previous.peek(), current.peek()) {
+						(None, None) => break,
+						(Some(Ok((old, _))), Some(Ok((new, _)))) => old.cmp(new),
+						(Some(_), None) | (Some(Err(_)), _) => CmpOrdering::Less,
+						(None, Some(_)) | (_, Some(Err(_))) => CmpOrdering::Greater,
+					};
+					let (url, entry) = match next {
+						CmpOrdering::Less => {
+							let kept = previous.next().expect("peeked entry")?;
+							if drop_removed {
+								continue;
+							}
+							kept
+						}
+						CmpOrdering::Equal => {
+							previous.next();
+							current.next().expect("peeked entry")?
+						}
+						CmpOrdering::Greater => current.next().expect("peeked entry")?,
+					};
+
+					if !first {
+						out.write_all(b",")?;
+					}
+					first = false;
+					serde_json::to_writer(&mut out, std::str::from_utf8(&url)?)?;
+					out.write_all(b":")?;
+					out.write_all(&entry)?;
+				}
+				out.write_all(b"}")?;
+			}
+		}
+		out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+		fs::rename(tmp_path, &self.path)?;
+
+		Ok(())
+	}
+}
+
+/// Streams the entries of `http-cache.json` into a tree, one at a time
+struct ImportEntries<'a>(&'a sled::Tree);
+
+impl<'de> Visitor<'de> for ImportEntries<'_> {
+	type Value = ();
+
+	fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str("a map of cache entries by URL")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+		while let Some((url, entry)) = map.next_entry::<String, CacheEntry>()? {
+			let value = serde_json::to_vec(&entry).map_err(de::Error::custom)?;
+			self.0.insert(url.as_bytes(), value).map_err(de::Error::custom)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crawler::store::StoreConfig;
+
+	fn page(url: &str, hash: &str, etag: Option<&str>) -> PageResult {
+		PageResult {
+			url: url.to_string(),
+			status_code: 200,
+			content_hash: Some(hash.to_string()),
+			etag: etag.map(str::to_string),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_classify_against_previous_crawl() {
+		for disk in [false, true] {
+			let dir = tempfile::tempdir().unwrap();
+			// Each crawl reopens the cache; the store stays open in between
+			let store = disk.then(|| DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+			let open = || HttpCache::open(dir.path(), store.as_ref()).unwrap();
+			let first = open();
+			assert!(first.is_empty());
+			assert_eq!(first.classify(&page("https://a.com/", "1", None)), PageChange::New);
+
+			first.record(&page("https://a.com/", "1", Some("\"v1\"")));
+			first.record(&page("https://a.com/b", "2", None));
+			first.record(&page("https://a.com/gone", "3", None));
+			first.save(true).unwrap();
+
+			let second = open();
+			assert_eq!(second.len(), 3);
+			assert_eq!(second.classify(&page("https://a.com/", "1", Some("\"v2\""))), PageChange::Unchanged);
+			assert_eq!(second.classify(&page("https://a.com/b", "9", None)), PageChange::Changed);
+			assert_eq!(second.classify(&page("https://a.com/c", "4", None)), PageChange::New);
+
+			second.record(&page("https://a.com/", "1", None));
+			second.record(&page("https://a.com/b", "9", None));
+			assert_eq!(second.removed(), 1);
+		}
+	}
+
+	#[test]
+	fn test_conditional_headers_and_save() {
+		for disk in [false, true] {
+			let dir = tempfile::tempdir().unwrap();
+			let store = disk.then(|| DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+			let open = || HttpCache::open(dir.path(), store.as_ref()).unwrap();
+			let first = open();
+			first.record(&PageResult {
+				last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+				..page("https://a.com/", "1", Some("\"v1\""))
+			});
+			first.record(&page("https://a.com/old", "2", None));
+			first.save(true).unwrap();
+
+			let second = open();
+			let entry = second.replayable("https://a.com/", false).unwrap();
+			let headers = entry.conditional_headers();
+			assert_eq!(headers[IF_NONE_MATCH], "\"v1\"");
+			assert_eq!(headers[IF_MODIFIED_SINCE], "Wed, 21 Oct 2015 07:28:00 GMT");
+			assert_eq!(entry.page.content_hash.as_deref(), Some("1"));
+			assert!(second.replayable("https://a.com/unknown", false).is_none());
+			// Stored page has no Markdown to replay
+			assert!(second.replayable("https://a.com/", true).is_none());
+
+			// A partial crawl keeps the pages it did not crawl again
+			second.record(&page("https://a.com/", "4", None));
+			second.record(&page("https://a.com/new", "3", None));
+			second.save(false).unwrap();
+
+			let third = open();
+			assert_eq!(third.len(), 3);
+			assert_eq!(third.classify(&page("https://a.com/", "4", None)), PageChange::Unchanged);
+
+			// A complete one drops the pages not found again
+			third.record(&page("https://a.com/new", "3", None));
+			third.save(true).unwrap();
+
+			let fourth = open();
+			assert_eq!(fourth.len(), 1);
+			assert!(fourth.replayable("https://a.com/old", false).is_none());
+			assert!(fourth.replayable("https://a.com/new", false).is_some());
+		}
+	}
+}
@@ -1,5 +1,14 @@
 //! Crawler module
+pub mod body;
+pub mod budget;
 pub mod engine;
 pub mod robots;
 pub mod checkpoint;
+pub mod concurrency;
+pub mod dedup;
+pub mod frontier;
+pub mod http_cache;
+pub mod priority;
 pub mod rate_limiter;
+pub mod retry;
+pub mod store;
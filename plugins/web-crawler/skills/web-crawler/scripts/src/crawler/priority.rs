@@ -0,0 +1,152 @@
+//! Best-first scoring for the priority frontier
+//!
+//! A URL's score rises with its sitemap `<priority>`, with keyword matches in
+//! the URL or the anchor text of the link that led to it, and with the number
+//! of pages linking to it so far; it falls with the depth of its path.
+
+use serde::{Deserialize, Serialize};
+
+/// Sitemap priority assumed for URLs without one (the protocol's default)
+const DEFAULT_SITEMAP_PRIORITY: f32 = 0.5;
+
+/// Weights of the priority score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PriorityConfig {
+	/// Words that make a URL relevant, matched case-insensitively in the URL
+	/// and the anchor text
+	pub keywords: Vec<String>,
+
+	/// Subtracted for each path segment
+	pub depth_weight: f64,
+
+	/// Multiplies the sitemap priority (0.0 to 1.0)
+	pub sitemap_weight: f64,
+
+	/// Added for each keyword found
+	pub keyword_weight: f64,
+
+	/// Multiplies ln(1 + inbound links)
+	pub inbound_weight: f64,
+}
+
+impl Default for PriorityConfig {
+	fn default() -> Self {
+		Self {
+			keywords: Vec::new(),
+			depth_weight: 1.0,
+			sitemap_weight: 2.0,
+			keyword_weight: 3.0,
+			inbound_weight: 1.0,
+		}
+	}
+}
+
+/// What is known about a URL when it is queued
+#[derive(Debug, Clone, Default)]
+pub struct ScoreInput<'a> {
+	pub url: &'a str,
+	/// Anchor text of the link that led to the URL
+	pub anchor_text: &'a str,
+	pub sitemap_priority: Option<f32>,
+	/// Pages seen linking to the URL so far
+	pub inbound_links: usize,
+}
+
+/// Scores URLs for the priority frontier (higher is crawled first)
+#[derive(Debug, Clone)]
+pub struct PriorityScorer {
+	config: PriorityConfig,
+	/// Keywords, lowercased
+	keywords: Vec<String>,
+}
+
+impl PriorityScorer {
+	pub fn new(config: PriorityConfig) -> Self {
+		let keywords = config
+			.keywords
+			.iter()
+			.map(|keyword| keyword.trim().to_lowercase())
+			.filter(|keyword| !keyword.is_empty())
+			.collect();
+		Self { config, keywords }
+	}
+
+	pub fn score(&self, input: &ScoreInput) -> f64 {
+		let sitemap_priority = input.sitemap_priority.unwrap_or(DEFAULT_SITEMAP_PRIORITY) as f64;
+
+		let url = input.url.to_lowercase();
+		let anchor_text = input.anchor_text.to_lowercase();
+		let keyword_matches = self
+			.keywords
+			.iter()
+			.filter(|keyword| url.contains(keyword.as_str()) || anchor_text.contains(keyword.as_str()))
+			.count();
+
+		-self.config.depth_weight * path_depth(input.url) as f64
+			+ self.config.sitemap_weight * sitemap_priority
+			+ self.config.keyword_weight * keyword_matches as f64
+			+ self.config.inbound_weight * (input.inbound_links as f64).ln_1p()
+	}
+}
+
+/// Number of non-empty path segments (`/docs/api/` has 2)
+fn path_depth(url: &str) -> usize {
+	url::Url::parse(url)
+		.ok()
+		.and_then(|url| url.path_segments().map(|segments| segments.filter(|s| !s.is_empty()).count()))
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scorer(keywords: &[&str]) -> PriorityScorer {
+		PriorityScorer::new(PriorityConfig {
+			keywords: keywords.iter().map(|k| k.to_string()).collect(),
+			..Default::default()
+		})
+	}
+
+	fn score(scorer: &PriorityScorer, url: &str) -> f64 {
+		scorer.score(&ScoreInput { url, ..Default::default() })
+	}
+
+	#[test]
+	fn test_shallow_paths_score_higher() {
+		let scorer = scorer(&[]);
+		assert_eq!(path_depth("https://example.com/docs/api/"), 2);
+		assert!(score(&scorer, "https://example.com/docs") > score(&scorer, "https://example.com/docs/api/v2"));
+	}
+
+	#[test]
+	fn test_keywords_match_url_or_anchor_text() {
+		let scorer = scorer(&["Pricing", "api"]);
+		let plain = score(&scorer, "https://example.com/blog/post");
+		let in_url = score(&scorer, "https://example.com/blog/api-changes");
+		let in_anchor = scorer.score(&ScoreInput {
+			url: "https://example.com/blog/post",
+			anchor_text: "See our PRICING",
+			..Default::default()
+		});
+
+		assert_eq!(in_url - plain, 3.0);
+		assert_eq!(in_anchor - plain, 3.0);
+	}
+
+	#[test]
+	fn test_sitemap_priority_and_inbound_links() {
+		let scorer = scorer(&[]);
+		let input = |sitemap_priority, inbound_links| ScoreInput {
+			url: "https://example.com/page",
+			sitemap_priority,
+			inbound_links,
+			..Default::default()
+		};
+
+		assert!(scorer.score(&input(Some(1.0), 0)) > scorer.score(&input(None, 0)));
+		assert!(scorer.score(&input(None, 0)) > scorer.score(&input(Some(0.1), 0)));
+		assert!(scorer.score(&input(None, 10)) > scorer.score(&input(None, 1)));
+	}
+}
@@ -1,48 +1,121 @@
-//! Rate limiting with token bucket algorithm
+//! Per-host rate limiting with token bucket algorithm
 
-use governor::{Quota, RateLimiter as GovernorLimiter};
-use std::num::NonZeroU32;
+use dashmap::DashMap;
+use parking_lot::Mutex;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-/// Rate limiter using token bucket algorithm
+/// Longest robots.txt Crawl-delay honored; larger values would stall the crawl
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// Rate limiter keyed on hostname, one token bucket per host
 #[derive(Clone)]
 pub struct RateLimiter {
-	limiter: Arc<GovernorLimiter<
-		governor::state::direct::NotKeyed,
-		governor::state::InMemoryState,
-		governor::clock::DefaultClock,
-	>>,
+	period: Duration,
+	hosts: Arc<DashMap<String, HostBucket>>,
+}
+
+/// Token bucket for one host, holding a single token so requests are evenly
+/// spaced `period` apart
+#[derive(Clone)]
+struct HostBucket {
+	period: Duration,
+	/// When the next token is available
+	next: Arc<Mutex<Instant>>,
+}
+
+impl HostBucket {
+	fn new(period: Duration) -> Self {
+		Self {
+			period,
+			next: Arc::new(Mutex::new(Instant::now())),
+		}
+	}
+
+	fn try_acquire(&self) -> Result<(), Duration> {
+		let now = Instant::now();
+		let mut next = self.next.lock();
+		if *next > now {
+			return Err(*next - now);
+		}
+		*next = now + self.period;
+		Ok(())
+	}
+
+	/// Gives back the last token taken
+	fn refund(&self) {
+		let mut next = self.next.lock();
+		if let Some(earlier) = next.checked_sub(self.period) {
+			*next = earlier;
+		}
+	}
 }
 
 impl RateLimiter {
-	/// Creates a new rate limiter with requests per second
+	/// Creates a new rate limiter with requests per second per host
 	pub fn new(requests_per_second: f64) -> Self {
-		// Convert to requests per minute for better precision
-		let requests_per_minute = (requests_per_second * 60.0) as u32;
-		let quota = Quota::per_minute(NonZeroU32::new(requests_per_minute).unwrap());
-
 		Self {
-			limiter: Arc::new(GovernorLimiter::direct(quota)),
+			period: Duration::from_secs_f64(1.0 / requests_per_second.max(0.001)),
+			hosts: Arc::new(DashMap::new()),
 		}
 	}
 
-	/// Waits until a request is allowed (blocking)
-	pub async fn wait(&self) {
-		loop {
-			match self.limiter.check() {
-				Ok(_) => return,
-				Err(_) => {
-					// Wait a bit before retrying
-					tokio::time::sleep(Duration::from_millis(10)).await;
+	/// Spaces requests to `host` at least `delay` apart (robots.txt
+	/// Crawl-delay). Only ever slows a host down, capped at `MAX_CRAWL_DELAY`.
+	pub fn set_crawl_delay(&self, host: &str, delay: Duration) {
+		let period = delay.min(MAX_CRAWL_DELAY);
+		if period <= self.period {
+			return;
+		}
+
+		self.hosts
+			.entry(host.to_string())
+			.and_modify(|bucket| {
+				if bucket.period != period {
+					*bucket = HostBucket::new(period);
 				}
-			}
+			})
+			.or_insert_with(|| HostBucket::new(period));
+	}
+
+	/// Minimum spacing between requests to `host`
+	pub fn interval(&self, host: &str) -> Duration {
+		self.hosts
+			.get(host)
+			.map_or(self.period, |bucket| bucket.period)
+	}
+
+	/// Takes a token for `host`, or returns how long until one is available
+	pub fn try_acquire(&self, host: &str) -> Result<(), Duration> {
+		self.bucket(host).try_acquire()
+	}
+
+	/// Gives back a token taken for a request that was never sent
+	pub fn refund(&self, host: &str) {
+		self.bucket(host).refund();
+	}
+
+	/// Waits until a request to `host` is allowed
+	pub async fn wait(&self, host: &str) {
+		while let Err(delay) = self.try_acquire(host) {
+			tokio::time::sleep(delay).await;
 		}
 	}
 
-	/// Checks if a request is allowed without waiting
-	pub fn check(&self) -> bool {
-		self.limiter.check().is_ok()
+	/// Checks if a request to `host` is allowed without waiting
+	pub fn check(&self, host: &str) -> bool {
+		self.try_acquire(host).is_ok()
+	}
+
+	fn bucket(&self, host: &str) -> HostBucket {
+		if let Some(bucket) = self.hosts.get(host) {
+			return bucket.clone();
+		}
+
+		self.hosts
+			.entry(host.to_string())
+			.or_insert_with(|| HostBucket::new(self.period))
+			.clone()
 	}
 }
 
@@ -55,10 +128,10 @@ mod tests {
 		let limiter = RateLimiter::new(10.0); // 10 req/s
 
 		// First request should be immediate
-		limiter.wait().await;
+		limiter.wait("example.com").await;
 
 		// Second request should also work quickly
-		limiter.wait().await;
+		limiter.wait("example.com").await;
 	}
 
 	#[tokio::test]
@@ -66,15 +139,61 @@ mod tests {
 		let limiter = RateLimiter::new(2.0); // 2 req/s (very slow)
 
 		let start = std::time::Instant::now();
-		
+
 		// Make 3 requests
-		limiter.wait().await;
-		limiter.wait().await;
-		limiter.wait().await;
+		limiter.wait("example.com").await;
+		limiter.wait("example.com").await;
+		limiter.wait("example.com").await;
 
 		let elapsed = start.elapsed();
 
 		// Should take at least 1 second (3 requests at 2 req/s)
 		assert!(elapsed.as_secs() >= 1);
 	}
+
+	#[test]
+	fn test_huge_rates_do_not_panic() {
+		for rate in [1e10, 1e300, f64::INFINITY] {
+			let limiter = RateLimiter::new(rate);
+			assert!(limiter.try_acquire("example.com").is_ok());
+		}
+	}
+
+	#[test]
+	fn test_hosts_are_limited_independently() {
+		let limiter = RateLimiter::new(1.0);
+
+		assert!(limiter.try_acquire("a.example.com").is_ok());
+		assert!(limiter.try_acquire("b.example.com").is_ok());
+
+		// The first host is throttled, and reports roughly one period to wait
+		let delay = limiter.try_acquire("a.example.com").unwrap_err();
+		assert!(delay > Duration::from_millis(500) && delay <= Duration::from_secs(1));
+	}
+
+	#[test]
+	fn test_refunded_token_is_available_again() {
+		let limiter = RateLimiter::new(1.0);
+
+		assert!(limiter.try_acquire("example.com").is_ok());
+		limiter.refund("example.com");
+		assert!(limiter.try_acquire("example.com").is_ok());
+		assert!(limiter.try_acquire("example.com").is_err());
+	}
+
+	#[test]
+	fn test_crawl_delay_only_slows_down() {
+		let limiter = RateLimiter::new(2.0);
+
+		limiter.set_crawl_delay("fast.com", Duration::from_millis(100));
+		assert_eq!(limiter.interval("fast.com"), Duration::from_millis(500));
+
+		limiter.set_crawl_delay("slow.com", Duration::from_secs(5));
+		assert_eq!(limiter.interval("slow.com"), Duration::from_secs(5));
+		assert!(limiter.try_acquire("slow.com").is_ok());
+		assert!(limiter.try_acquire("slow.com").unwrap_err() > Duration::from_secs(4));
+
+		limiter.set_crawl_delay("huge.com", Duration::from_secs(3600));
+		assert_eq!(limiter.interval("huge.com"), MAX_CRAWL_DELAY);
+	}
 }
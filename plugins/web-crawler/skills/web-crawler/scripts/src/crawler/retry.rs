@@ -0,0 +1,182 @@
+//! Retry policy with exponential backoff and Retry-After support
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// When and how often failed fetches are retried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+	/// Attempts per URL, including the first (1 = no retries)
+	pub max_attempts: u32,
+
+	/// Delay before the first retry in milliseconds, doubled on each retry
+	pub backoff_base_ms: u64,
+
+	/// Upper bound of the random jitter added to a delay, as a fraction of it
+	pub jitter: f64,
+
+	/// Longest wait between attempts in milliseconds. A server asking for a
+	/// longer Retry-After gets its response returned instead of retried
+	pub max_backoff_ms: u64,
+
+	/// HTTP status codes worth retrying
+	pub retryable_status: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 3,
+			backoff_base_ms: 500,
+			jitter: 0.25,
+			max_backoff_ms: 30_000,
+			retryable_status: vec![408, 429, 500, 502, 503, 504],
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Policy that never retries
+	pub fn none() -> Self {
+		Self {
+			max_attempts: 1,
+			..Self::default()
+		}
+	}
+
+	/// Checks if another attempt is allowed after `attempt` attempts
+	pub fn can_retry(&self, attempt: u32) -> bool {
+		attempt < self.max_attempts
+	}
+
+	pub fn is_retryable_status(&self, status: u16) -> bool {
+		self.retryable_status.contains(&status)
+	}
+
+	/// Checks if a request error is transient (timeout, refused connection or
+	/// a body cut off mid-transfer); other request errors would fail again
+	pub fn is_retryable_error(&self, error: &reqwest::Error) -> bool {
+		error.is_timeout() || error.is_connect() || error.is_body()
+	}
+
+	/// Delay before the retry following `attempt`: exponential backoff with
+	/// jitter, or the server's Retry-After if that is longer. `None` when the
+	/// Retry-After exceeds `max_backoff_ms`, as retrying sooner is pointless
+	pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+		let max_backoff = Duration::from_millis(self.max_backoff_ms);
+		match retry_after {
+			Some(retry_after) if retry_after > max_backoff => None,
+			Some(retry_after) => Some(self.backoff(attempt).max(retry_after)),
+			None => Some(self.backoff(attempt)),
+		}
+	}
+
+	/// Exponential backoff with jitter before the retry following `attempt`,
+	/// capped at `max_backoff_ms`
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		let exponent = attempt.saturating_sub(1).min(16);
+		let backoff = self.backoff_base_ms.saturating_mul(1 << exponent) as f64;
+		let jitter = if self.jitter > 0.0 {
+			rand::thread_rng().gen_range(0.0..=self.jitter)
+		} else {
+			0.0
+		};
+		let backoff = Duration::from_millis((backoff * (1.0 + jitter)) as u64);
+		backoff.min(Duration::from_millis(self.max_backoff_ms))
+	}
+}
+
+/// Reads a Retry-After header, given either as seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+	let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+	if let Ok(seconds) = value.parse::<u64>() {
+		return Some(Duration::from_secs(seconds));
+	}
+
+	let date = DateTime::parse_from_rfc2822(value).ok()?;
+	let wait = date.with_timezone(&Utc).signed_duration_since(Utc::now());
+	Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use reqwest::header::HeaderValue;
+
+	fn without_jitter() -> RetryPolicy {
+		RetryPolicy {
+			jitter: 0.0,
+			..RetryPolicy::default()
+		}
+	}
+
+	#[test]
+	fn test_exponential_backoff() {
+		let policy = without_jitter();
+
+		assert_eq!(policy.backoff(1), Duration::from_millis(500));
+		assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+		assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+		assert_eq!(policy.backoff(20), Duration::from_secs(30));
+		assert_eq!(policy.delay(2, None), Some(Duration::from_millis(1000)));
+	}
+
+	#[test]
+	fn test_jitter_stays_in_bounds() {
+		let policy = RetryPolicy::default();
+
+		for _ in 0..100 {
+			let delay = policy.backoff(1);
+			assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(625));
+		}
+	}
+
+	#[test]
+	fn test_retry_after_extends_backoff() {
+		let policy = without_jitter();
+
+		assert_eq!(policy.delay(1, Some(Duration::from_secs(5))), Some(Duration::from_secs(5)));
+		assert_eq!(policy.delay(3, Some(Duration::from_millis(10))), Some(Duration::from_secs(2)));
+		assert_eq!(policy.delay(1, Some(Duration::from_secs(30))), Some(Duration::from_secs(30)));
+	}
+
+	#[test]
+	fn test_long_retry_after_stops_retrying() {
+		let policy = without_jitter();
+
+		assert_eq!(policy.delay(1, Some(Duration::from_secs(600))), None);
+		assert_eq!(policy.delay(1, Some(Duration::from_millis(30_001))), None);
+	}
+
+	#[test]
+	fn test_attempts_and_statuses() {
+		let policy = RetryPolicy::default();
+
+		assert!(policy.can_retry(2));
+		assert!(!policy.can_retry(3));
+		assert!(!RetryPolicy::none().can_retry(1));
+		assert!(policy.is_retryable_status(503));
+		assert!(!policy.is_retryable_status(404));
+	}
+
+	#[test]
+	fn test_parse_retry_after() {
+		let mut headers = HeaderMap::new();
+		assert_eq!(retry_after(&headers), None);
+
+		headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+		assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+		// Dates in the past mean "retry now"
+		headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+		assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+		headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+		assert_eq!(retry_after(&headers), None);
+	}
+}
@@ -1,15 +1,159 @@
 //! robots.txt parser and checker
 
+use crate::crawler::rate_limiter::MAX_CRAWL_DELAY;
+use crate::parser::sitemap::parse_sitemap_directives;
 use anyhow::Result;
 use dashmap::DashMap;
+use serde::Serialize;
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Parsed robots.txt policy for one host, as it applies to our user agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RobotsRules {
+	/// Raw robots.txt body (None when the host has none)
+	#[serde(skip)]
+	pub content: Option<String>,
+
+	/// User-agent group the rules were taken from ("*" or our product token)
+	pub agent: Option<String>,
+
+	/// Allow rules for that group
+	pub allow: Vec<String>,
+
+	/// Disallow rules for that group
+	pub disallow: Vec<String>,
+
+	/// Crawl-delay for that group, in seconds
+	pub crawl_delay: Option<f64>,
+
+	/// Sitemaps declared anywhere in the file
+	pub sitemaps: Vec<String>,
+}
+
+impl RobotsRules {
+	/// Parses robots.txt content, keeping the group that matches `user_agent`
+	/// (by product token, e.g. "rcrawler" for "rcrawler/0.1.0") or else `*`
+	pub fn parse(content: &str, user_agent: &str) -> Self {
+		let product = product_token(user_agent).to_ascii_lowercase();
+
+		let mut own = GroupRules::default();
+		let mut wildcard = GroupRules::default();
+
+		// Agents of the group being read, and whether its rules have started
+		let mut agents: Vec<String> = Vec::new();
+		let mut in_rules = false;
+
+		for line in content.lines() {
+			// Strip comments
+			let line = line.split('#').next().unwrap_or("").trim();
+
+			let (key, value) = match line.split_once(':') {
+				Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+				None => continue,
+			};
+
+			match key.as_str() {
+				"user-agent" => {
+					if in_rules {
+						agents.clear();
+						in_rules = false;
+					}
+					agents.push(value.to_ascii_lowercase());
+				}
+				"allow" | "disallow" | "crawl-delay" => {
+					in_rules = true;
+					for agent in &agents {
+						let group = if *agent == product {
+							&mut own
+						} else if agent == "*" {
+							&mut wildcard
+						} else {
+							continue;
+						};
+						group.add(&key, value);
+					}
+				}
+				_ => {}
+			}
+		}
+
+		let (agent, group) = if own.seen {
+			(Some(product), own)
+		} else if wildcard.seen {
+			(Some("*".to_string()), wildcard)
+		} else {
+			(None, GroupRules::default())
+		};
+
+		Self {
+			content: Some(content.to_string()),
+			agent,
+			allow: group.allow,
+			disallow: group.disallow,
+			crawl_delay: group.crawl_delay,
+			sitemaps: parse_sitemap_directives(content),
+		}
+	}
+
+	/// Checks if a URL is allowed for `user_agent`
+	pub fn is_allowed(&self, user_agent: &str, url: &str) -> bool {
+		match &self.content {
+			Some(robots_txt) => {
+				let mut matcher = robotstxt::DefaultMatcher::default();
+				// Groups are matched on the product token, not the full user agent
+				matcher.one_agent_allowed_by_robots(robots_txt, product_token(user_agent), url)
+			}
+			None => true, // No robots.txt found, allow all
+		}
+	}
+
+	/// Crawl-delay as a duration, if one was given
+	pub fn crawl_delay(&self) -> Option<Duration> {
+		self.crawl_delay.map(Duration::from_secs_f64)
+	}
+}
+
+/// Product token of a user agent ("rcrawler" for "rcrawler/0.1.0")
+pub(crate) fn product_token(user_agent: &str) -> &str {
+	user_agent.split('/').next().unwrap_or(user_agent).trim()
+}
+
+/// Rules collected for one user-agent group while parsing
+#[derive(Default)]
+struct GroupRules {
+	seen: bool,
+	allow: Vec<String>,
+	disallow: Vec<String>,
+	crawl_delay: Option<f64>,
+}
+
+impl GroupRules {
+	fn add(&mut self, key: &str, value: &str) {
+		self.seen = true;
+		match key {
+			"allow" if !value.is_empty() => self.allow.push(value.to_string()),
+			// An empty Disallow allows everything
+			"disallow" if !value.is_empty() => self.disallow.push(value.to_string()),
+			"crawl-delay" => {
+				if let Ok(delay) = value.parse::<f64>() {
+					// Clamped here so absurd values can't overflow a Duration
+					if delay.is_finite() && delay >= 0.0 {
+						self.crawl_delay = Some(delay.min(MAX_CRAWL_DELAY.as_secs_f64()));
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
 /// Manages robots.txt rules for multiple domains
 #[derive(Clone)]
 pub struct RobotsChecker {
 	client: reqwest::Client,
-	cache: Arc<DashMap<String, Option<String>>>, // Cache robots.txt content
+	cache: Arc<DashMap<String, Arc<RobotsRules>>>, // Cache parsed robots.txt per host
 	user_agent: String,
 }
 
@@ -30,48 +174,47 @@ impl RobotsChecker {
 
 	/// Checks if a URL is allowed by robots.txt
 	pub async fn is_allowed(&self, url: &str) -> bool {
-		let parsed = match url::Url::parse(url) {
-			Ok(u) => u,
-			Err(_) => return true, // Invalid URL, allow by default
-		};
+		match self.fetch_rules(url).await {
+			Some(rules) => rules.is_allowed(&self.user_agent, url),
+			None => true, // Invalid URL, allow by default
+		}
+	}
 
-		let domain = match parsed.domain() {
-			Some(d) => d.to_string(),
-			None => return true, // No domain, allow by default
-		};
+	/// Returns the cached policy for a host, if its robots.txt was loaded
+	pub fn rules_for(&self, host: &str) -> Option<Arc<RobotsRules>> {
+		self.cache
+			.get(&host.to_ascii_lowercase())
+			.map(|rules| Arc::clone(rules.value()))
+	}
+
+	/// Returns the policy for a URL's host, fetching robots.txt on first use
+	pub async fn fetch_rules(&self, url: &str) -> Option<Arc<RobotsRules>> {
+		let parsed = url::Url::parse(url).ok()?;
+		let host = parsed.host_str()?.to_ascii_lowercase();
 
 		// Check cache first
-		if let Some(content) = self.cache.get(&domain) {
-			return match content.value() {
-				Some(robots_txt) => {
-					let mut matcher = robotstxt::DefaultMatcher::default();
-					matcher.one_agent_allowed_by_robots(&self.user_agent, robots_txt, url)
-				}
-				None => true, // No robots.txt found, allow all
-			};
+		if let Some(rules) = self.rules_for(&host) {
+			return Some(rules);
 		}
 
 		// Fetch and parse robots.txt
-		let robots_url = format!("{}://{}/robots.txt", parsed.scheme(), domain);
+		let robots_url = match parsed.port() {
+			Some(port) => format!("{}://{}:{}/robots.txt", parsed.scheme(), host, port),
+			None => format!("{}://{}/robots.txt", parsed.scheme(), host),
+		};
 
-		match self.fetch_robots(&robots_url).await {
-			Ok(Some(content)) => {
-				let mut matcher = robotstxt::DefaultMatcher::default();
-				let allowed = matcher.one_agent_allowed_by_robots(&self.user_agent, &content, url);
-				self.cache.insert(domain, Some(content));
-				allowed
-			}
-			Ok(None) => {
-				// No robots.txt found, allow all
-				self.cache.insert(domain, None);
-				true
-			}
+		let rules = match self.fetch_robots(&robots_url).await {
+			Ok(Some(content)) => RobotsRules::parse(&content, &self.user_agent),
+			Ok(None) => RobotsRules::default(), // No robots.txt found, allow all
 			Err(e) => {
-				eprintln!("Failed to fetch robots.txt for {}: {}", domain, e);
-				self.cache.insert(domain, None);
-				true // Allow on error
+				eprintln!("Failed to fetch robots.txt for {}: {}", host, e);
+				RobotsRules::default() // Allow on error
 			}
-		}
+		};
+
+		let rules = Arc::new(rules);
+		self.cache.insert(host, Arc::clone(&rules));
+		Some(rules)
 	}
 
 	/// Fetches robots.txt content from a URL
@@ -102,6 +245,7 @@ mod tests {
 		// Should be cached now
 		let allowed2 = checker.is_allowed("https://www.google.com/search").await;
 		assert_eq!(allowed, allowed2);
+		assert!(checker.rules_for("www.google.com").is_some());
 	}
 
 	#[tokio::test]
@@ -112,4 +256,69 @@ mod tests {
 		let allowed = checker.is_allowed("https://example.com/page").await;
 		assert!(allowed); // Should allow by default
 	}
+
+	#[test]
+	fn test_parse_prefers_own_agent_group() {
+		let robots = "User-agent: *\n\
+			Disallow: /private\n\
+			Crawl-delay: 10\n\
+			\n\
+			User-agent: rcrawler\n\
+			Disallow: /admin\n\
+			Allow: /admin/public\n\
+			Crawl-delay: 2.5\n";
+
+		let rules = RobotsRules::parse(robots, "rcrawler/0.1.0");
+
+		assert_eq!(rules.agent.as_deref(), Some("rcrawler"));
+		assert_eq!(rules.disallow, vec!["/admin".to_string()]);
+		assert_eq!(rules.allow, vec!["/admin/public".to_string()]);
+		assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+		assert!(!rules.is_allowed("rcrawler/0.1.0", "https://example.com/admin"));
+		assert!(rules.is_allowed("rcrawler/0.1.0", "https://example.com/private"));
+	}
+
+	#[test]
+	fn test_parse_wildcard_group_and_shared_agents() {
+		let robots = "User-agent: googlebot\n\
+			User-agent: *\n\
+			Disallow: /tmp\n\
+			Crawl-delay: nonsense\n\
+			\n\
+			User-agent: bingbot\n\
+			Crawl-delay: 30\n";
+
+		let rules = RobotsRules::parse(robots, "rcrawler/0.1.0");
+
+		assert_eq!(rules.agent.as_deref(), Some("*"));
+		assert_eq!(rules.disallow, vec!["/tmp".to_string()]);
+		assert_eq!(rules.crawl_delay, None);
+	}
+
+	#[test]
+	fn test_huge_crawl_delay_is_clamped() {
+		let rules = RobotsRules::parse("User-agent: *\nCrawl-delay: 1e20\n", "rcrawler/0.1.0");
+
+		assert_eq!(rules.crawl_delay(), Some(MAX_CRAWL_DELAY));
+	}
+
+	#[test]
+	fn test_parse_collects_sitemaps() {
+		let robots = "User-agent: *\n\
+			Disallow: /admin\n\
+			Sitemap: https://example.com/sitemap-posts.xml\n";
+
+		let rules = RobotsRules::parse(robots, "rcrawler/0.1.0");
+
+		assert_eq!(rules.sitemaps, vec!["https://example.com/sitemap-posts.xml".to_string()]);
+	}
+
+	#[test]
+	fn test_parse_without_rules() {
+		let rules = RobotsRules::parse("# nothing here\n", "rcrawler/0.1.0");
+
+		assert_eq!(rules.agent, None);
+		assert!(rules.sitemaps.is_empty());
+		assert!(rules.is_allowed("rcrawler/0.1.0", "https://example.com/anything"));
+	}
 }
@@ -0,0 +1,648 @@
+//! Disk-backed crawl state for very large crawls
+//!
+//! With the store enabled, the visited set, the pending URLs, the referrer
+//! index and the part of the frontier beyond an in-memory limit live in an
+//! embedded key-value store (`crawl-store/` in the output directory), so
+//! the per-URL state no longer grows with the number of URLs discovered. A
+//! bloom filter answers most "already visited?" checks without touching the
+//! disk. Page results are written only to the store and read back from it
+//! when the outputs are written, and the HTTP cache of incremental crawls
+//! keeps its entries in the store as well. The map-only inventory and the
+//! duplicate index stay in memory: those grow with the pages crawled.
+//!
+//! The store is also the checkpoint: every visit and result is tagged with
+//! the checkpoint generation (epoch) it happened in. Saving a checkpoint
+//! seals the current epoch and flushes; resuming rolls back what happened
+//! after the sealed epoch, so `checkpoint.json` only has to list in-flight
+//! URLs.
+
+use crate::crawler::checkpoint::PendingUrl;
+use crate::PageResult;
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const STORE_DIR: &str = "crawl-store";
+const EPOCH_KEY: &[u8] = b"epoch";
+
+/// False positive rate of the visited bloom filter at its expected size
+const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Disk-backed state settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StoreConfig {
+	/// Keep the visited set, pending URLs, referrers and frontier overflow on disk
+	pub enabled: bool,
+
+	/// Jobs the frontier holds in memory; the rest wait on disk
+	pub max_queued_in_memory: usize,
+
+	/// URLs the bloom filter is sized for (more only raise its false positive
+	/// rate, answered by the disk)
+	pub expected_urls: usize,
+}
+
+impl Default for StoreConfig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			max_queued_in_memory: 10_000,
+			expected_urls: 1_000_000,
+		}
+	}
+}
+
+/// Fixed-size set of hashes: "no" is certain, "yes" may be wrong
+struct BloomFilter {
+	bits: Vec<AtomicU64>,
+	hashes: u64,
+}
+
+impl BloomFilter {
+	fn new(expected: usize, false_positive_rate: f64) -> Self {
+		let expected = expected.max(1) as f64;
+		let ln2 = std::f64::consts::LN_2;
+		let bits = (-expected * false_positive_rate.ln() / (ln2 * ln2)).ceil() as usize;
+		let words = bits.div_ceil(64).max(1);
+		let hashes = ((words * 64) as f64 / expected * ln2).round().clamp(1.0, 16.0) as u64;
+
+		Self {
+			bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+			hashes,
+		}
+	}
+
+	fn insert(&self, key: &[u8]) {
+		for bit in self.positions(key) {
+			self.bits[bit / 64].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+		}
+	}
+
+	fn might_contain(&self, key: &[u8]) -> bool {
+		self.positions(key)
+			.all(|bit| self.bits[bit / 64].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0)
+	}
+
+	fn clear(&self) {
+		for word in &self.bits {
+			word.store(0, Ordering::Relaxed);
+		}
+	}
+
+	/// Bit positions of a key, by double hashing
+	fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+		let hash = |seed: u64| {
+			let mut hasher = DefaultHasher::new();
+			seed.hash(&mut hasher);
+			key.hash(&mut hasher);
+			hasher.finish()
+		};
+		let (first, second) = (hash(0), hash(1) | 1);
+		let len = (self.bits.len() * 64) as u64;
+		(0..self.hashes).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+	}
+}
+
+/// A visited URL, with the epoch it was visited in and how it was queued
+/// (to queue it again if the visit is rolled back)
+#[derive(Serialize, Deserialize)]
+struct VisitedEntry {
+	epoch: u64,
+	url: PendingUrl,
+}
+
+/// Visited and pending URLs, referrers, results and frontier overflow, on disk
+pub struct DiskStore {
+	db: sled::Db,
+	visited: sled::Tree,
+	pending: sled::Tree,
+	queue: sled::Tree,
+	referrers: sled::Tree,
+	/// Page results keyed by epoch, then by the order they were stored in
+	results: sled::Tree,
+	/// HTTP cache entries of the previous crawl and of this one, by URL
+	cache_previous: sled::Tree,
+	cache_current: sled::Tree,
+	bloom: BloomFilter,
+	epoch: AtomicU64,
+	next_result: AtomicU64,
+}
+
+impl DiskStore {
+	/// Opens (or creates) the store in `output_dir`
+	pub fn open(output_dir: &Path, config: &StoreConfig) -> Result<Self> {
+		let db = sled::open(output_dir.join(STORE_DIR))?;
+		let visited = db.open_tree("visited")?;
+		let pending = db.open_tree("pending")?;
+		let queue = db.open_tree("queue")?;
+		let referrers = db.open_tree("referrers")?;
+		let results = db.open_tree("results")?;
+		let cache_previous = db.open_tree("http-cache-previous")?;
+		let cache_current = db.open_tree("http-cache-current")?;
+
+		let epoch = db
+			.get(EPOCH_KEY)?
+			.and_then(|bytes| bytes.as_ref().try_into().ok())
+			.map_or(0, u64::from_be_bytes);
+
+		let bloom = BloomFilter::new(config.expected_urls, BLOOM_FALSE_POSITIVE_RATE);
+		for key in visited.iter().keys() {
+			bloom.insert(&key?);
+		}
+
+		let next_result = match results.last()? {
+			Some((key, _)) => result_seq(&key) + 1,
+			None => 0,
+		};
+
+		Ok(Self {
+			db,
+			visited,
+			pending,
+			queue,
+			referrers,
+			results,
+			cache_previous,
+			cache_current,
+			bloom,
+			epoch: AtomicU64::new(epoch),
+			next_result: AtomicU64::new(next_result),
+		})
+	}
+
+	/// Tree the frontier spills its overflow into
+	pub fn queue_tree(&self) -> sled::Tree {
+		self.queue.clone()
+	}
+
+	/// Trees the HTTP cache keeps the previous crawl's entries and this
+	/// crawl's in; the cache resets them itself when it opens
+	pub fn http_cache_trees(&self) -> (sled::Tree, sled::Tree) {
+		(self.cache_previous.clone(), self.cache_current.clone())
+	}
+
+	/// Forgets everything but the HTTP cache, for a fresh crawl
+	pub fn clear(&self) -> Result<()> {
+		self.visited.clear()?;
+		self.pending.clear()?;
+		self.queue.clear()?;
+		self.referrers.clear()?;
+		self.results.clear()?;
+		self.bloom.clear();
+		Ok(())
+	}
+
+	/// Seals the current epoch for a checkpoint and flushes it to disk,
+	/// returning the sealed epoch
+	pub fn seal(&self) -> Result<u64> {
+		let sealed = self.epoch.fetch_add(1, Ordering::SeqCst);
+		self.db.insert(EPOCH_KEY, &(sealed + 1).to_be_bytes())?;
+		self.db.flush()?;
+		Ok(sealed)
+	}
+
+	/// Goes back to the checkpoint sealed at `epoch`: URLs visited after it,
+	/// and the ones in flight when it was taken, are pending again, and their
+	/// results are dropped. The frontier overflow is dropped too, as it is
+	/// rebuilt from the pending URLs.
+	pub fn rollback(&self, epoch: u64, in_flight: &[PendingUrl]) -> Result<()> {
+		self.queue.clear()?;
+
+		for key in self.results.range((epoch + 1).to_be_bytes()..).keys() {
+			self.results.remove(key?)?;
+		}
+
+		for item in self.visited.iter() {
+			let (key, value) = item?;
+			let entry: VisitedEntry = serde_json::from_slice(&value)?;
+			if entry.epoch > epoch {
+				self.visited.remove(&key)?;
+				self.pending.insert(key, serde_json::to_vec(&entry.url)?)?;
+			}
+		}
+
+		for url in in_flight {
+			self.visited.remove(url.url.as_bytes())?;
+			self.pending.insert(url.url.as_bytes(), serde_json::to_vec(url)?)?;
+		}
+		Ok(())
+	}
+
+	/// Saves a page result under the current epoch
+	pub fn add_result(&self, page: &PageResult) -> Result<()> {
+		let mut key = self.epoch.load(Ordering::SeqCst).to_be_bytes().to_vec();
+		key.extend_from_slice(&self.next_result.fetch_add(1, Ordering::SeqCst).to_be_bytes());
+		self.results.insert(key, serde_json::to_vec(page)?)?;
+		Ok(())
+	}
+
+	/// Page results in the order they were saved
+	pub fn results(&self) -> impl Iterator<Item = PageResult> + '_ {
+		self.results
+			.iter()
+			.values()
+			.filter_map(|value| value.ok().and_then(|value| serde_json::from_slice(&value).ok()))
+	}
+
+	/// Pending URLs, read from disk as the iterator advances
+	pub fn pending_urls(&self) -> impl Iterator<Item = PendingUrl> + '_ {
+		self.pending
+			.iter()
+			.values()
+			.filter_map(|value| value.ok().and_then(|value| serde_json::from_slice(&value).ok()))
+	}
+}
+
+/// Sequence number of a result key (after its 8-byte epoch)
+fn result_seq(key: &[u8]) -> u64 {
+	key.get(8..16)
+		.and_then(|bytes| bytes.try_into().ok())
+		.map_or(0, u64::from_be_bytes)
+}
+
+/// Reports a store failure; the crawl goes on with a possibly stale answer
+pub(crate) fn checked<T>(result: sled::Result<T>) -> Option<T> {
+	result
+		.map_err(|e| eprintln!("Warning: Crawl store error: {}", e))
+		.ok()
+}
+
+/// URLs already taken off the frontier
+pub enum VisitedSet {
+	Memory(DashMap<String, ()>),
+	Disk(Arc<DiskStore>),
+}
+
+impl VisitedSet {
+	pub fn new(store: Option<Arc<DiskStore>>) -> Self {
+		match store {
+			Some(store) => Self::Disk(store),
+			None => Self::Memory(DashMap::new()),
+		}
+	}
+
+	pub fn contains(&self, url: &str) -> bool {
+		match self {
+			Self::Memory(urls) => urls.contains_key(url),
+			Self::Disk(store) => {
+				store.bloom.might_contain(url.as_bytes())
+					&& checked(store.visited.contains_key(url)).unwrap_or(false)
+			}
+		}
+	}
+
+	/// Marks `url.url` visited, returning whether it was not already
+	pub fn insert(&self, url: PendingUrl) -> bool {
+		match self {
+			Self::Memory(urls) => urls.insert(url.url, ()).is_none(),
+			Self::Disk(store) => {
+				let key = url.url.clone();
+				let entry = VisitedEntry {
+					epoch: store.epoch.load(Ordering::SeqCst),
+					url,
+				};
+				let value = serde_json::to_vec(&entry).expect("visited entry serializes");
+				store.bloom.insert(key.as_bytes());
+				// Keep the first visit's epoch
+				checked(store.visited.compare_and_swap(key, None as Option<&[u8]>, Some(value)))
+					.is_none_or(|swapped| swapped.is_ok())
+			}
+		}
+	}
+
+	pub fn remove(&self, url: &str) {
+		match self {
+			Self::Memory(urls) => {
+				urls.remove(url);
+			}
+			Self::Disk(store) => {
+				checked(store.visited.remove(url));
+			}
+		}
+	}
+
+	/// Marks the URLs listed by an in-memory checkpoint as visited
+	pub fn restore(&self, urls: HashSet<String>) {
+		if let Self::Memory(visited) = self {
+			for url in urls {
+				visited.insert(url, ());
+			}
+		}
+	}
+
+	/// Visited URLs (only listed in memory; the store keeps its own)
+	pub fn urls(&self) -> Vec<String> {
+		match self {
+			Self::Memory(urls) => urls.iter().map(|entry| entry.key().clone()).collect(),
+			Self::Disk(_) => Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Memory(urls) => urls.len(),
+			Self::Disk(store) => store.visited.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		match self {
+			Self::Memory(urls) => urls.is_empty(),
+			Self::Disk(store) => store.visited.is_empty(),
+		}
+	}
+}
+
+/// Pages linking to each discovered URL
+pub enum ReferrerIndex {
+	Memory(DashMap<String, Vec<String>>),
+	Disk(Arc<DiskStore>),
+}
+
+impl ReferrerIndex {
+	pub fn new(store: Option<Arc<DiskStore>>) -> Self {
+		match store {
+			Some(store) => Self::Disk(store),
+			None => Self::Memory(DashMap::new()),
+		}
+	}
+
+	/// Adds `referrer` to the inbound pages of `url`, keeping at most `max`,
+	/// and returns whether `url` was seen for the first time
+	pub fn record(&self, url: &str, referrer: &str, max: usize) -> bool {
+		let add = |referrers: &mut Vec<String>| {
+			if referrers.len() < max && !referrers.iter().any(|r| r == referrer) {
+				referrers.push(referrer.to_string());
+			}
+		};
+
+		match self {
+			Self::Memory(index) => {
+				let mut referrers = index.entry(url.to_string()).or_default();
+				let first_seen = referrers.is_empty();
+				add(&mut referrers);
+				first_seen
+			}
+			Self::Disk(store) => {
+				let mut first_seen = false;
+				checked(store.referrers.fetch_and_update(url.as_bytes(), |old| {
+					let mut referrers: Vec<String> = old
+						.and_then(|old| serde_json::from_slice(old).ok())
+						.unwrap_or_default();
+					first_seen = referrers.is_empty();
+					add(&mut referrers);
+					serde_json::to_vec(&referrers).ok()
+				}));
+				first_seen
+			}
+		}
+	}
+
+	/// Inbound pages recorded for `url`
+	pub fn get(&self, url: &str) -> Vec<String> {
+		match self {
+			Self::Memory(index) => index.get(url).map(|referrers| referrers.clone()).unwrap_or_default(),
+			Self::Disk(store) => checked(store.referrers.get(url))
+				.flatten()
+				.and_then(|value| serde_json::from_slice(&value).ok())
+				.unwrap_or_default(),
+		}
+	}
+}
+
+/// What `PendingSet::upsert` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upsert {
+	/// The URL was not pending
+	New,
+	/// The URL was pending with a lower score
+	Raised,
+	/// The URL was pending with at least this score
+	Kept,
+}
+
+/// URLs queued or waiting to be, by canonical URL
+pub enum PendingSet {
+	Memory(DashMap<String, PendingUrl>),
+	Disk(Arc<DiskStore>),
+}
+
+impl PendingSet {
+	pub fn new(store: Option<Arc<DiskStore>>) -> Self {
+		match store {
+			Some(store) => Self::Disk(store),
+			None => Self::Memory(DashMap::new()),
+		}
+	}
+
+	/// Adds a URL, or lowers the depth and raises the score of a pending one
+	/// (which keeps the seed it was queued from first)
+	pub fn upsert(&self, url: PendingUrl) -> Upsert {
+		let merge = |queued: &mut PendingUrl| {
+			queued.depth = queued.depth.min(url.depth);
+			if url.score > queued.score {
+				queued.score = url.score;
+				Upsert::Raised
+			} else {
+				Upsert::Kept
+			}
+		};
+
+		match self {
+			Self::Memory(urls) => match urls.entry(url.url.clone()) {
+				dashmap::mapref::entry::Entry::Occupied(mut entry) => merge(entry.get_mut()),
+				dashmap::mapref::entry::Entry::Vacant(entry) => {
+					entry.insert(url.clone());
+					Upsert::New
+				}
+			},
+			Self::Disk(store) => {
+				let mut outcome = Upsert::New;
+				checked(store.pending.fetch_and_update(url.url.as_bytes(), |old| {
+					let queued = match old.and_then(|old| serde_json::from_slice::<PendingUrl>(old).ok()) {
+						Some(mut queued) => {
+							outcome = merge(&mut queued);
+							queued
+						}
+						None => {
+							outcome = Upsert::New;
+							url.clone()
+						}
+					};
+					serde_json::to_vec(&queued).ok()
+				}));
+				outcome
+			}
+		}
+	}
+
+	/// Adds or replaces a URL
+	pub fn insert(&self, url: PendingUrl) {
+		match self {
+			Self::Memory(urls) => {
+				urls.insert(url.url.clone(), url);
+			}
+			Self::Disk(store) => {
+				let value = serde_json::to_vec(&url).expect("pending URL serializes");
+				checked(store.pending.insert(url.url.as_bytes(), value));
+			}
+		}
+	}
+
+	pub fn get(&self, url: &str) -> Option<PendingUrl> {
+		match self {
+			Self::Memory(urls) => urls.get(url).map(|entry| entry.value().clone()),
+			Self::Disk(store) => checked(store.pending.get(url))
+				.flatten()
+				.and_then(|value| serde_json::from_slice(&value).ok()),
+		}
+	}
+
+	pub fn remove(&self, url: &str) {
+		match self {
+			Self::Memory(urls) => {
+				urls.remove(url);
+			}
+			Self::Disk(store) => {
+				checked(store.pending.remove(url));
+			}
+		}
+	}
+
+	pub fn contains(&self, url: &str) -> bool {
+		match self {
+			Self::Memory(urls) => urls.contains_key(url),
+			Self::Disk(store) => checked(store.pending.contains_key(url)).unwrap_or(false),
+		}
+	}
+
+	/// Pending URLs (only listed in memory; the store keeps its own)
+	pub fn urls(&self) -> Vec<PendingUrl> {
+		match self {
+			Self::Memory(urls) => urls.iter().map(|entry| entry.value().clone()).collect(),
+			Self::Disk(_) => Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Memory(urls) => urls.len(),
+			Self::Disk(store) => store.pending.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		match self {
+			Self::Memory(urls) => urls.is_empty(),
+			Self::Disk(store) => store.pending.is_empty(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pending(url: &str, depth: usize, score: f64) -> PendingUrl {
+		PendingUrl { url: url.to_string(), depth, seed: 0, score, fetch_url: None }
+	}
+
+	#[test]
+	fn test_bloom_filter() {
+		let bloom = BloomFilter::new(1000, 0.01);
+		for i in 0..1000 {
+			bloom.insert(format!("https://example.com/{}", i).as_bytes());
+		}
+		assert!((0..1000).all(|i| bloom.might_contain(format!("https://example.com/{}", i).as_bytes())));
+
+		let false_positives = (1000..11000)
+			.filter(|i| bloom.might_contain(format!("https://example.com/{}", i).as_bytes()))
+			.count();
+		assert!(false_positives < 300, "{} false positives", false_positives);
+	}
+
+	#[test]
+	fn test_disk_sets_match_memory_sets() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = Arc::new(DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+
+		for (visited, queued) in [
+			(VisitedSet::new(None), PendingSet::new(None)),
+			(VisitedSet::new(Some(Arc::clone(&store))), PendingSet::new(Some(Arc::clone(&store)))),
+		] {
+			assert!(visited.insert(pending("https://a.com/", 0, 0.0)));
+			assert!(!visited.insert(pending("https://a.com/", 1, 0.0)));
+			assert!(visited.contains("https://a.com/"));
+			assert!(!visited.contains("https://a.com/other"));
+			visited.remove("https://a.com/");
+			assert!(visited.is_empty());
+
+			assert_eq!(queued.upsert(pending("https://a.com/b", 2, 1.0)), Upsert::New);
+			assert_eq!(queued.upsert(pending("https://a.com/b", 1, 0.5)), Upsert::Kept);
+			assert_eq!(queued.upsert(pending("https://a.com/b", 3, 2.0)), Upsert::Raised);
+			let b = queued.get("https://a.com/b").unwrap();
+			assert_eq!((b.depth, b.score), (1, 2.0));
+			queued.remove("https://a.com/b");
+			assert!(!queued.contains("https://a.com/b"));
+			assert_eq!(queued.len(), 0);
+		}
+	}
+
+	#[test]
+	fn test_disk_referrers_match_memory_referrers() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = Arc::new(DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+
+		for index in [ReferrerIndex::new(None), ReferrerIndex::new(Some(Arc::clone(&store)))] {
+			assert!(index.record("https://a.com/x", "https://a.com/1", 2));
+			assert!(!index.record("https://a.com/x", "https://a.com/1", 2));
+			index.record("https://a.com/x", "https://a.com/2", 2);
+			index.record("https://a.com/x", "https://a.com/3", 2);
+			assert_eq!(index.get("https://a.com/x"), vec!["https://a.com/1", "https://a.com/2"]);
+			assert!(index.get("https://a.com/y").is_empty());
+		}
+	}
+
+	#[test]
+	fn test_rollback_to_sealed_epoch() {
+		let dir = tempfile::tempdir().unwrap();
+		{
+			let store = Arc::new(DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+			let visited = VisitedSet::new(Some(Arc::clone(&store)));
+			visited.insert(pending("https://a.com/", 0, 0.0));
+			visited.insert(pending("https://a.com/busy", 1, 0.0));
+			store.add_result(&PageResult { url: "https://a.com/".to_string(), ..Default::default() }).unwrap();
+			let sealed = store.seal().unwrap();
+			assert_eq!(sealed, 0);
+
+			// Visited after the checkpoint, then the process dies
+			visited.insert(pending("https://a.com/late", 1, 0.0));
+			store.add_result(&PageResult { url: "https://a.com/late".to_string(), ..Default::default() }).unwrap();
+		}
+
+		let store = Arc::new(DiskStore::open(dir.path(), &StoreConfig::default()).unwrap());
+		store.rollback(0, &[pending("https://a.com/busy", 1, 0.0)]).unwrap();
+		let visited = VisitedSet::new(Some(Arc::clone(&store)));
+		assert!(visited.contains("https://a.com/"));
+		assert!(!visited.contains("https://a.com/busy"));
+		assert!(!visited.contains("https://a.com/late"));
+
+		let mut urls: Vec<String> = store.pending_urls().map(|url| url.url).collect();
+		urls.sort();
+		assert_eq!(urls, vec!["https://a.com/busy", "https://a.com/late"]);
+		let results: Vec<String> = store.results().map(|page| page.url).collect();
+		assert_eq!(results, vec!["https://a.com/"]);
+
+		// Visits after a rollback belong to a new epoch
+		assert_eq!(store.seal().unwrap(), 1);
+		store.clear().unwrap();
+		assert!(visited.is_empty());
+	}
+}
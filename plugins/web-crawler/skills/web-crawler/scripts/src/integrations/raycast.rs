@@ -13,11 +13,26 @@ pub fn format_for_raycast(results: &CrawlResults) -> String {
 	let stats = &results.stats;
 	
 	// Compact summary
-	let summary = format!(
-		"✅ Crawl complete: {} pages in {}ms",
-		stats.pages_crawled,
-		stats.duration.unwrap_or(0)
-	);
+	let summary = if stats.interrupted {
+		format!(
+			"⚠️ Crawl interrupted: {} pages in {}ms (partial results)",
+			stats.pages_crawled,
+			stats.duration.unwrap_or(0)
+		)
+	} else if let Some(limit) = stats.stopped_by {
+		format!(
+			"⚠️ Crawl stopped by {} budget: {} pages in {}ms (partial results)",
+			limit,
+			stats.pages_crawled,
+			stats.duration.unwrap_or(0)
+		)
+	} else {
+		format!(
+			"✅ Crawl complete: {} pages in {}ms",
+			stats.pages_crawled,
+			stats.duration.unwrap_or(0)
+		)
+	};
 
 	// Top 10 pages only for Raycast
 	let top_pages: Vec<RaycastPage> = results.results
@@ -86,6 +101,16 @@ mod tests {
 				start_time: Utc::now(),
 				end_time: Some(Utc::now()),
 				duration: Some(1000),
+				interrupted: false,
+				stopped_by: None,
+				bytes_downloaded: 0,
+				host_budget_skips: 0,
+				duplicates: 0,
+				new_pages: 0,
+				changed_pages: 0,
+				unchanged_pages: 0,
+				removed_pages: 0,
+				concurrency: None,
 			},
 			results: vec![
 				PageResult {
@@ -97,8 +122,10 @@ mod tests {
 					error: None,
 					crawled_at: Utc::now(),
 					content_type: "text/html".to_string(),
+					..Default::default()
 				}
 			],
+			..Default::default()
 		};
 
 		let output = format_for_raycast(&results);
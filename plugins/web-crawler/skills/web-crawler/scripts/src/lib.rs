@@ -4,6 +4,16 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::crawler::budget::{BudgetLimit, CrawlBudget};
+use crate::crawler::concurrency::{AdaptiveConfig, ConcurrencyStats};
+use crate::crawler::dedup::{DedupConfig, DuplicateKind};
+use crate::crawler::frontier::CrawlStrategy;
+use crate::crawler::priority::PriorityConfig;
+use crate::crawler::retry::RetryPolicy;
+use crate::crawler::store::StoreConfig;
+use crate::services::content_filter::FilterStats;
+use crate::utils::url::UrlNormalizerConfig;
+
 // Module exports
 pub mod config;
 pub mod crawler;
@@ -23,15 +33,36 @@ pub struct CrawlerConfig {
 	/// Restrict crawling to this domain (optional)
 	pub allowed_domain: Option<String>,
 
+	/// Starting URLs, each with its own domain scope (empty = `base_url`
+	/// scoped to `allowed_domain`)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub seeds: Vec<Seed>,
+
 	/// Maximum crawl depth
 	pub max_depth: usize,
 
-	/// Number of concurrent workers
+	/// Order in which queued URLs are crawled
+	#[serde(default)]
+	pub strategy: CrawlStrategy,
+
+	/// Scoring of the priority strategy
+	#[serde(default)]
+	pub priority: PriorityConfig,
+
+	/// Number of concurrent workers (the starting point with adaptive concurrency)
 	pub max_workers: usize,
 
-	/// Rate limit (requests per second)
+	/// Adjust concurrency to server latency and overload during the crawl
+	#[serde(default)]
+	pub adaptive: AdaptiveConfig,
+
+	/// Rate limit (requests per second, per host)
 	pub rate_limit: f64,
 
+	/// Maximum concurrent requests per host (0 = unlimited)
+	#[serde(default)]
+	pub max_per_host: usize,
+
 	/// Output directory for results
 	pub output_dir: PathBuf,
 
@@ -44,9 +75,62 @@ pub struct CrawlerConfig {
 	/// HTTP request timeout in seconds
 	pub timeout: u64,
 
-	/// Respect robots.txt rules
+	/// Retries for timeouts, connection errors and retryable statuses
+	#[serde(default)]
+	pub retry: RetryPolicy,
+
+	/// Maximum redirects followed per page
+	#[serde(default = "default_max_redirects")]
+	pub max_redirects: usize,
+
+	/// Largest response body read, in bytes (0 = unlimited)
+	#[serde(default = "default_max_body_bytes")]
+	pub max_body_bytes: u64,
+
+	/// Content types whose bodies are downloaded (`text/*` style wildcards
+	/// allowed, empty = all)
+	#[serde(default = "default_content_types")]
+	pub allowed_content_types: Vec<String>,
+
+	/// Limits on pages, bytes, time and pages per host for this run
+	#[serde(default)]
+	pub budget: CrawlBudget,
+
+	/// Respect robots.txt rules, meta robots tags and X-Robots-Tag headers
 	pub respect_robots_txt: bool,
 
+	/// Store converted Markdown on each page result
+	#[serde(default)]
+	pub convert_markdown: bool,
+
+	/// Only discover URLs (sitemaps + shallow link walk), skip post-processing
+	#[serde(default)]
+	pub map_only: bool,
+
+	/// Resume from the checkpoint in `output_dir` if one matches this config
+	#[serde(default)]
+	pub resume: bool,
+
+	/// Seconds between checkpoint saves (0 = disabled)
+	#[serde(default)]
+	pub checkpoint_interval: u64,
+
+	/// Keep the visited set and frontier on disk instead of in memory
+	#[serde(default)]
+	pub store: StoreConfig,
+
+	/// Re-crawl with conditional requests, using the HTTP cache in `output_dir`
+	#[serde(default)]
+	pub incremental: bool,
+
+	/// How URLs are canonicalized before deduplication
+	#[serde(default)]
+	pub url_normalization: UrlNormalizerConfig,
+
+	/// Exact and near-duplicate content detection
+	#[serde(default)]
+	pub dedup: DedupConfig,
+
 	/// URL patterns to exclude (regex)
 	pub exclude_patterns: Vec<String>,
 
@@ -54,6 +138,59 @@ pub struct CrawlerConfig {
 	pub include_patterns: Vec<String>,
 }
 
+impl CrawlerConfig {
+	/// Seeds of the crawl: `seeds`, or the single `base_url` seed
+	pub fn seeds(&self) -> Vec<Seed> {
+		if self.seeds.is_empty() {
+			vec![Seed {
+				url: self.base_url.clone(),
+				allowed_domain: self.allowed_domain.clone(),
+			}]
+		} else {
+			self.seeds.clone()
+		}
+	}
+}
+
+/// Starting URL and the domain its crawl is restricted to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Seed {
+	pub url: String,
+
+	/// Domain (and its subdomains) links from this seed may stay on
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub allowed_domain: Option<String>,
+}
+
+impl Seed {
+	/// Seed for `url`, scoped to `domain` or else to the URL's own domain
+	pub fn new(url: impl Into<String>, domain: Option<String>) -> Self {
+		let url = url.into();
+		let allowed_domain = domain.or_else(|| {
+			url::Url::parse(&url)
+				.ok()
+				.and_then(|url| url.domain().map(|d| d.to_string()))
+		});
+		Self { url, allowed_domain }
+	}
+}
+
+fn default_max_redirects() -> usize {
+	10
+}
+
+fn default_max_body_bytes() -> u64 {
+	10 * 1024 * 1024
+}
+
+fn default_content_types() -> Vec<String> {
+	crate::crawler::body::DEFAULT_CONTENT_TYPES
+		.iter()
+		.map(|t| t.to_string())
+		.collect()
+}
+
 /// Predefined crawl profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrawlProfile {
@@ -62,11 +199,14 @@ pub struct CrawlProfile {
 	pub max_depth: usize,
 	pub max_workers: usize,
 	pub rate_limit: f64,
+	pub max_per_host: usize,
 	pub timeout: u64,
+	pub retry: RetryPolicy,
+	pub adaptive: AdaptiveConfig,
 }
 
 /// Result from crawling a single page
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PageResult {
 	/// Page URL
@@ -81,18 +221,360 @@ pub struct PageResult {
 	/// Crawl depth from base URL
 	pub depth: usize,
 
+	/// Seed URL the page was reached from
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub seed: Option<String>,
+
 	/// Links found on the page
-	pub links: Vec<String>,
+	pub links: Vec<PageLink>,
+
+	/// URL declared by `<link rel="canonical">`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub canonical_url: Option<String>,
+
+	/// URL the request ended up at, when redirected
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub final_url: Option<String>,
+
+	/// Redirects followed from `url` to `final_url`
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub redirects: Vec<RedirectHop>,
+
+	/// Problem with the redirect chain, if any
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub redirect_issue: Option<RedirectIssue>,
+
+	/// Indexing directives from meta robots tags and X-Robots-Tag headers
+	#[serde(default, skip_serializing_if = "RobotsDirectives::is_empty")]
+	pub robots: RobotsDirectives,
 
 	/// Error message if crawl failed
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub error: Option<String>,
 
+	/// Category of the failure, when `error` is set
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error_kind: Option<ErrorKind>,
+
+	/// Page the URL was first discovered on (None for seeds and sitemap URLs)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub referrer: Option<String>,
+
+	/// Crawled pages linking to this URL (filled in for failed pages)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub linked_from: Vec<String>,
+
 	/// Timestamp when page was crawled
 	pub crawled_at: DateTime<Utc>,
 
+	/// Number of fetch attempts (more than 1 when retried)
+	#[serde(default)]
+	pub attempts: u32,
+
 	/// Content-Type header
 	pub content_type: String,
+
+	/// Encoding the body was decoded with (e.g. "UTF-8", "Shift_JIS")
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub encoding: Option<String>,
+
+	/// ETag header, sent back as `If-None-Match` on incremental re-crawls
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub etag: Option<String>,
+
+	/// Last-Modified header, sent back as `If-Modified-Since`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_modified: Option<String>,
+
+	/// How the page compares with the previous crawl (incremental crawls)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub change: Option<PageChange>,
+
+	/// Cleaned Markdown content (when Markdown conversion is enabled)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub markdown: Option<String>,
+
+	/// Word count of the cleaned content
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub word_count: Option<usize>,
+
+	/// Content filtering statistics
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub filter_stats: Option<FilterStats>,
+
+	/// Hash of the normalized page text (hex)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub content_hash: Option<String>,
+
+	/// SimHash of the page text (hex), for near-duplicate detection
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub simhash: Option<String>,
+
+	/// Canonical page this page duplicates
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub duplicate_of: Option<String>,
+
+	/// Whether the duplicate is exact or near, when `duplicate_of` is set
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub duplicate_kind: Option<DuplicateKind>,
+
+	/// Duplicates folded into this page when outputs collapse them
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub duplicates: Vec<String>,
+}
+
+impl PageResult {
+	/// Canonical URL, when it points to another page
+	pub fn canonical_elsewhere(&self) -> Option<&str> {
+		self.canonical_url.as_deref().filter(|canonical| *canonical != self.url)
+	}
+}
+
+/// How a page compares with the previous incremental crawl
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageChange {
+	/// Not in the previous crawl
+	New,
+	/// Content differs from the previous crawl
+	Changed,
+	/// Not modified (304) or same content hash
+	Unchanged,
+}
+
+/// One redirect followed while fetching a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedirectHop {
+	/// URL that answered with a redirect
+	pub url: String,
+
+	/// Redirect status code (301, 302, 303, 307 or 308)
+	pub status: u16,
+
+	/// Location header, as sent by the server
+	pub location: String,
+}
+
+/// Why a redirect chain is flagged in reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RedirectIssue {
+	/// The chain came back to a URL it already visited
+	Loop,
+	/// More redirects than `max_redirects`
+	TooManyRedirects,
+	/// The page was reached, but through several redirects
+	LongChain,
+	/// The chain left the allowed domain and was not followed further
+	OutOfScope,
+}
+
+impl std::fmt::Display for RedirectIssue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let label = match self {
+			Self::Loop => "redirect loop",
+			Self::TooManyRedirects => "too many redirects",
+			Self::LongChain => "long redirect chain",
+			Self::OutOfScope => "redirect out of scope",
+		};
+		f.write_str(label)
+	}
+}
+
+/// Page-level indexing directives (`<meta name="robots">`, `X-Robots-Tag`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RobotsDirectives {
+	/// Page content should not be indexed
+	#[serde(default)]
+	pub noindex: bool,
+
+	/// Links on the page should not be followed
+	#[serde(default)]
+	pub nofollow: bool,
+}
+
+impl RobotsDirectives {
+	pub fn is_empty(&self) -> bool {
+		!self.noindex && !self.nofollow
+	}
+
+	/// Adds a comma-separated directive list such as `noindex, nofollow`
+	pub fn add(&mut self, directives: &str) {
+		for directive in directives.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+			match directive.as_str() {
+				"noindex" => self.noindex = true,
+				"nofollow" => self.nofollow = true,
+				"none" => {
+					self.noindex = true;
+					self.nofollow = true;
+				}
+				_ => {}
+			}
+		}
+	}
+
+	/// Adds an X-Robots-Tag value, which may be scoped to one crawler
+	/// (`googlebot: noindex`); other crawlers' directives are ignored
+	pub fn add_header(&mut self, value: &str, product: &str) {
+		if let Some((agent, directives)) = value.split_once(':') {
+			let agent = agent.trim();
+			// `unavailable_after: <date>` is a directive, not an agent
+			if !agent.contains(',') && !agent.eq_ignore_ascii_case("unavailable_after") {
+				if agent.eq_ignore_ascii_case(product) {
+					self.add(directives);
+				}
+				return;
+			}
+		}
+		self.add(value);
+	}
+}
+
+/// Link found on a page, with the context it appeared in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "StoredLink")]
+pub struct PageLink {
+	/// Absolute URL the link points to
+	pub href: String,
+
+	/// Anchor text (or title/alt for elements without text)
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub text: String,
+
+	/// `rel` attribute, as written
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub rel: Option<String>,
+
+	/// Element the link came from
+	pub element: LinkElement,
+
+	/// Part of the page the link sits in
+	pub location: LinkLocation,
+}
+
+impl PageLink {
+	/// Plain `<a>` link in the page body
+	pub fn new(href: impl Into<String>) -> Self {
+		Self {
+			href: href.into(),
+			text: String::new(),
+			rel: None,
+			element: LinkElement::A,
+			location: LinkLocation::Body,
+		}
+	}
+
+	/// Checks if `rel` contains the given token (case-insensitive)
+	pub fn has_rel(&self, token: &str) -> bool {
+		self.rel
+			.as_deref()
+			.is_some_and(|rel| rel.split_whitespace().any(|t| t.eq_ignore_ascii_case(token)))
+	}
+
+	/// Links a crawler follows: anchors, image map areas and iframes, but
+	/// not `<link>` resources such as stylesheets and icons
+	pub fn is_navigable(&self) -> bool {
+		self.element != LinkElement::Link
+	}
+}
+
+/// `PageLink` as found in result files: older ones list bare hrefs
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredLink {
+	Href(String),
+	#[serde(rename_all = "camelCase")]
+	Link {
+		href: String,
+		#[serde(default)]
+		text: String,
+		#[serde(default)]
+		rel: Option<String>,
+		element: LinkElement,
+		location: LinkLocation,
+	},
+}
+
+impl From<StoredLink> for PageLink {
+	fn from(stored: StoredLink) -> Self {
+		match stored {
+			StoredLink::Href(href) => Self::new(href),
+			StoredLink::Link { href, text, rel, element, location } => Self {
+				href,
+				text,
+				rel,
+				element,
+				location,
+			},
+		}
+	}
+}
+
+/// HTML element a link was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkElement {
+	A,
+	Link,
+	Area,
+	Iframe,
+}
+
+/// Where on the page a link was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkLocation {
+	/// Document head (`<link>` elements)
+	Head,
+	/// Navigation or page header
+	Nav,
+	/// Main page content
+	Body,
+	/// Page footer
+	Footer,
+}
+
+/// Why a page could not be crawled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+	/// Host name could not be resolved
+	Dns,
+	/// TLS handshake or certificate failure
+	Tls,
+	/// Request or response timed out
+	Timeout,
+	/// Connection refused or reset
+	Connection,
+	/// Server answered with a non-2xx status
+	HttpStatus,
+	/// Response body could not be read or decoded
+	BodyDecode,
+	/// Redirect loop or too many redirects
+	Redirect,
+	/// Response body larger than `max_body_bytes`
+	TooLarge,
+	/// Any other request failure
+	Request,
+}
+
+impl std::fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let label = match self {
+			Self::Dns => "DNS",
+			Self::Tls => "TLS",
+			Self::Timeout => "timeout",
+			Self::Connection => "connection",
+			Self::HttpStatus => "HTTP status",
+			Self::BodyDecode => "body decode",
+			Self::Redirect => "redirect",
+			Self::TooLarge => "body too large",
+			Self::Request => "request",
+		};
+		f.write_str(label)
+	}
 }
 
 /// Statistics for the entire crawl
@@ -124,13 +606,85 @@ pub struct CrawlStats {
 	/// Total duration in milliseconds
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub duration: Option<u64>,
+
+	/// Crawl was stopped by a signal before the frontier was drained
+	#[serde(default)]
+	pub interrupted: bool,
+
+	/// Budget that stopped the crawl before the frontier was drained
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub stopped_by: Option<BudgetLimit>,
+
+	/// Response body bytes downloaded
+	#[serde(default)]
+	pub bytes_downloaded: u64,
+
+	/// URLs skipped because their host used up its page budget
+	#[serde(default)]
+	pub host_budget_skips: usize,
+
+	/// Pages whose content duplicates an earlier page
+	#[serde(default)]
+	pub duplicates: usize,
+
+	/// Pages not in the previous crawl (incremental crawls)
+	#[serde(default)]
+	pub new_pages: usize,
+
+	/// Pages whose content changed since the previous crawl
+	#[serde(default)]
+	pub changed_pages: usize,
+
+	/// Pages unchanged since the previous crawl
+	#[serde(default)]
+	pub unchanged_pages: usize,
+
+	/// Pages of the previous crawl that were not found again
+	#[serde(default)]
+	pub removed_pages: usize,
+
+	/// Adaptive concurrency decisions (when enabled)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub concurrency: Option<ConcurrencyStats>,
 }
 
-/// Complete crawl results
+/// How a URL was first discovered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoverySource {
+	/// Starting URL of the crawl
+	Seed,
+	/// Listed in a sitemap
+	Sitemap,
+	/// Linked from a crawled page
+	Link,
+	/// Final URL of a redirect
+	Redirect,
+}
+
+/// Entry of the URL inventory built in map-only mode
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MappedUrl {
+	/// Canonicalized URL
+	pub url: String,
+
+	/// Where the URL was first discovered
+	pub source: DiscoverySource,
+
+	/// Shallowest depth at which the URL was discovered
+	pub depth: usize,
+}
+
+/// Complete crawl results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CrawlResults {
 	pub stats: CrawlStats,
 	pub results: Vec<PageResult>,
+
+	/// Deduplicated URL inventory (map-only mode)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub map: Vec<MappedUrl>,
 }
 
 /// Output format options
@@ -203,14 +757,32 @@ impl Default for CrawlerConfig {
 		Self {
 			base_url: String::new(),
 			allowed_domain: None,
+			seeds: Vec::new(),
 			max_depth: 2,
+			strategy: CrawlStrategy::default(),
+			priority: PriorityConfig::default(),
 			max_workers: 20,
+			adaptive: AdaptiveConfig::default(),
 			rate_limit: 2.0,
+			max_per_host: 0,
 			output_dir,
 			use_sitemap: true,
 			max_sitemap_urls: 1000,
 			timeout: 30,
+			retry: RetryPolicy::default(),
+			max_redirects: default_max_redirects(),
+			max_body_bytes: default_max_body_bytes(),
+			allowed_content_types: default_content_types(),
+			budget: CrawlBudget::default(),
 			respect_robots_txt: true,
+			convert_markdown: false,
+			map_only: false,
+			resume: false,
+			checkpoint_interval: 30,
+			store: StoreConfig::default(),
+			incremental: false,
+			url_normalization: UrlNormalizerConfig::default(),
+			dedup: DedupConfig::default(),
 			exclude_patterns: vec![
 				r"\.jpg$".to_string(),
 				r"\.png$".to_string(),
@@ -241,8 +813,23 @@ impl CrawlStats {
 			start_time: Utc::now(),
 			end_time: None,
 			duration: None,
+			interrupted: false,
+			stopped_by: None,
+			bytes_downloaded: 0,
+			host_budget_skips: 0,
+			duplicates: 0,
+			new_pages: 0,
+			changed_pages: 0,
+			unchanged_pages: 0,
+			removed_pages: 0,
+			concurrency: None,
 		}
 	}
+
+	/// Checks if the crawl was compared with a previous one
+	pub fn is_incremental(&self) -> bool {
+		self.new_pages + self.changed_pages + self.unchanged_pages + self.removed_pages > 0
+	}
 }
 
 impl Default for CrawlStats {
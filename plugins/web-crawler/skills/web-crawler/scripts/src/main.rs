@@ -1,18 +1,22 @@
+use anyhow::Context;
 use clap::Parser;
 use rcrawler::{
     config,
-    crawler::engine::CrawlEngine,
+    crawler::{engine::CrawlEngine, frontier::CrawlStrategy},
     integrations::raycast,
+    output::{diff::{self, CrawlDiff}, html},
     services::{
-        content_filter::{ContentFilterConfig, DefaultContentFilterService},
+        content_filter::{ContentFilterConfig, DefaultContentFilterService, ExtractionStrategy},
         markdown::{DefaultMarkdownService, MarkdownConfig},
         output_formatter::{DefaultOutputFormatterService, OutputFormat, OutputFormatterConfig},
         stealth::{DefaultStealthService, StealthConfig},
         ServiceContainer,
     },
-    utils::logger,
+    utils::{logger, url::TrailingSlash},
+    Seed,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
 use tracing::info;
 
@@ -20,25 +24,124 @@ use tracing::info;
 #[command(name = "rcrawler")]
 #[command(about = "High-performance web crawler in Rust", long_about = None)]
 struct Cli {
-    /// URL to crawl
-    url: String,
+    /// URLs to crawl (each seed is restricted to its own domain unless --domain is given)
+    #[arg(required_unless_present_any = ["diff", "seed_file"])]
+    urls: Vec<String>,
+
+    /// Read more seeds from a file ("-" for stdin): one URL per line,
+    /// optionally followed by the domain it is restricted to
+    #[arg(long, value_name = "PATH")]
+    seed_file: Option<PathBuf>,
+
+    /// Compare two results.json files instead of crawling (writes diff.json and diff.html)
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    diff: Vec<PathBuf>,
 
     /// Restrict to this domain
     #[arg(long)]
     domain: Option<String>,
 
-    /// Number of concurrent workers
+    /// Number of concurrent workers (the starting point with adaptive concurrency)
     #[arg(short, long)]
     workers: Option<usize>,
 
+    /// Adjust concurrency to server latency and 429/503/timeout rates
+    /// (on by default with the fast and gentle profiles)
+    #[arg(long, conflicts_with = "no_adaptive")]
+    adaptive: bool,
+
+    /// Keep the worker count fixed, even with a profile that adapts it
+    #[arg(long)]
+    no_adaptive: bool,
+
+    /// Lowest adaptive concurrency (implies --adaptive)
+    #[arg(long, conflicts_with = "no_adaptive")]
+    min_workers: Option<usize>,
+
+    /// Highest adaptive concurrency (implies --adaptive)
+    #[arg(long, conflicts_with = "no_adaptive")]
+    max_workers: Option<usize>,
+
     /// Maximum crawl depth
     #[arg(short = 'd', long)]
     depth: Option<usize>,
 
-    /// Rate limit (requests per second)
+    /// Crawl order: bfs, dfs or priority (default: bfs, or priority with --keywords)
+    #[arg(long)]
+    strategy: Option<CrawlStrategy>,
+
+    /// Keywords that make URLs and link text worth crawling first (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    keywords: Vec<String>,
+
+    /// Rate limit (requests per second, per host)
     #[arg(short, long)]
     rate: Option<f64>,
 
+    /// Maximum concurrent requests per host (0 for no cap)
+    #[arg(long)]
+    max_per_host: Option<usize>,
+
+    /// Attempts per URL, including the first (1 disables retries)
+    #[arg(long)]
+    max_attempts: Option<u32>,
+
+    /// Delay before the first retry in milliseconds (doubled on each retry)
+    #[arg(long)]
+    retry_backoff: Option<u64>,
+
+    /// Maximum redirects followed per page
+    #[arg(long)]
+    max_redirects: Option<usize>,
+
+    /// Largest response body read, in bytes (0 for no limit)
+    #[arg(long)]
+    max_body_bytes: Option<u64>,
+
+    /// Stop after fetching this many pages
+    #[arg(long)]
+    max_pages: Option<usize>,
+
+    /// Stop after downloading this many response body bytes
+    #[arg(long)]
+    max_bytes: Option<u64>,
+
+    /// Stop after this many seconds of crawling
+    #[arg(long, value_name = "SECS")]
+    max_time: Option<u64>,
+
+    /// Fetch at most this many pages from any one host
+    #[arg(long)]
+    max_pages_per_host: Option<usize>,
+
+    /// Content types to download (comma-separated, e.g. text/html,text/*)
+    #[arg(long, value_delimiter = ',')]
+    content_types: Vec<String>,
+
+    /// Trailing slash handling when deduplicating URLs (strip, add, keep)
+    #[arg(long)]
+    trailing_slash: Option<TrailingSlash>,
+
+    /// Extra query parameters to strip from URLs (comma-separated, `*` suffix for prefixes)
+    #[arg(long, value_delimiter = ',')]
+    strip_params: Vec<String>,
+
+    /// Disable duplicate content detection
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// Largest SimHash distance for near duplicates (0-3 bits, 0 for exact only)
+    #[arg(long)]
+    near_duplicate_distance: Option<u32>,
+
+    /// Do not follow links found on duplicate pages
+    #[arg(long)]
+    skip_duplicate_links: bool,
+
+    /// Fold duplicate pages into their canonical page in JSON and Markdown outputs
+    #[arg(long)]
+    collapse_duplicates: bool,
+
     /// Profile (fast, deep, gentle)
     #[arg(short, long)]
     profile: Option<String>,
@@ -63,6 +166,10 @@ struct Cli {
     #[arg(long)]
     filter_content: bool,
 
+    /// Main content extraction (blacklist, readability, css:<selector>)
+    #[arg(long, default_value = "blacklist")]
+    extract: ExtractionStrategy,
+
     /// Convert HTML to Markdown (LLM-ready)
     #[arg(long)]
     markdown: bool,
@@ -75,9 +182,26 @@ struct Cli {
     #[arg(long)]
     resume: bool,
 
+    /// Seconds between checkpoint saves (0 to disable)
+    #[arg(long)]
+    checkpoint_interval: Option<u64>,
+
+    /// Keep visited URLs, the frontier and referrers on disk, for crawls with too many URLs for memory
+    #[arg(long)]
+    disk_store: bool,
+
+    /// Queued jobs kept in memory with --disk-store (the rest wait on disk)
+    #[arg(long, requires = "disk_store")]
+    max_queued: Option<usize>,
+
     /// Enable map-only mode (extract links without full crawl)
     #[arg(long)]
     map_only: bool,
+
+    /// Re-crawl incrementally: send conditional requests using the HTTP cache
+    /// kept in the output directory, and report new/changed/removed pages
+    #[arg(long)]
+    incremental: bool,
 }
 
 #[tokio::main]
@@ -87,9 +211,18 @@ async fn main() -> anyhow::Result<()> {
     // Initialize logger
     logger::init_logger(cli.debug);
 
+    if let [old, new] = cli.diff.as_slice() {
+        return run_diff(old, new, cli.output.as_deref());
+    }
+
+    let seeds = read_seeds(&cli)?;
+    if seeds.is_empty() {
+        anyhow::bail!("No seed URLs to crawl");
+    }
+
     // Build configuration
-    let config = config::build_config(
-        cli.url.clone(),
+    let mut config = config::build_config(
+        seeds[0].url.clone(),
         cli.domain.clone(),
         cli.workers,
         cli.depth,
@@ -98,32 +231,142 @@ async fn main() -> anyhow::Result<()> {
         cli.output.clone(),
         cli.sitemap,
     );
+    config.seeds = seeds;
+    config.convert_markdown = cli.markdown;
+    config.map_only = cli.map_only;
+    config.resume = cli.resume;
+    config.incremental = cli.incremental;
+    if let Some(min) = cli.min_workers {
+        config.adaptive.min_workers = min;
+    }
+    if let Some(max) = cli.max_workers {
+        config.adaptive.max_workers = max;
+    }
+    if cli.adaptive || cli.min_workers.is_some() || cli.max_workers.is_some() {
+        config.adaptive.enabled = true;
+    }
+    if cli.no_adaptive {
+        config.adaptive.enabled = false;
+    }
+    if let Some(max) = cli.max_per_host {
+        config.max_per_host = max;
+    }
+    if let Some(attempts) = cli.max_attempts {
+        config.retry.max_attempts = attempts.max(1);
+    }
+    if let Some(backoff) = cli.retry_backoff {
+        config.retry.backoff_base_ms = backoff;
+    }
+    if let Some(max) = cli.max_redirects {
+        config.max_redirects = max;
+    }
+    if let Some(max) = cli.max_body_bytes {
+        config.max_body_bytes = max;
+    }
+    config.strategy = match cli.strategy {
+        Some(strategy) => strategy,
+        None if !cli.keywords.is_empty() => CrawlStrategy::Priority,
+        None => config.strategy,
+    };
+    config.priority.keywords = cli.keywords.clone();
+    if let Some(max) = cli.max_pages {
+        config.budget.max_pages = max;
+    }
+    if let Some(max) = cli.max_bytes {
+        config.budget.max_bytes = max;
+    }
+    if let Some(secs) = cli.max_time {
+        config.budget.max_duration_secs = secs;
+    }
+    if let Some(max) = cli.max_pages_per_host {
+        config.budget.max_pages_per_host = max;
+    }
+    if !cli.content_types.is_empty() {
+        config.allowed_content_types = cli.content_types.clone();
+    }
+    if let Some(mode) = cli.trailing_slash {
+        config.url_normalization.trailing_slash = mode;
+    }
+    config
+        .url_normalization
+        .tracking_params
+        .extend(cli.strip_params.iter().cloned());
+    config.dedup.enabled = !cli.no_dedup;
+    config.dedup.expand_duplicates = !cli.skip_duplicate_links;
+    if let Some(distance) = cli.near_duplicate_distance {
+        config.dedup.max_distance = distance.min(3);
+    }
+    if let Some(interval) = cli.checkpoint_interval {
+        config.checkpoint_interval = interval;
+    }
+    if cli.disk_store {
+        config.store.enabled = true;
+    }
+    if let Some(max) = cli.max_queued {
+        config.store.max_queued_in_memory = max;
+    }
+    if cli.map_only && cli.depth.is_none() {
+        // Map mode is a shallow link walk unless a depth is given
+        config.max_depth = 1;
+    }
 
-    info!("Starting crawl of: {}", config.base_url);
+    if config.seeds.len() > 1 {
+        info!("Starting crawl of {} seeds, first: {}", config.seeds.len(), config.base_url);
+    } else {
+        info!("Starting crawl of: {}", config.base_url);
+    }
     info!(
         "Config: {} workers, depth {}",
         config.max_workers, config.max_depth
     );
+    if config.adaptive.enabled {
+        info!(
+            "Adaptive concurrency: {} to {} workers",
+            config.adaptive.min_workers, config.adaptive.max_workers
+        );
+    }
+    if config.budget.is_limited() {
+        info!("Budget: {:?}", config.budget);
+    }
+    if config.store.enabled {
+        info!("Disk store: {}", config.output_dir.join("crawl-store").display());
+    }
 
     // Build service container
     let services = build_services(&cli);
 
+    if cli.map_only {
+        info!("Map-only mode enabled");
+    }
     if cli.stealth {
         info!("Stealth mode enabled");
     }
     if cli.filter_content {
         info!("Content filtering enabled");
     }
+    if cli.extract != ExtractionStrategy::Blacklist {
+        info!("Content extraction: {:?}", cli.extract);
+    }
     if cli.markdown {
         info!("Markdown conversion enabled");
     }
 
-    // Create engine and crawl
-    let engine = CrawlEngine::new(config.clone())?;
-    let results = engine.crawl().await?;
+    // Create engine and crawl (pages are filtered and converted as they are fetched)
+    let engine = CrawlEngine::new(config.clone(), services.clone())?;
+
+    // First Ctrl-C / SIGTERM stops gracefully, a second one exits immediately
+    let signal_engine = engine.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        eprintln!("\nInterrupted: finishing in-flight requests (press Ctrl-C again to force exit)");
+        signal_engine.interrupt();
+
+        shutdown_signal().await;
+        eprintln!("Forced exit");
+        std::process::exit(130);
+    });
 
-    // Process results with services
-    let processed_results = process_results(&results, &services, &cli).await?;
+    let results = engine.crawl().await?;
 
     // Parse output formats
     let output_formats: Vec<OutputFormat> = cli
@@ -138,11 +381,12 @@ async fn main() -> anyhow::Result<()> {
         pretty_json: true,
         include_errors: true,
         max_links: None,
+        collapse_duplicates: cli.collapse_duplicates,
     };
 
     let outputs = services
         .output_formatter
-        .format_with_config(&processed_results, &formatter_config)
+        .format_with_config(&results, &formatter_config)
         .map_err(|e| anyhow::anyhow!(e))?;
 
     // Write output files
@@ -155,13 +399,37 @@ async fn main() -> anyhow::Result<()> {
     // Check if running in Raycast environment
     if raycast::is_raycast_env() {
         // Compact output for Raycast
-        let raycast_output = raycast::format_for_raycast(&processed_results);
+        let raycast_output = raycast::format_for_raycast(&results);
         println!("{}", raycast_output);
     } else {
         // Standard output
-        println!("\nCrawl complete!");
-        println!("Pages crawled: {}", processed_results.stats.pages_crawled);
-        if let Some(duration) = processed_results.stats.duration {
+        if results.stats.interrupted {
+            println!("\nCrawl interrupted, writing partial results");
+            if config.checkpoint_interval > 0 {
+                println!("Run again with --resume to continue");
+            }
+        } else if let Some(limit) = results.stats.stopped_by {
+            println!("\nCrawl stopped by the {} budget, writing partial results", limit);
+            if config.checkpoint_interval > 0 {
+                println!("Run again with --resume to continue");
+            }
+        } else {
+            println!("\nCrawl complete!");
+        }
+        println!("Pages crawled: {}", results.stats.pages_crawled);
+        if cli.map_only {
+            println!("URLs mapped: {}", results.map.len());
+        }
+        if cli.incremental {
+            println!(
+                "Changes: {} new, {} changed, {} unchanged, {} removed",
+                results.stats.new_pages,
+                results.stats.changed_pages,
+                results.stats.unchanged_pages,
+                results.stats.removed_pages
+            );
+        }
+        if let Some(duration) = results.stats.duration {
             println!("Duration: {}ms", duration);
         }
 
@@ -175,6 +443,63 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Seeds from the command line followed by those of `--seed-file`
+fn read_seeds(cli: &Cli) -> anyhow::Result<Vec<Seed>> {
+    let mut seeds: Vec<Seed> = cli
+        .urls
+        .iter()
+        .map(|url| Seed::new(url.clone(), cli.domain.clone()))
+        .collect();
+
+    if let Some(path) = &cli.seed_file {
+        let text = if path.as_os_str() == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read seed file {}", path.display()))?
+        };
+        seeds.extend(config::parse_seeds(&text, cli.domain.as_deref()));
+    }
+
+    Ok(seeds)
+}
+
+/// Compares two crawls, writing diff.json and diff.html to `output` (by
+/// default next to the new results)
+fn run_diff(old: &Path, new: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let old_results = diff::load_results(old)?;
+    let new_results = diff::load_results(new)?;
+    let crawl_diff = CrawlDiff::between(&old_results, &new_results);
+
+    let output_dir = output
+        .or_else(|| new.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    std::fs::create_dir_all(&output_dir)?;
+
+    let json_path = output_dir.join("diff.json");
+    std::fs::write(&json_path, serde_json::to_string_pretty(&crawl_diff)?)?;
+    let html_path = output_dir.join("diff.html");
+    std::fs::write(&html_path, html::generate_diff_html(&crawl_diff, &new_results)?)?;
+
+    if crawl_diff.is_empty() {
+        println!("No differences");
+    } else {
+        println!("Added pages: {}", crawl_diff.added.len());
+        println!("Removed pages: {}", crawl_diff.removed.len());
+        println!("Status changes: {}", crawl_diff.status_changes.len());
+        println!("Title changes: {}", crawl_diff.title_changes.len());
+        println!("New broken links: {}", crawl_diff.new_broken_links.len());
+        println!("Content changes: {}", crawl_diff.content_changes.len());
+    }
+
+    println!("\nGenerated outputs:");
+    println!("  - json: {}", json_path.display());
+    println!("  - html: {}", html_path.display());
+
+    Ok(())
+}
+
 /// Build service container based on CLI options
 fn build_services(cli: &Cli) -> ServiceContainer {
     let mut builder = ServiceContainer::builder();
@@ -206,7 +531,10 @@ fn build_services(cli: &Cli) -> ServiceContainer {
 
     // Content filter service
     if cli.filter_content {
-        let filter_config = ContentFilterConfig::default();
+        let filter_config = ContentFilterConfig {
+            extraction: cli.extract.clone(),
+            ..Default::default()
+        };
         builder = builder.with_content_filter(Arc::new(
             DefaultContentFilterService::with_config(filter_config),
         ));
@@ -223,6 +551,7 @@ fn build_services(cli: &Cli) -> ServiceContainer {
             blacklist_classes: vec![],
             blacklist_tags: vec!["script".to_string(), "style".to_string()],
             whitelist_tags: vec![],
+            extraction: cli.extract.clone(),
         };
         builder = builder.with_content_filter(Arc::new(
             DefaultContentFilterService::with_config(filter_config),
@@ -230,22 +559,38 @@ fn build_services(cli: &Cli) -> ServiceContainer {
     }
 
     // Output formatter service
-    builder = builder.with_output_formatter(Arc::new(DefaultOutputFormatterService::new()));
+    let formatter_config = OutputFormatterConfig {
+        collapse_duplicates: cli.collapse_duplicates,
+        ..Default::default()
+    };
+    builder = builder.with_output_formatter(Arc::new(DefaultOutputFormatterService::with_config(
+        formatter_config,
+    )));
 
     builder.build()
 }
 
-/// Process results with services (filtering, markdown conversion, etc.)
-async fn process_results(
-    results: &rcrawler::CrawlResults,
-    _services: &ServiceContainer,
-    _cli: &Cli,
-) -> anyhow::Result<rcrawler::CrawlResults> {
-    let processed = results.clone();
-
-    // Note: In a real implementation, we would process each page's HTML
-    // For now, we just return the results as-is
-    // This would be integrated into the crawling engine itself
+/// Waits for Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
 
-    Ok(processed)
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
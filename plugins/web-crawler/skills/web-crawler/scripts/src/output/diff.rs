@@ -0,0 +1,307 @@
+//! Comparison of two crawls
+//!
+//! Compares two `results.json` files page by page to catch regressions
+//! between releases: pages that appeared or disappeared, status and title
+//! changes, newly broken links, and changes to the stored Markdown.
+
+use crate::{CrawlResults, PageResult};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Unchanged lines kept around each change in content diffs
+const CONTEXT_LINES: usize = 2;
+
+/// Differences between an old and a new crawl
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlDiff {
+	/// URLs only in the new crawl
+	pub added: Vec<String>,
+
+	/// URLs only in the old crawl
+	pub removed: Vec<String>,
+
+	/// Pages whose HTTP status changed
+	pub status_changes: Vec<StatusChange>,
+
+	/// Pages whose title changed
+	pub title_changes: Vec<TitleChange>,
+
+	/// Pages that fail in the new crawl but did not in the old one
+	pub new_broken_links: Vec<BrokenLink>,
+
+	/// Pages whose Markdown changed (when both crawls stored it)
+	pub content_changes: Vec<ContentChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+	pub url: String,
+	pub old_status: u16,
+	pub new_status: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleChange {
+	pub url: String,
+	pub old_title: String,
+	pub new_title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokenLink {
+	pub url: String,
+	pub status_code: u16,
+	pub error: String,
+	/// Pages of the new crawl linking to it
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub linked_from: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentChange {
+	pub url: String,
+	pub added_lines: usize,
+	pub removed_lines: usize,
+	/// Unified diff of the Markdown
+	pub diff: String,
+}
+
+impl CrawlDiff {
+	/// Compares two crawls, matching pages by URL
+	pub fn between(old: &CrawlResults, new: &CrawlResults) -> Self {
+		let old_pages = by_url(&old.results);
+		let new_pages = by_url(&new.results);
+		let mut diff = Self::default();
+
+		for (url, page) in &new_pages {
+			let previous = match old_pages.get(url) {
+				Some(previous) => previous,
+				None => {
+					diff.added.push(url.to_string());
+					if let Some(broken) = broken_link(page) {
+						diff.new_broken_links.push(broken);
+					}
+					continue;
+				}
+			};
+
+			if previous.status_code != page.status_code {
+				diff.status_changes.push(StatusChange {
+					url: url.to_string(),
+					old_status: previous.status_code,
+					new_status: page.status_code,
+				});
+			}
+
+			if previous.title != page.title && previous.error.is_none() && page.error.is_none() {
+				diff.title_changes.push(TitleChange {
+					url: url.to_string(),
+					old_title: previous.title.clone(),
+					new_title: page.title.clone(),
+				});
+			}
+
+			if previous.error.is_none() {
+				if let Some(broken) = broken_link(page) {
+					diff.new_broken_links.push(broken);
+				}
+			}
+
+			if let (Some(old_markdown), Some(new_markdown)) = (&previous.markdown, &page.markdown) {
+				let (old_body, new_body) = (markdown_body(old_markdown), markdown_body(new_markdown));
+				if old_body != new_body {
+					diff.content_changes.push(content_change(url, old_body, new_body));
+				}
+			}
+		}
+
+		diff.removed = old_pages
+			.keys()
+			.filter(|url| !new_pages.contains_key(*url))
+			.map(|url| url.to_string())
+			.collect();
+
+		diff
+	}
+
+	/// Checks if the crawls are the same for every reported aspect
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty()
+			&& self.removed.is_empty()
+			&& self.status_changes.is_empty()
+			&& self.title_changes.is_empty()
+			&& self.new_broken_links.is_empty()
+			&& self.content_changes.is_empty()
+	}
+}
+
+/// Reads a `results.json` file written by a previous crawl
+pub fn load_results(path: &Path) -> Result<CrawlResults> {
+	let json = std::fs::read_to_string(path)
+		.with_context(|| format!("Failed to read {}", path.display()))?;
+	serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Pages keyed by URL, in URL order (the first result wins if a URL repeats)
+fn by_url(results: &[PageResult]) -> BTreeMap<&str, &PageResult> {
+	let mut pages = BTreeMap::new();
+	for page in results {
+		pages.entry(page.url.as_str()).or_insert(page);
+	}
+	pages
+}
+
+fn broken_link(page: &PageResult) -> Option<BrokenLink> {
+	let error = page.error.as_ref()?;
+	let linked_from = if page.linked_from.is_empty() {
+		page.referrer.iter().cloned().collect()
+	} else {
+		page.linked_from.clone()
+	};
+
+	Some(BrokenLink {
+		url: page.url.clone(),
+		status_code: page.status_code,
+		error: error.clone(),
+		linked_from,
+	})
+}
+
+/// Markdown without its frontmatter, whose crawl timestamp always differs
+/// (title changes are reported on their own)
+fn markdown_body(markdown: &str) -> &str {
+	markdown
+		.strip_prefix("---\n")
+		.and_then(|rest| rest.split_once("\n---\n"))
+		.map_or(markdown, |(_, body)| body.trim_start_matches('\n'))
+}
+
+fn content_change(url: &str, old: &str, new: &str) -> ContentChange {
+	let text_diff = TextDiff::from_lines(old, new);
+	let (mut added_lines, mut removed_lines) = (0, 0);
+	for change in text_diff.iter_all_changes() {
+		match change.tag() {
+			ChangeTag::Insert => added_lines += 1,
+			ChangeTag::Delete => removed_lines += 1,
+			ChangeTag::Equal => {}
+		}
+	}
+
+	let diff = text_diff
+		.unified_diff()
+		.context_radius(CONTEXT_LINES)
+		.header("old", "new")
+		.to_string();
+
+	ContentChange {
+		url: url.to_string(),
+		added_lines,
+		removed_lines,
+		diff,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ErrorKind;
+
+	fn page(url: &str, title: &str, status_code: u16) -> PageResult {
+		PageResult {
+			url: url.to_string(),
+			title: title.to_string(),
+			status_code,
+			..Default::default()
+		}
+	}
+
+	fn crawl(results: Vec<PageResult>) -> CrawlResults {
+		CrawlResults {
+			results,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_added_removed_and_changed_pages() {
+		let old = crawl(vec![
+			page("https://docs.example.com/", "Docs", 200),
+			page("https://docs.example.com/install", "Install", 200),
+			page("https://docs.example.com/old", "Old", 200),
+		]);
+		let new = crawl(vec![
+			page("https://docs.example.com/", "Docs v2", 200),
+			PageResult {
+				error: Some("HTTP 404 Not Found".to_string()),
+				error_kind: Some(ErrorKind::HttpStatus),
+				referrer: Some("https://docs.example.com/".to_string()),
+				..page("https://docs.example.com/install", "", 404)
+			},
+			page("https://docs.example.com/new", "New", 200),
+		]);
+
+		let diff = CrawlDiff::between(&old, &new);
+
+		assert_eq!(diff.added, vec!["https://docs.example.com/new"]);
+		assert_eq!(diff.removed, vec!["https://docs.example.com/old"]);
+		assert_eq!(
+			diff.status_changes,
+			vec![StatusChange {
+				url: "https://docs.example.com/install".to_string(),
+				old_status: 200,
+				new_status: 404,
+			}]
+		);
+		// The broken page's empty title is not reported as a title change
+		assert_eq!(diff.title_changes.len(), 1);
+		assert_eq!(diff.title_changes[0].new_title, "Docs v2");
+		assert_eq!(diff.new_broken_links.len(), 1);
+		assert_eq!(diff.new_broken_links[0].linked_from, vec!["https://docs.example.com/"]);
+		assert!(CrawlDiff::between(&new, &new).is_empty());
+	}
+
+	#[test]
+	fn test_already_broken_links_are_not_new() {
+		let broken = PageResult {
+			error: Some("dns error".to_string()),
+			..page("https://gone.example.com/", "", 0)
+		};
+		let diff = CrawlDiff::between(&crawl(vec![broken.clone()]), &crawl(vec![broken]));
+		assert!(diff.new_broken_links.is_empty());
+	}
+
+	#[test]
+	fn test_markdown_content_diff() {
+		let with_markdown = |markdown: &str| PageResult {
+			markdown: Some(markdown.to_string()),
+			..page("https://docs.example.com/api", "API", 200)
+		};
+		let old = crawl(vec![with_markdown("# API\n\nCall `init()` first.\n\nThen `run()`.\n")]);
+		let new = crawl(vec![with_markdown("# API\n\nCall `setup()` first.\n\nThen `run()`.\n")]);
+
+		let diff = CrawlDiff::between(&old, &new);
+
+		assert_eq!(diff.content_changes.len(), 1);
+		let change = &diff.content_changes[0];
+		assert_eq!((change.added_lines, change.removed_lines), (1, 1));
+		assert!(change.diff.contains("-Call `init()` first."));
+		assert!(change.diff.contains("+Call `setup()` first."));
+
+		// Frontmatter is not content
+		let old = crawl(vec![with_markdown("---\ntitle: \"API\"\ncrawled_at: \"2024-01-01\"\n---\n\n# API\n")]);
+		let new = crawl(vec![with_markdown("---\ntitle: \"API\"\ncrawled_at: \"2024-06-01\"\n---\n\n# API\n")]);
+		assert!(CrawlDiff::between(&old, &new).content_changes.is_empty());
+
+		// Pages without stored Markdown are not compared
+		let plain = crawl(vec![page("https://docs.example.com/api", "API", 200)]);
+		assert!(CrawlDiff::between(&old, &plain).content_changes.is_empty());
+	}
+}
@@ -1,5 +1,6 @@
 //! HTML report generation with dark/light theme and graph visualization
 
+use crate::output::diff::CrawlDiff;
 use crate::{CrawlResults, PageResult};
 use anyhow::Result;
 use std::collections::HashMap;
@@ -80,13 +81,13 @@ fn transform_to_graph_data(results: &[PageResult], base_domain: Option<&str>) ->
 
 		// Count incoming links
 		for link in &page.links {
-			*in_degree_count.entry(link.clone()).or_insert(0) += 1;
+			*in_degree_count.entry(link.href.clone()).or_insert(0) += 1;
 		}
 	}
 
 	// Pass 2: Create links and missing nodes (external/uncrawled)
 	for page in results {
-		for target_url in &page.links {
+		for target_url in page.links.iter().map(|link| &link.href) {
 			// Create node if not yet crawled (external or not visited)
 			if !node_map.contains_key(target_url) {
 				let is_external = base_domain
@@ -172,8 +173,322 @@ fn escape_html(text: &str) -> String {
 		.replace('\'', "&#039;")
 }
 
+/// Status badge class and text for a page (error kind when there is no status code)
+fn status_badge(page: &PageResult) -> (&'static str, String) {
+	let class = if page.error.is_none() && (200..300).contains(&page.status_code) {
+		"success"
+	} else {
+		"error"
+	};
+
+	let label = match (page.status_code, page.error_kind) {
+		(0, Some(kind)) => kind.to_string(),
+		(status_code, _) => status_code.to_string(),
+	};
+
+	(class, label)
+}
+
+/// Opens a report section with its title and count badge
+fn section_start(title: &str, count: usize, unit: &str) -> String {
+	format!(
+		r#"
+    <div class="section">
+      <div class="section-header">
+        <h2 class="section-title">{}</h2>
+        <span class="badge">{} {}</span>
+      </div>
+
+"#,
+		title, count, unit
+	)
+}
+
+/// Header of a page card: URL on the left, badges on the right
+fn page_card_header(url: &str, badges: &str) -> String {
+	format!(
+		r#"      <div class="page-card">
+        <div class="page-header">
+          <div class="page-url">
+            <a href="{}" target="_blank" rel="noopener noreferrer">{}</a>
+          </div>
+          <div class="page-meta">
+            {}
+          </div>
+        </div>
+
+"#,
+		escape_html(url),
+		escape_html(url),
+		badges
+	)
+}
+
+/// Status and depth badges of a page card
+fn page_badges(status_class: &str, status_label: &str, depth: usize) -> String {
+	format!(
+		"<span class=\"status-badge {}\">{}</span>\n            {}",
+		status_class,
+		escape_html(status_label),
+		depth_badge(depth)
+	)
+}
+
+fn depth_badge(depth: usize) -> String {
+	format!("<span class=\"depth-badge\">Depth {}</span>", depth)
+}
+
+/// Status badge for a bare status code
+fn status_code_badge(status_code: u16) -> String {
+	let class = if (200..300).contains(&status_code) { "success" } else { "error" };
+	format!("<span class=\"status-badge {}\">{}</span>", class, status_code)
+}
+
+/// Line naming a page that links to the one in the card
+fn linked_from_item(referrer: &str) -> String {
+	format!(
+		"        <div class=\"link-item\">Linked from <a href=\"{}\" target=\"_blank\" rel=\"noopener noreferrer\">{}</a></div>\n",
+		escape_html(referrer),
+		escape_html(referrer)
+	)
+}
+
+/// Card listing URLs, one per line
+fn url_list_card(urls: &[String]) -> String {
+	let mut card = String::from("      <div class=\"page-card\">\n");
+	for url in urls {
+		card.push_str(&format!(
+			"        <div class=\"link-item\"><a href=\"{}\" target=\"_blank\" rel=\"noopener noreferrer\">{}</a></div>\n",
+			escape_html(url),
+			escape_html(url)
+		));
+	}
+	card.push_str("      </div>\n");
+	card
+}
+
+/// Generates the section listing pages that failed to crawl
+fn broken_links_section(results: &[PageResult]) -> String {
+	let broken: Vec<&PageResult> = results.iter().filter(|p| p.error.is_some()).collect();
+	if broken.is_empty() {
+		return String::new();
+	}
+
+	let mut section = section_start("Broken Links", broken.len(), "pages");
+
+	for page in broken {
+		let (status_class, status_label) = status_badge(page);
+		section.push_str(&page_card_header(&page.url, &page_badges(status_class, &status_label, page.depth)));
+
+		let inbound = if page.linked_from.is_empty() {
+			page.referrer.iter().collect::<Vec<_>>()
+		} else {
+			page.linked_from.iter().collect()
+		};
+		for referrer in inbound {
+			section.push_str(&linked_from_item(referrer));
+		}
+
+		if let Some(error) = &page.error {
+			section.push_str(&format!(
+				"        <div class=\"error-message\">Error: {}</div>\n",
+				escape_html(error)
+			));
+		}
+
+		section.push_str("      </div>\n");
+	}
+
+	section.push_str("    </div>\n");
+	section
+}
+
+/// Generates the section listing pages whose canonical URL points elsewhere
+fn canonical_section(results: &[PageResult]) -> String {
+	let canonicalized: Vec<&PageResult> = results
+		.iter()
+		.filter(|p| p.canonical_elsewhere().is_some())
+		.collect();
+	if canonicalized.is_empty() {
+		return String::new();
+	}
+
+	let mut section = section_start("Canonicalized Pages", canonicalized.len(), "pages");
+
+	for page in canonicalized {
+		let canonical = page.canonical_elsewhere().unwrap_or_default();
+		section.push_str(&page_card_header(&page.url, &depth_badge(page.depth)));
+		section.push_str(&format!(
+			"        <div class=\"link-item\">Canonical: <a href=\"{}\" target=\"_blank\" rel=\"noopener noreferrer\">{}</a></div>\n      </div>\n",
+			escape_html(canonical),
+			escape_html(canonical)
+		));
+	}
+
+	section.push_str("    </div>\n");
+	section
+}
+
+/// Generates the section listing redirect loops, long chains and redirects
+/// leaving the allowed domain
+fn redirect_section(results: &[PageResult]) -> String {
+	let flagged: Vec<&PageResult> = results.iter().filter(|p| p.redirect_issue.is_some()).collect();
+	if flagged.is_empty() {
+		return String::new();
+	}
+
+	let mut section = section_start("Redirect Issues", flagged.len(), "pages");
+
+	for page in flagged {
+		let issue = page.redirect_issue.map(|issue| issue.to_string()).unwrap_or_default();
+		section.push_str(&page_card_header(&page.url, &page_badges("error", &issue, page.depth)));
+
+		for hop in &page.redirects {
+			section.push_str(&format!(
+				"        <div class=\"link-item\">{} {} &rarr; {}</div>\n",
+				hop.status,
+				escape_html(&hop.url),
+				escape_html(&hop.location)
+			));
+		}
+
+		section.push_str("      </div>\n");
+	}
+
+	section.push_str("    </div>\n");
+	section
+}
+
+/// Generates the sections describing the differences between two crawls
+pub fn diff_sections(diff: &CrawlDiff) -> String {
+	if diff.is_empty() {
+		return r#"
+    <div class="section">
+      <div class="section-header">
+        <h2 class="section-title">No Differences</h2>
+      </div>
+    </div>
+"#
+		.to_string();
+	}
+
+	let mut sections = String::new();
+
+	if !diff.new_broken_links.is_empty() {
+		sections.push_str(&section_start("New Broken Links", diff.new_broken_links.len(), "pages"));
+		for broken in &diff.new_broken_links {
+			let badge = match broken.status_code {
+				0 => "<span class=\"status-badge error\">failed</span>".to_string(),
+				status_code => status_code_badge(status_code),
+			};
+			sections.push_str(&page_card_header(&broken.url, &badge));
+			for referrer in &broken.linked_from {
+				sections.push_str(&linked_from_item(referrer));
+			}
+			sections.push_str(&format!(
+				"        <div class=\"error-message\">Error: {}</div>\n      </div>\n",
+				escape_html(&broken.error)
+			));
+		}
+		sections.push_str("    </div>\n");
+	}
+
+	if !diff.status_changes.is_empty() {
+		sections.push_str(&section_start("Status Changes", diff.status_changes.len(), "pages"));
+		for change in &diff.status_changes {
+			let badges = format!(
+				"{} &rarr; {}",
+				status_code_badge(change.old_status),
+				status_code_badge(change.new_status)
+			);
+			sections.push_str(&page_card_header(&change.url, &badges));
+			sections.push_str("      </div>\n");
+		}
+		sections.push_str("    </div>\n");
+	}
+
+	for (title, urls) in [("Added Pages", &diff.added), ("Removed Pages", &diff.removed)] {
+		if !urls.is_empty() {
+			sections.push_str(&section_start(title, urls.len(), "pages"));
+			sections.push_str(&url_list_card(urls));
+			sections.push_str("    </div>\n");
+		}
+	}
+
+	if !diff.title_changes.is_empty() {
+		sections.push_str(&section_start("Title Changes", diff.title_changes.len(), "pages"));
+		for change in &diff.title_changes {
+			sections.push_str(&page_card_header(&change.url, ""));
+			sections.push_str(&format!(
+				"        <div class=\"page-title\">{} &rarr; {}</div>\n      </div>\n",
+				escape_html(&change.old_title),
+				escape_html(&change.new_title)
+			));
+		}
+		sections.push_str("    </div>\n");
+	}
+
+	if !diff.content_changes.is_empty() {
+		sections.push_str(&section_start("Content Changes", diff.content_changes.len(), "pages"));
+		for (index, change) in diff.content_changes.iter().enumerate() {
+			let diff_id = format!("diff-{}", index);
+			let badges = format!(
+				"<span class=\"status-badge success\">+{}</span>\n            <span class=\"status-badge error\">-{}</span>",
+				change.added_lines, change.removed_lines
+			);
+			sections.push_str(&page_card_header(&change.url, &badges));
+			sections.push_str(&format!(
+				r#"        <div class="links">
+          <button class="links-toggle" onclick="toggleLinks('{}', this)">
+            <span>Diff</span>
+            <span class="links-toggle-icon">▼</span>
+          </button>
+          <div class="links-content" id="{}">
+            <div class="links-container">
+              <div class="diff-lines">"#,
+				diff_id, diff_id
+			));
+
+			for line in change.diff.lines() {
+				let class = if line.starts_with("+++") || line.starts_with("---") {
+					"diff-line"
+				} else if line.starts_with('+') {
+					"diff-line added"
+				} else if line.starts_with('-') {
+					"diff-line removed"
+				} else {
+					"diff-line"
+				};
+				sections.push_str(&format!("<div class=\"{}\">{}</div>", class, escape_html(line)));
+			}
+
+			sections.push_str(
+				r#"</div>
+            </div>
+          </div>
+        </div>
+      </div>
+"#,
+			);
+		}
+		sections.push_str("    </div>\n");
+	}
+
+	sections
+}
+
 /// Generates HTML report from crawl results
 pub fn generate_html(results: &CrawlResults) -> Result<String> {
+	render_report(results, "")
+}
+
+/// Generates the report of the new crawl, led by its differences with the old one
+pub fn generate_diff_html(diff: &CrawlDiff, new: &CrawlResults) -> Result<String> {
+	render_report(new, &diff_sections(diff))
+}
+
+/// Fills the report template, placing `leading_sections` before the page sections
+fn render_report(results: &CrawlResults, leading_sections: &str) -> Result<String> {
 	let stats = &results.stats;
 	let pages_crawled = stats.pages_crawled;
 	let pages_found = stats.pages_found;
@@ -185,7 +500,7 @@ pub fn generate_html(results: &CrawlResults) -> Result<String> {
 	for page in &results.results {
 		pages_by_depth
 			.entry(page.depth)
-			.or_insert_with(Vec::new)
+			.or_default()
 			.push(page);
 	}
 
@@ -196,43 +511,19 @@ pub fn generate_html(results: &CrawlResults) -> Result<String> {
 	let graph_data = transform_to_graph_data(&results.results, None);
 	let graph_data_json = serde_json::to_string(&graph_data)?;
 
-	// Generate depth sections HTML
-	let mut depth_sections = String::new();
+	// Generate broken links, redirect, canonical and depth sections HTML
+	let mut depth_sections = leading_sections.to_string();
+	depth_sections.push_str(&broken_links_section(&results.results));
+	depth_sections.push_str(&redirect_section(&results.results));
+	depth_sections.push_str(&canonical_section(&results.results));
 	for depth in sorted_depths {
 		let pages = pages_by_depth.get(&depth).unwrap();
-		depth_sections.push_str(&format!(
-			r#"
-    <div class="section">
-      <div class="section-header">
-        <h2 class="section-title">Depth {}</h2>
-        <span class="badge">{} pages</span>
-      </div>
-
-"#,
-			depth,
-			pages.len()
-		));
+		depth_sections.push_str(&section_start(&format!("Depth {}", depth), pages.len(), "pages"));
 
 		for (page_index, page) in pages.iter().enumerate() {
 			let link_id = format!("links-{}-{}", depth, page_index);
-			let status_code = page.status_code;
-			let status_class = if status_code == 200 { "success" } else { "error" };
-
-			depth_sections.push_str(&format!(
-				r#"      <div class="page-card">
-        <div class="page-header">
-          <div class="page-url">
-            <a href="{}" target="_blank" rel="noopener noreferrer">{}</a>
-          </div>
-          <div class="page-meta">
-            <span class="status-badge {}">{}</span>
-            <span class="depth-badge">Depth {}</span>
-          </div>
-        </div>
-
-"#,
-				page.url, page.url, status_class, status_code, page.depth
-			));
+			let (status_class, status_label) = status_badge(page);
+			depth_sections.push_str(&page_card_header(&page.url, &page_badges(status_class, &status_label, page.depth)));
 
 			if !page.title.is_empty() {
 				depth_sections.push_str(&format!(
@@ -265,12 +556,14 @@ pub fn generate_html(results: &CrawlResults) -> Result<String> {
 				));
 
 				for link in page.links.iter().take(link_limit) {
+					let label = if link.text.is_empty() { &link.href } else { &link.text };
 					depth_sections.push_str(&format!(
 						r#"              <div class="link-item">
                 <a href="{}" target="_blank" rel="noopener noreferrer">{}</a>
               </div>
 "#,
-						link, link
+						escape_html(&link.href),
+						escape_html(label)
 					));
 				}
 
@@ -313,3 +606,124 @@ pub fn write_html_report(results: &CrawlResults, output_path: &Path) -> Result<(
 	std::fs::write(output_path, html)?;
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ErrorKind;
+
+	#[test]
+	fn test_broken_links_section() {
+		let results = vec![
+			PageResult {
+				url: "https://example.com".to_string(),
+				status_code: 200,
+				..Default::default()
+			},
+			PageResult {
+				url: "https://example.com/gone".to_string(),
+				depth: 1,
+				error: Some("dns error: failed to lookup address".to_string()),
+				error_kind: Some(ErrorKind::Dns),
+				referrer: Some("https://example.com".to_string()),
+				..Default::default()
+			},
+		];
+
+		let section = broken_links_section(&results);
+
+		assert!(section.contains("<span class=\"badge\">1 pages</span>"));
+		assert!(section.contains("<span class=\"status-badge error\">DNS</span>"));
+		assert!(section.contains("Linked from <a href=\"https://example.com\""));
+		assert!(broken_links_section(&results[..1]).is_empty());
+	}
+
+	#[test]
+	fn test_redirect_section() {
+		let results = vec![
+			PageResult {
+				url: "https://example.com/old".to_string(),
+				final_url: Some("https://example.com/new".to_string()),
+				redirects: vec![crate::RedirectHop {
+					url: "https://example.com/old".to_string(),
+					status: 301,
+					location: "/new".to_string(),
+				}],
+				..Default::default()
+			},
+			PageResult {
+				url: "https://example.com/a".to_string(),
+				redirects: vec![crate::RedirectHop {
+					url: "https://example.com/a".to_string(),
+					status: 302,
+					location: "/a?x=<1>".to_string(),
+				}],
+				redirect_issue: Some(crate::RedirectIssue::Loop),
+				..Default::default()
+			},
+		];
+
+		let section = redirect_section(&results);
+
+		assert!(section.contains("<span class=\"badge\">1 pages</span>"));
+		assert!(section.contains("<span class=\"status-badge error\">redirect loop</span>"));
+		assert!(section.contains("302 https://example.com/a &rarr; /a?x=&lt;1&gt;"));
+		assert!(redirect_section(&results[..1]).is_empty());
+	}
+
+	#[test]
+	fn test_canonical_section() {
+		let results = vec![
+			PageResult {
+				url: "https://example.com/docs".to_string(),
+				canonical_url: Some("https://example.com/docs".to_string()),
+				..Default::default()
+			},
+			PageResult {
+				url: "https://example.com/docs?print=1".to_string(),
+				depth: 1,
+				canonical_url: Some("https://example.com/docs".to_string()),
+				..Default::default()
+			},
+		];
+
+		let section = canonical_section(&results);
+
+		assert!(section.contains("<span class=\"badge\">1 pages</span>"));
+		assert!(section.contains("Canonical: <a href=\"https://example.com/docs\""));
+		assert!(canonical_section(&results[..1]).is_empty());
+	}
+
+	#[test]
+	fn test_diff_sections() {
+		use crate::output::diff::{ContentChange, StatusChange};
+
+		let diff = CrawlDiff {
+			removed: vec!["https://example.com/old".to_string()],
+			status_changes: vec![StatusChange {
+				url: "https://example.com/api".to_string(),
+				old_status: 200,
+				new_status: 500,
+			}],
+			content_changes: vec![ContentChange {
+				url: "https://example.com/guide".to_string(),
+				added_lines: 1,
+				removed_lines: 1,
+				diff: "--- old\n+++ new\n@@ -1 +1 @@\n-<b>old</b>\n+new\n".to_string(),
+			}],
+			..Default::default()
+		};
+
+		let sections = diff_sections(&diff);
+
+		assert!(sections.contains("<h2 class=\"section-title\">Removed Pages</h2>"));
+		assert!(!sections.contains("Added Pages"));
+		assert!(sections.contains(
+			"<span class=\"status-badge success\">200</span> &rarr; <span class=\"status-badge error\">500</span>"
+		));
+		assert!(sections.contains("<div class=\"diff-line removed\">-&lt;b&gt;old&lt;/b&gt;</div>"));
+		assert!(sections.contains("<div class=\"diff-line added\">+new</div>"));
+		assert!(sections.contains("<div class=\"diff-line\">+++ new</div>"));
+		assert!(diff_sections(&CrawlDiff::default()).contains("No Differences"));
+	}
+}
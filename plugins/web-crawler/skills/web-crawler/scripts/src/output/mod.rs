@@ -1,3 +1,4 @@
 //! Output module
+pub mod diff;
 pub mod html;
 pub mod json;
@@ -1,6 +1,7 @@
 //! HTML parsing
 
-use scraper::{Html, Selector};
+use crate::{LinkElement, LinkLocation, PageLink};
+use scraper::{ElementRef, Html, Selector};
 use anyhow::Result;
 
 pub struct HtmlParser;
@@ -21,20 +22,168 @@ impl HtmlParser {
             .unwrap_or_else(|| "No title".to_string())
     }
     
-    pub fn parse_links(&self, html: &str, base_url: &url::Url) -> Result<Vec<String>> {
+    /// Contents of `<meta name="robots">` tags and of tags addressed to
+    /// `product` (e.g. `<meta name="rcrawler">`)
+    pub fn parse_meta_robots(&self, html: &str, product: &str) -> Vec<String> {
         let document = Html::parse_document(html);
-        let link_selector = Selector::parse("a[href]").unwrap();
+        let meta_selector = Selector::parse("meta[name][content]").unwrap();
+
+        document
+            .select(&meta_selector)
+            .filter(|meta| {
+                let name = meta.value().attr("name").unwrap_or("").trim();
+                name.eq_ignore_ascii_case("robots") || name.eq_ignore_ascii_case(product)
+            })
+            .filter_map(|meta| meta.value().attr("content"))
+            .map(|content| content.trim().to_string())
+            .collect()
+    }
+
+    /// Extracts links from `<a>`, `<area>`, `<link>` and `<iframe>` elements,
+    /// resolved against `base_url`, with their anchor text, rel and location
+    pub fn parse_links(&self, html: &str, base_url: &url::Url) -> Result<Vec<PageLink>> {
+        let document = Html::parse_document(html);
+        let link_selector =
+            Selector::parse("a[href], area[href], link[href], iframe[src]").unwrap();
         
         let mut links = Vec::new();
         
         for element in document.select(&link_selector) {
-            if let Some(href) = element.value().attr("href") {
-                if let Ok(absolute_url) = base_url.join(href) {
-                    links.push(absolute_url.to_string());
-                }
-            }
+            let (element_kind, target) = match element.value().name() {
+                "a" => (LinkElement::A, element.value().attr("href")),
+                "area" => (LinkElement::Area, element.value().attr("href")),
+                "link" => (LinkElement::Link, element.value().attr("href")),
+                _ => (LinkElement::Iframe, element.value().attr("src")),
+            };
+
+            let absolute_url = match target.and_then(|href| base_url.join(href.trim()).ok()) {
+                Some(url) => url,
+                None => continue,
+            };
+
+            links.push(PageLink {
+                href: absolute_url.to_string(),
+                text: link_text(&element),
+                rel: element.value().attr("rel").map(|rel| rel.trim().to_string()),
+                element: element_kind,
+                location: link_location(&element),
+            });
         }
         
         Ok(links)
     }
 }
+
+/// Visible text of a link, falling back to its title, aria-label or alt
+fn link_text(element: &ElementRef) -> String {
+    let text = element.text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        return text;
+    }
+
+    ["title", "aria-label", "alt"]
+        .iter()
+        .find_map(|attr| element.value().attr(attr))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Location hint from the closest nav/header/footer/head ancestor
+fn link_location(element: &ElementRef) -> LinkLocation {
+    for ancestor in element.ancestors().filter_map(ElementRef::wrap) {
+        let role = ancestor.value().attr("role").unwrap_or("");
+        match ancestor.value().name() {
+            "nav" | "header" => return LinkLocation::Nav,
+            "footer" => return LinkLocation::Footer,
+            "head" => return LinkLocation::Head,
+            _ if role.eq_ignore_ascii_case("navigation") || role.eq_ignore_ascii_case("banner") => {
+                return LinkLocation::Nav
+            }
+            _ if role.eq_ignore_ascii_case("contentinfo") => return LinkLocation::Footer,
+            _ => {}
+        }
+    }
+
+    LinkLocation::Body
+}
+
+impl Default for HtmlParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(html: &str) -> Vec<PageLink> {
+        let base = url::Url::parse("https://example.com/docs/").unwrap();
+        HtmlParser::new().parse_links(html, &base).unwrap()
+    }
+
+    #[test]
+    fn test_parse_links_structured() {
+        let links = parse(
+            r#"<html><head><link rel="canonical" href="/docs/intro"></head><body>
+            <header><a href="/">Home</a></header>
+            <nav><a href="guide">Guide</a></nav>
+            <main><p>See <a href="https://other.org/x" rel="nofollow noopener">the
+                other   site</a>.</p>
+                <map><area href="/map" alt="Region"></map>
+                <iframe src="/embed"></iframe></main>
+            <footer><a href="/legal" title="Legal notice"></a></footer>
+            </body></html>"#,
+        );
+
+        assert_eq!(links.len(), 7);
+
+        assert_eq!(links[0].href, "https://example.com/docs/intro");
+        assert_eq!(links[0].element, LinkElement::Link);
+        assert_eq!(links[0].location, LinkLocation::Head);
+        assert!(!links[0].is_navigable());
+
+        assert_eq!(links[1].location, LinkLocation::Nav);
+        assert_eq!(links[2].href, "https://example.com/docs/guide");
+        assert_eq!(links[2].location, LinkLocation::Nav);
+
+        assert_eq!(links[3].text, "the other site");
+        assert!(links[3].has_rel("nofollow"));
+        assert_eq!(links[3].location, LinkLocation::Body);
+
+        assert_eq!(links[4].element, LinkElement::Area);
+        assert_eq!(links[4].text, "Region");
+        assert_eq!(links[5].element, LinkElement::Iframe);
+        assert_eq!(links[5].href, "https://example.com/embed");
+
+        assert_eq!(links[6].text, "Legal notice");
+        assert_eq!(links[6].location, LinkLocation::Footer);
+    }
+
+    #[test]
+    fn test_parse_meta_robots() {
+        let html = r#"<html><head>
+            <meta name="ROBOTS" content="noindex, follow">
+            <meta name="rcrawler" content="nofollow">
+            <meta name="googlebot" content="none">
+            <meta name="description" content="Docs">
+            </head></html>"#;
+
+        let meta = HtmlParser::new().parse_meta_robots(html, "rcrawler");
+
+        assert_eq!(meta, vec!["noindex, follow".to_string(), "nofollow".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_links_role_hints() {
+        let links = parse(
+            r#"<div role="navigation"><a href="/a">A</a></div>
+            <div role="contentinfo"><a href="/b">B</a></div>"#,
+        );
+
+        assert_eq!(links[0].location, LinkLocation::Nav);
+        assert_eq!(links[1].location, LinkLocation::Footer);
+        assert!(!links[0].has_rel("nofollow"));
+    }
+}
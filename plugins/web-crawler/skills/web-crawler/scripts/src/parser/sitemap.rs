@@ -8,6 +8,15 @@ use std::collections::HashSet;
 #[derive(Debug, Clone, Deserialize)]
 struct SitemapUrl {
 	loc: String,
+	#[serde(default)]
+	priority: Option<String>,
+}
+
+/// URL listed in a sitemap, with its `<priority>` (0.0 to 1.0) if given
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+	pub url: String,
+	pub priority: Option<f32>,
 }
 
 /// Regular sitemap structure
@@ -47,16 +56,35 @@ impl SitemapParser {
 		Self { client, max_urls }
 	}
 
-	/// Fetches URLs from sitemap.xml (tries multiple common locations)
-	pub async fn fetch_sitemap_urls(&self, base_domain: &str) -> Result<Vec<String>> {
-		let sitemap_urls = vec![
-			format!("https://{}/sitemap.xml", base_domain),
-			format!("https://{}/sitemap_index.xml", base_domain),
-			format!("https://{}/wp-sitemap.xml", base_domain),
-		];
-
+	/// Fetches URLs from the sitemaps declared in robots.txt, or from common
+	/// locations when robots.txt declares none
+	pub async fn fetch_sitemap_urls(
+		&self,
+		base_domain: &str,
+		declared: &[String],
+	) -> Result<Vec<SitemapEntry>> {
 		let mut all_urls = Vec::new();
 
+		// Declared sitemaps are authoritative: read all of them
+		for sitemap_url in declared {
+			println!("Trying sitemap from robots.txt: {}", sitemap_url);
+
+			match self.fetch_single_sitemap(sitemap_url).await {
+				Ok(urls) => all_urls.extend(urls),
+				Err(e) => eprintln!("Failed to fetch sitemap {}: {}", sitemap_url, e),
+			}
+		}
+
+		let sitemap_urls = if declared.is_empty() {
+			vec![
+				format!("https://{}/sitemap.xml", base_domain),
+				format!("https://{}/sitemap_index.xml", base_domain),
+				format!("https://{}/wp-sitemap.xml", base_domain),
+			]
+		} else {
+			Vec::new()
+		};
+
 		for sitemap_url in sitemap_urls {
 			println!("Trying sitemap: {}", sitemap_url);
 
@@ -78,11 +106,11 @@ impl SitemapParser {
 			}
 		}
 
-		// Deduplicate and limit
-		let unique_urls: Vec<String> = all_urls
-			.into_iter()
-			.collect::<HashSet<_>>()
+		// Deduplicate (the first listing wins) and limit
+		let mut seen = HashSet::new();
+		let unique_urls: Vec<SitemapEntry> = all_urls
 			.into_iter()
+			.filter(|entry: &SitemapEntry| seen.insert(entry.url.clone()))
 			.take(self.max_urls)
 			.collect();
 
@@ -90,7 +118,7 @@ impl SitemapParser {
 	}
 
 	/// Fetches a single sitemap URL
-	async fn fetch_single_sitemap(&self, url: &str) -> Result<Vec<String>> {
+	async fn fetch_single_sitemap(&self, url: &str) -> Result<Vec<SitemapEntry>> {
 		let response = self.client.get(url).send().await?;
 
 		if !response.status().is_success() {
@@ -114,7 +142,7 @@ impl SitemapParser {
 	fn parse_sitemap_index<'a>(
 		&'a self,
 		xml: &'a str,
-	) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<String>>> + 'a>> {
+	) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<SitemapEntry>>> + 'a>> {
 		Box::pin(async move {
 			let index: SitemapIndex = quick_xml::de::from_str(xml)?;
 
@@ -139,15 +167,48 @@ impl SitemapParser {
 	}
 
 	/// Parses a regular sitemap
-	fn parse_sitemap(&self, xml: &str) -> Result<Vec<String>> {
+	fn parse_sitemap(&self, xml: &str) -> Result<Vec<SitemapEntry>> {
 		let sitemap: Sitemap = quick_xml::de::from_str(xml)?;
 
-		let urls: Vec<String> = sitemap.urls.into_iter().map(|u| u.loc).collect();
+		let urls: Vec<SitemapEntry> = sitemap
+			.urls
+			.into_iter()
+			.map(|u| SitemapEntry {
+				url: u.loc,
+				// Out of range or malformed priorities are ignored
+				priority: u
+					.priority
+					.and_then(|p| p.trim().parse::<f32>().ok())
+					.filter(|p| (0.0..=1.0).contains(p)),
+			})
+			.collect();
 
 		Ok(urls)
 	}
 }
 
+/// Extracts `Sitemap:` directives from robots.txt content
+pub fn parse_sitemap_directives(robots_txt: &str) -> Vec<String> {
+	let mut sitemaps: Vec<String> = Vec::new();
+
+	for line in robots_txt.lines() {
+		// Strip comments
+		let line = line.split('#').next().unwrap_or("").trim();
+
+		if let Some((key, value)) = line.split_once(':') {
+			let value = value.trim();
+			if key.trim().eq_ignore_ascii_case("sitemap")
+				&& !value.is_empty()
+				&& !sitemaps.iter().any(|s| s == value)
+			{
+				sitemaps.push(value.to_string());
+			}
+		}
+	}
+
+	sitemaps
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -161,15 +222,23 @@ mod tests {
   </url>
   <url>
     <loc>https://example.com/about</loc>
+    <priority>0.8</priority>
+  </url>
+  <url>
+    <loc>https://example.com/old</loc>
+    <priority>high</priority>
   </url>
 </urlset>"#;
 
 		let parser = SitemapParser::new(30, 1000);
 		let urls = parser.parse_sitemap(xml).unwrap();
 
-		assert_eq!(urls.len(), 2);
-		assert_eq!(urls[0], "https://example.com/");
-		assert_eq!(urls[1], "https://example.com/about");
+		assert_eq!(urls.len(), 3);
+		assert_eq!(urls[0].url, "https://example.com/");
+		assert_eq!(urls[0].priority, None);
+		assert_eq!(urls[1].url, "https://example.com/about");
+		assert_eq!(urls[1].priority, Some(0.8));
+		assert_eq!(urls[2].priority, None);
 	}
 
 	#[tokio::test]
@@ -189,4 +258,28 @@ mod tests {
 		assert_eq!(index.sitemaps.len(), 2);
 		assert_eq!(index.sitemaps[0].loc, "https://example.com/sitemap-1.xml");
 	}
+
+	#[test]
+	fn test_parse_sitemap_directives() {
+		let robots = "User-agent: *\n\
+			Disallow: /admin\n\
+			Sitemap: https://example.com/sitemap-posts.xml\n\
+			sitemap:https://example.com/sitemap-pages.xml # pages\n\
+			SITEMAP: https://example.com/sitemap-posts.xml\n";
+
+		let sitemaps = parse_sitemap_directives(robots);
+
+		assert_eq!(
+			sitemaps,
+			vec![
+				"https://example.com/sitemap-posts.xml".to_string(),
+				"https://example.com/sitemap-pages.xml".to_string(),
+			]
+		);
+	}
+
+	#[test]
+	fn test_parse_sitemap_directives_none() {
+		assert!(parse_sitemap_directives("User-agent: *\nDisallow:\n").is_empty());
+	}
 }
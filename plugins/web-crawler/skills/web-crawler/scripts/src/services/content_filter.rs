@@ -3,7 +3,10 @@
 //! Removes unwanted elements like navigation, ads, footers to improve
 //! data quality for downstream processing (LLMs, analysis, archival)
 
-use scraper::{Html, ElementRef};
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use super::Service;
@@ -31,8 +34,53 @@ pub struct ContentFilterConfig {
     pub blacklist_tags: Vec<String>,
     /// Keep only these elements (if not empty, whitelist mode)
     pub whitelist_tags: Vec<String>,
+    /// How the main content is extracted after blacklist filtering
+    pub extraction: ExtractionStrategy,
 }
 
+/// Main content extraction strategy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExtractionStrategy {
+    /// Only remove blacklisted elements
+    #[default]
+    Blacklist,
+    /// Keep the highest-scoring content subtree (Mozilla Readability style)
+    Readability,
+    /// Keep only elements matching a CSS selector
+    CssSelector(String),
+}
+
+impl std::str::FromStr for ExtractionStrategy {
+    type Err = String;
+
+    /// Parse `blacklist`, `readability` or `css:<selector>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(selector) = s.strip_prefix("css:") {
+            if Selector::parse(selector).is_err() {
+                return Err(format!("Invalid CSS selector: {}", selector));
+            }
+            return Ok(Self::CssSelector(selector.to_string()));
+        }
+
+        match s.to_lowercase().as_str() {
+            "blacklist" => Ok(Self::Blacklist),
+            "readability" => Ok(Self::Readability),
+            _ => Err(format!("Unknown extraction strategy: {}", s)),
+        }
+    }
+}
+
+/// ID/class segments that mark advertising containers
+const AD_PATTERNS: &[&str] = &[
+    "ad",
+    "ads",
+    "advert",
+    "advertisement",
+    "sponsor",
+    "sponsored",
+    "banner",
+];
+
 impl Default for ContentFilterConfig {
     fn default() -> Self {
         Self {
@@ -78,15 +126,20 @@ impl Default for ContentFilterConfig {
                 "iframe".to_string(),
             ],
             whitelist_tags: vec![],
+            extraction: ExtractionStrategy::Blacklist,
         }
     }
 }
 
 /// Statistics about filtered content
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FilterStats {
-    /// Number of elements removed
+    /// Number of elements removed (a match nested in a removed element counts once)
     pub elements_removed: usize,
+    /// Number of HTML comments removed
+    #[serde(default)]
+    pub comments_removed: usize,
     /// Original HTML size in bytes
     pub original_size: usize,
     /// Filtered HTML size in bytes
@@ -105,6 +158,7 @@ impl FilterStats {
 
         Self {
             elements_removed,
+            comments_removed: 0,
             original_size,
             filtered_size,
             reduction_percent,
@@ -173,6 +227,10 @@ impl DefaultContentFilterService {
     }
 
     /// Check if element should be removed by attributes
+    ///
+    /// IDs and classes are matched per token and per run of `-`/`_`
+    /// segments, so `site-nav` matches `nav` and `cookie-banner-container`
+    /// matches `cookie-banner`, but `header` does not match `ad`.
     fn should_remove_by_attributes(
         &self,
         element: ElementRef,
@@ -181,39 +239,28 @@ impl DefaultContentFilterService {
         // Check ID attribute
         if let Some(id) = element.value().attr("id") {
             let id_lower = id.to_lowercase();
-            if config.blacklist_ids.iter().any(|bid| id_lower.contains(bid)) {
+            if Self::matches_any(&id_lower, &config.blacklist_ids) {
                 return true;
             }
 
             // Common ad/tracking patterns
-            if config.remove_ads
-                && (id_lower.contains("ad")
-                    || id_lower.contains("advertisement")
-                    || id_lower.contains("sponsor"))
-            {
+            if config.remove_ads && Self::matches_any(&id_lower, AD_PATTERNS) {
                 return true;
             }
         }
 
         // Check class attribute
         if let Some(classes) = element.value().attr("class") {
-            let classes_lower = classes.to_lowercase();
-            if config
-                .blacklist_classes
-                .iter()
-                .any(|bc| classes_lower.contains(bc))
-            {
-                return true;
-            }
+            for class in classes.split_whitespace() {
+                let class_lower = class.to_lowercase();
+                if Self::matches_any(&class_lower, &config.blacklist_classes) {
+                    return true;
+                }
 
-            // Common ad/tracking patterns
-            if config.remove_ads
-                && (classes_lower.contains("ad")
-                    || classes_lower.contains("advertisement")
-                    || classes_lower.contains("sponsor")
-                    || classes_lower.contains("banner"))
-            {
-                return true;
+                // Common ad/tracking patterns
+                if config.remove_ads && Self::matches_any(&class_lower, AD_PATTERNS) {
+                    return true;
+                }
             }
         }
 
@@ -222,6 +269,9 @@ impl DefaultContentFilterService {
             if config.remove_nav && (role == "navigation" || role == "banner") {
                 return true;
             }
+            if config.remove_footer && role == "contentinfo" {
+                return true;
+            }
             if config.remove_ads && role == "complementary" {
                 return true; // Often used for ads/sidebars
             }
@@ -230,7 +280,40 @@ impl DefaultContentFilterService {
         false
     }
 
-    /// Build list of selectors to remove
+    /// Check whether a pattern in the list is a run of consecutive `-`/`_`
+    /// segments of a token (the whole token included)
+    fn matches_any<S: AsRef<str>>(token: &str, patterns: &[S]) -> bool {
+        let segments: Vec<&str> = token.split(['-', '_']).collect();
+        patterns.iter().any(|pattern| {
+            let pattern: Vec<&str> = pattern.as_ref().split(['-', '_']).collect();
+            segments.windows(pattern.len()).any(|run| run == pattern)
+        })
+    }
+
+    /// Check if element must never be removed
+    ///
+    /// The document skeleton and the main content element always stay. Other
+    /// elements holding main content, such as `<div class="sidebar-layout"><main>`
+    /// or `<header class="entry-header"><h1>`, are only kept from heuristic
+    /// matches, never from ones the blacklist names outright.
+    fn is_protected(element: ElementRef, listed: bool) -> bool {
+        let is_main = |el: &scraper::node::Element| el.name() == "main" || el.attr("role") == Some("main");
+
+        if matches!(element.value().name(), "html" | "head" | "body") || is_main(element.value()) {
+            return true;
+        }
+        if listed {
+            return false;
+        }
+
+        element.descendants().any(|node| {
+            node.value()
+                .as_element()
+                .is_some_and(|el| matches!(el.name(), "article" | "h1") || is_main(el))
+        })
+    }
+
+    /// Build list of selectors for the built-in removal rules
     fn build_removal_selectors(&self, config: &ContentFilterConfig) -> Vec<String> {
         let mut selectors = Vec::new();
 
@@ -251,36 +334,385 @@ impl DefaultContentFilterService {
             selectors.push("noscript".to_string());
         }
 
-        // Blacklisted IDs
-        for id in &config.blacklist_ids {
-            selectors.push(format!("#{}", id));
+        selectors
+    }
+
+    /// Build list of selectors for the blacklisted IDs, classes and tags
+    fn build_blacklist_selectors(config: &ContentFilterConfig) -> Vec<String> {
+        let ids = config.blacklist_ids.iter().map(|id| format!("#{}", id));
+        let classes = config.blacklist_classes.iter().map(|class| format!(".{}", class));
+        let tags = config.blacklist_tags.iter().cloned();
+
+        ids.chain(classes).chain(tags).collect()
+    }
+
+    /// Collect elements matched by the removal selectors and heuristics
+    fn collect_removals(&self, document: &Html, config: &ContentFilterConfig) -> HashSet<NodeId> {
+        // Elements the blacklist names outright
+        let listed = Self::collect_matching(document, &Self::build_blacklist_selectors(config));
+        let mut removals = Self::collect_matching(document, &self.build_removal_selectors(config));
+        removals.extend(listed.iter().copied());
+
+        for node in document.tree.root().descendants() {
+            if let Some(element) = ElementRef::wrap(node) {
+                if self.should_remove_semantic(element, config)
+                    || self.should_remove_by_attributes(element, config)
+                {
+                    removals.insert(element.id());
+                }
+            }
+        }
+
+        removals.retain(|id| {
+            document
+                .tree
+                .get(*id)
+                .and_then(ElementRef::wrap)
+                .is_some_and(|el| !Self::is_protected(el, listed.contains(id)))
+        });
+
+        removals
+    }
+
+    /// Collect elements matching any of the selectors
+    fn collect_matching(document: &Html, selectors: &[String]) -> HashSet<NodeId> {
+        let mut matches = HashSet::new();
+
+        for selector in selectors {
+            // Blacklist entries are user-provided and may not be valid CSS
+            if let Ok(selector) = Selector::parse(selector) {
+                matches.extend(document.select(&selector).map(|el| el.id()));
+            }
         }
 
-        // Blacklisted classes
-        for class in &config.blacklist_classes {
-            selectors.push(format!(".{}", class));
+        matches
+    }
+
+    /// Collect body nodes that neither are, contain nor sit inside a kept node
+    ///
+    /// Ancestors of kept nodes stay as wrappers. Returns nothing when no kept
+    /// node is inside `<body>`, leaving the document untouched rather than empty.
+    fn collect_outside(document: &Html, keep: &HashSet<NodeId>) -> HashSet<NodeId> {
+        let body = match Selector::parse("body")
+            .ok()
+            .and_then(|selector| document.select(&selector).next())
+        {
+            Some(body) => body,
+            None => return HashSet::new(),
+        };
+
+        let contains_kept = |node: ego_tree::NodeRef<Node>| {
+            node.descendants().any(|d| keep.contains(&d.id()))
+        };
+
+        if !contains_kept(*body) {
+            return HashSet::new();
         }
 
-        // Blacklisted tags
-        for tag in &config.blacklist_tags {
-            selectors.push(tag.clone());
+        let mut removals = HashSet::new();
+        let mut stack: Vec<_> = body.children().collect();
+
+        while let Some(node) = stack.pop() {
+            if keep.contains(&node.id()) {
+                continue;
+            }
+            if contains_kept(node) {
+                stack.extend(node.children());
+            } else {
+                removals.insert(node.id());
+            }
         }
 
-        selectors
+        removals
     }
 
-    /// Filter HTML and return cleaned version
-    fn filter_html(&self, html: &str, _config: &ContentFilterConfig) -> (String, usize) {
-        let document = Html::parse_document(html);
+    /// Collect elements whose tag is in the whitelist
+    fn collect_whitelisted(document: &Html, whitelist: &[String]) -> HashSet<NodeId> {
+        document
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| whitelist.iter().any(|tag| tag.eq_ignore_ascii_case(el.value().name())))
+            .map(|el| el.id())
+            .collect()
+    }
 
-        // For now, return a simplified version
-        // In a full implementation, we would actually remove elements
-        // This requires more complex DOM manipulation
+    /// Collect elements matching a CSS selector (nothing if the selector is invalid)
+    fn collect_selected(document: &Html, selector: &str) -> HashSet<NodeId> {
+        match Selector::parse(selector) {
+            Ok(selector) => document.select(&selector).map(|el| el.id()).collect(),
+            Err(_) => HashSet::new(),
+        }
+    }
+
+    /// Detach the outermost nodes of a removal set, returning how many were elements
+    fn detach_outermost(document: &mut Html, removals: &HashSet<NodeId>) -> usize {
+        // Only detach outermost matches so nested matches are not double counted
+        let roots: Vec<NodeId> = document
+            .tree
+            .root()
+            .descendants()
+            .filter(|node| removals.contains(&node.id()))
+            .filter(|node| !node.ancestors().any(|a| removals.contains(&a.id())))
+            .map(|node| node.id())
+            .collect();
 
-        let cleaned = document.html();
-        let removed_count = 0;
+        let mut removed_count = 0;
+        for id in roots {
+            if let Some(mut node) = document.tree.get_mut(id) {
+                if matches!(node.value(), Node::Element(_)) {
+                    removed_count += 1;
+                }
+                node.detach();
+            }
+        }
 
-        (cleaned, removed_count)
+        removed_count
+    }
+
+    /// Detach every comment still attached to the document
+    fn remove_comments(document: &mut Html) -> usize {
+        let comments: Vec<NodeId> = document
+            .tree
+            .root()
+            .descendants()
+            .filter(|node| node.value().is_comment())
+            .map(|node| node.id())
+            .collect();
+
+        for id in &comments {
+            if let Some(mut node) = document.tree.get_mut(*id) {
+                node.detach();
+            }
+        }
+
+        comments.len()
+    }
+
+    /// Filter HTML and return cleaned version with the number of removed
+    /// elements and comments
+    fn filter_html(&self, html: &str, config: &ContentFilterConfig) -> (String, usize, usize) {
+        let mut document = Html::parse_document(html);
+
+        let removals = self.collect_removals(&document, config);
+        let mut removed_count = Self::detach_outermost(&mut document, &removals);
+
+        if !config.whitelist_tags.is_empty() {
+            let keep = Self::collect_whitelisted(&document, &config.whitelist_tags);
+            let outside = Self::collect_outside(&document, &keep);
+            removed_count += Self::detach_outermost(&mut document, &outside);
+        }
+
+        let keep = match &config.extraction {
+            ExtractionStrategy::Blacklist => HashSet::new(),
+            ExtractionStrategy::Readability => readability::main_content(&document),
+            ExtractionStrategy::CssSelector(selector) => Self::collect_selected(&document, selector),
+        };
+        let outside = Self::collect_outside(&document, &keep);
+        removed_count += Self::detach_outermost(&mut document, &outside);
+
+        let comments_removed = if config.remove_comments {
+            Self::remove_comments(&mut document)
+        } else {
+            0
+        };
+
+        (document.html(), removed_count, comments_removed)
+    }
+}
+
+/// Readability-style main content detection
+///
+/// Scores ancestors of every paragraph by text length and comma count, adjusts
+/// them by tag and class/ID hints, penalizes link-heavy candidates, then keeps
+/// the best candidate plus related siblings (as Mozilla Readability does).
+mod readability {
+    use super::DefaultContentFilterService;
+    use ego_tree::NodeId;
+    use scraper::{ElementRef, Html, Selector};
+    use std::collections::{HashMap, HashSet};
+
+    /// Paragraphs shorter than this are ignored during scoring
+    const MIN_PARAGRAPH_LEN: usize = 25;
+
+    /// Only this many ancestors of a paragraph receive its score
+    const MAX_ANCESTOR_LEVELS: usize = 5;
+
+    /// Class/ID segments that suggest main content
+    const POSITIVE_HINTS: &[&str] = &[
+        "article", "body", "content", "entry", "main", "page", "post", "text", "blog", "story",
+    ];
+
+    /// Class/ID segments that suggest boilerplate
+    const NEGATIVE_HINTS: &[&str] = &[
+        "banner", "comment", "comments", "contact", "cookie", "foot", "footer", "footnote",
+        "masthead", "menu", "meta", "modal", "nav", "popup", "promo", "related", "share",
+        "sidebar", "social", "sponsor", "tags", "tool", "widget",
+    ];
+
+    /// Returns the ids of the main content elements, or nothing if no
+    /// paragraph-bearing candidate was found
+    pub(super) fn main_content(document: &Html) -> HashSet<NodeId> {
+        let scores = score_candidates(document);
+
+        let top = scores
+            .iter()
+            .filter_map(|(id, score)| {
+                let el = document.tree.get(*id).and_then(ElementRef::wrap)?;
+                Some((el, score * (1.0 - link_density(el))))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let (top, top_score) = match top {
+            Some(top) => top,
+            None => return HashSet::new(),
+        };
+
+        let mut keep = HashSet::from([top.id()]);
+        let sibling_threshold = (top_score * 0.2).max(10.0);
+
+        for sibling in top
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.children())
+            .filter_map(ElementRef::wrap)
+            .filter(|el| el.id() != top.id())
+        {
+            let score = scores.get(&sibling.id()).copied().unwrap_or(0.0)
+                * (1.0 - link_density(sibling));
+
+            let is_related = if score >= sibling_threshold {
+                true
+            } else if sibling.value().name() == "p" {
+                let len = inner_text(sibling).chars().count();
+                let density = link_density(sibling);
+                (len > 80 && density < 0.25) || (len > 0 && density == 0.0 && sentence_like(sibling))
+            } else {
+                false
+            };
+
+            if is_related {
+                keep.insert(sibling.id());
+            }
+        }
+
+        keep
+    }
+
+    /// Propagates paragraph scores to their ancestors
+    fn score_candidates(document: &Html) -> HashMap<NodeId, f64> {
+        let selector = Selector::parse("p, pre, td, blockquote").unwrap();
+        let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+        for paragraph in document.select(&selector) {
+            let text = inner_text(paragraph);
+            let len = text.chars().count();
+            if len < MIN_PARAGRAPH_LEN {
+                continue;
+            }
+
+            let score = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+
+            let ancestors = paragraph
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .take_while(|el| !matches!(el.value().name(), "body" | "html"))
+                .take(MAX_ANCESTOR_LEVELS);
+
+            for (level, ancestor) in ancestors.enumerate() {
+                let divider = match level {
+                    0 => 1.0,
+                    1 => 2.0,
+                    _ => level as f64 * 3.0,
+                };
+                *scores
+                    .entry(ancestor.id())
+                    .or_insert_with(|| initial_score(ancestor)) += score / divider;
+            }
+        }
+
+        scores
+    }
+
+    /// Base score from the tag name and class/ID hints
+    fn initial_score(element: ElementRef) -> f64 {
+        let tag_score = match element.value().name() {
+            "div" | "article" | "main" | "section" => 5.0,
+            "pre" | "td" | "blockquote" => 3.0,
+            "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+            _ => 0.0,
+        };
+
+        tag_score + class_weight(element)
+    }
+
+    /// +25 / -25 per attribute (id, class) carrying positive / negative hints
+    fn class_weight(element: ElementRef) -> f64 {
+        let mut weight = 0.0;
+
+        for value in [element.value().attr("id"), element.value().attr("class")]
+            .into_iter()
+            .flatten()
+        {
+            let tokens: Vec<String> = value.split_whitespace().map(str::to_lowercase).collect();
+
+            if tokens
+                .iter()
+                .any(|t| DefaultContentFilterService::matches_any(t, NEGATIVE_HINTS))
+            {
+                weight -= 25.0;
+            }
+            if tokens
+                .iter()
+                .any(|t| DefaultContentFilterService::matches_any(t, POSITIVE_HINTS))
+            {
+                weight += 25.0;
+            }
+        }
+
+        weight
+    }
+
+    /// Share of the element's text that sits inside links
+    fn link_density(element: ElementRef) -> f64 {
+        let total = inner_text(element).chars().count();
+        if total == 0 {
+            return 0.0;
+        }
+
+        let selector = Selector::parse("a").unwrap();
+        let linked: usize = element
+            .select(&selector)
+            .map(|a| inner_text(a).chars().count())
+            .sum();
+
+        linked as f64 / total as f64
+    }
+
+    /// Whether a short paragraph still reads like prose
+    fn sentence_like(element: ElementRef) -> bool {
+        let text = inner_text(element);
+        text.ends_with('.') || text.contains(". ")
+    }
+
+    /// Whitespace-normalized text content, leaving out script and style
+    /// text so it never counts as prose
+    fn inner_text(element: ElementRef) -> String {
+        element
+            .descendants()
+            .filter(|node| {
+                !node
+                    .ancestors()
+                    .take_while(|ancestor| ancestor.id() != element.id())
+                    .filter_map(|ancestor| ancestor.value().as_element())
+                    .any(|el| matches!(el.name(), "script" | "style" | "noscript"))
+            })
+            .filter_map(|node| node.value().as_text())
+            .flat_map(|text| text.split_whitespace())
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 }
 
@@ -304,10 +736,11 @@ impl ContentFilterService for DefaultContentFilterService {
     ) -> Result<(String, FilterStats), String> {
         let original_size = html.len();
 
-        let (filtered, removed_count) = self.filter_html(html, config);
+        let (filtered, removed_count, comments_removed) = self.filter_html(html, config);
         let filtered_size = filtered.len();
 
-        let stats = FilterStats::new(original_size, filtered_size, removed_count);
+        let mut stats = FilterStats::new(original_size, filtered_size, removed_count);
+        stats.comments_removed = comments_removed;
 
         Ok((filtered, stats))
     }
@@ -344,10 +777,11 @@ mod tests {
             </html>
         "#;
 
-        let (filtered, _) = service.filter(html).unwrap();
+        let (filtered, stats) = service.filter(html).unwrap();
 
-        // Current implementation returns HTML as-is
         assert!(filtered.contains("Content"));
+        assert!(!filtered.contains("Menu"));
+        assert_eq!(stats.elements_removed, 1);
     }
 
     #[test]
@@ -403,6 +837,441 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    const WORDPRESS_POST: &str = include_str!("../../tests/fixtures/wordpress_post.html");
+    const DOCS_PAGE: &str = include_str!("../../tests/fixtures/docs_page.html");
+    const NEWS_ARTICLE: &str = include_str!("../../tests/fixtures/news_article.html");
+
+    /// Config with every rule disabled, so each test can enable a single one
+    fn empty_config() -> ContentFilterConfig {
+        ContentFilterConfig {
+            remove_nav: false,
+            remove_footer: false,
+            remove_sidebar: false,
+            remove_ads: false,
+            remove_scripts_styles: false,
+            remove_comments: false,
+            blacklist_ids: vec![],
+            blacklist_classes: vec![],
+            blacklist_tags: vec![],
+            whitelist_tags: vec![],
+            extraction: ExtractionStrategy::Blacklist,
+        }
+    }
+
+    fn filter(html: &str, config: ContentFilterConfig) -> (String, FilterStats) {
+        DefaultContentFilterService::with_config(config)
+            .filter(html)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_empty_config_removes_nothing() {
+        let (filtered, stats) = filter(WORDPRESS_POST, empty_config());
+
+        assert_eq!(stats.elements_removed, 0);
+        assert_eq!(stats.comments_removed, 0);
+        assert!(filtered.contains("Proudly powered by WordPress"));
+    }
+
+    #[test]
+    fn test_blacklist_ids() {
+        let config = ContentFilterConfig {
+            blacklist_ids: vec!["colophon".to_string(), "secondary".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(WORDPRESS_POST, config);
+
+        assert_eq!(stats.elements_removed, 2);
+        assert!(!filtered.contains("Proudly powered by WordPress"));
+        assert!(!filtered.contains("Recent Posts"));
+        assert!(filtered.contains("forty minutes on CI"));
+    }
+
+    #[test]
+    fn test_blacklist_id_segments() {
+        let config = ContentFilterConfig {
+            blacklist_ids: vec!["modal".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        // `newsletter-modal` matches on its `modal` segment
+        assert_eq!(stats.elements_removed, 1);
+        assert!(!filtered.contains("Subscribe to our newsletter"));
+    }
+
+    #[test]
+    fn test_blacklist_matches_segment_runs() {
+        let config = ContentFilterConfig {
+            blacklist_ids: vec!["cookie-banner".to_string()],
+            blacklist_classes: vec!["social-share".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        // `cookie-banner-container` and `social-share-bar` hold the patterns
+        // as runs of segments
+        assert_eq!(stats.elements_removed, 2);
+        assert!(!filtered.contains("This site uses cookies"));
+        assert!(!filtered.contains("intent/tweet"));
+        assert!(filtered.contains("ten-year transit expansion"));
+    }
+
+    #[test]
+    fn test_blacklist_classes() {
+        let config = ContentFilterConfig {
+            blacklist_classes: vec!["sidebar".to_string(), "popup".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        assert_eq!(stats.elements_removed, 2);
+        assert!(!filtered.contains("Most Read"));
+        assert!(!filtered.contains("Subscribe to our newsletter"));
+        assert!(filtered.contains("ten-year transit expansion"));
+    }
+
+    #[test]
+    fn test_blacklist_classes_match_whole_segments() {
+        let config = ContentFilterConfig {
+            blacklist_classes: vec!["nav".to_string()],
+            ..empty_config()
+        };
+        let (filtered, _) = filter(DOCS_PAGE, config);
+
+        // `navbar` and `navigation-with-keyboard` are not `nav`
+        assert!(filtered.contains("navbar__brand"));
+        // `pagination-nav` is
+        assert!(!filtered.contains("Previous: Introduction"));
+    }
+
+    #[test]
+    fn test_blacklist_tags() {
+        let config = ContentFilterConfig {
+            blacklist_tags: vec!["iframe".to_string(), "figure".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        // The iframe inside <noscript> is raw text to the parser, not an element
+        assert_eq!(stats.elements_removed, 2);
+        assert!(!filtered.contains("youtube.com/embed"));
+        assert!(!filtered.contains("A proposed light-rail station"));
+    }
+
+    #[test]
+    fn test_scripts_and_styles() {
+        let config = ContentFilterConfig {
+            remove_scripts_styles: true,
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(WORDPRESS_POST, config);
+
+        assert_eq!(stats.elements_removed, 4);
+        assert!(!filtered.contains("<script"));
+        assert!(!filtered.contains("<style"));
+        assert!(!filtered.contains("<noscript"));
+    }
+
+    #[test]
+    fn test_semantic_tags() {
+        let config = ContentFilterConfig {
+            remove_nav: true,
+            remove_footer: true,
+            remove_sidebar: true,
+            ..empty_config()
+        };
+        let (filtered, _) = filter(WORDPRESS_POST, config);
+
+        assert!(!filtered.contains("primary-menu"));
+        assert!(!filtered.contains("Recent Posts"));
+        assert!(!filtered.contains("Proudly powered by WordPress"));
+        assert!(filtered.contains("Remote caching"));
+    }
+
+    #[test]
+    fn test_aria_roles() {
+        let config = ContentFilterConfig {
+            remove_nav: true,
+            remove_footer: true,
+            remove_ads: true,
+            ..empty_config()
+        };
+        let (filtered, _) = filter(DOCS_PAGE, config);
+
+        // role="navigation" on a div
+        assert!(!filtered.contains("navbar__brand"));
+        // role="complementary" table of contents
+        assert!(!filtered.contains("table-of-contents"));
+        // role="contentinfo" footer
+        assert!(!filtered.contains("Copyright © 2024"));
+        assert!(filtered.contains("brew install widget"));
+    }
+
+    #[test]
+    fn test_ad_patterns() {
+        let config = ContentFilterConfig {
+            remove_ads: true,
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        assert_eq!(stats.elements_removed, 3);
+        assert!(!filtered.contains("ad-leaderboard"));
+        assert!(!filtered.contains("best mortgage rates"));
+        // `cookie-banner-container` has a `banner` segment
+        assert!(!filtered.contains("This site uses cookies"));
+        // `header-bar` and `headline` contain "ad" but are not ad segments
+        assert!(filtered.contains("header-bar"));
+        assert!(filtered.contains("City Council Approves New Transit Plan"));
+    }
+
+    #[test]
+    fn test_remove_comments() {
+        let config = ContentFilterConfig {
+            remove_comments: true,
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(WORDPRESS_POST, config);
+
+        assert_eq!(stats.elements_removed, 0);
+        assert_eq!(stats.comments_removed, 3);
+        assert!(!filtered.contains("<!--"));
+    }
+
+    #[test]
+    fn test_comments_inside_removed_elements_not_counted() {
+        let config = ContentFilterConfig {
+            remove_comments: true,
+            whitelist_tags: vec!["h2".to_string()],
+            ..empty_config()
+        };
+        let (_, stats) = filter(WORDPRESS_POST, config);
+
+        // Only the <head> comment survives the whitelist pass to be removed
+        assert_eq!(stats.comments_removed, 1);
+    }
+
+    #[test]
+    fn test_whitelist_tags() {
+        let config = ContentFilterConfig {
+            whitelist_tags: vec!["article".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(WORDPRESS_POST, config);
+
+        assert!(stats.elements_removed > 0);
+        assert!(filtered.contains("Remote caching"));
+        assert!(!filtered.contains("Great write-up!"));
+        assert!(!filtered.contains("Recent Posts"));
+        assert!(!filtered.contains("Proudly powered by WordPress"));
+        // <head> is kept for title extraction
+        assert!(filtered.contains("<title>"));
+    }
+
+    #[test]
+    fn test_whitelist_without_match_keeps_document() {
+        let config = ContentFilterConfig {
+            whitelist_tags: vec!["article".to_string()],
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        assert_eq!(stats.elements_removed, 0);
+        assert!(filtered.contains("ten-year transit expansion"));
+    }
+
+    #[test]
+    fn test_main_content_is_protected() {
+        let config = ContentFilterConfig {
+            blacklist_classes: vec!["wrapper".to_string(), "header".to_string()],
+            ..empty_config()
+        };
+
+        let (filtered, _) = filter(DOCS_PAGE, config.clone());
+        // `main-wrapper` wraps <main>
+        assert!(filtered.contains("brew install widget"));
+
+        let (filtered, _) = filter(WORDPRESS_POST, config);
+        // `entry-header` holds the article <h1>, `site-header` does not
+        assert!(filtered.contains("How We Cut Build Times in Half</h1>"));
+        assert!(!filtered.contains("site-branding"));
+    }
+
+    #[test]
+    fn test_blacklisted_wrapper_of_main_content_is_removed() {
+        let html = r#"<html><body>
+            <div id="hero"><h1>Spring Sale</h1></div>
+            <main class="promo"><p>Body text</p></main>
+        </body></html>"#;
+        let config = ContentFilterConfig {
+            blacklist_ids: vec!["hero".to_string()],
+            blacklist_classes: vec!["promo".to_string()],
+            ..empty_config()
+        };
+
+        let (filtered, stats) = filter(html, config);
+
+        // Named outright, so its <h1> does not save it; <main> itself stays
+        assert_eq!(stats.elements_removed, 1);
+        assert!(!filtered.contains("Spring Sale"));
+        assert!(filtered.contains("Body text"));
+    }
+
+    #[test]
+    fn test_default_config_on_fixtures() {
+        let service = DefaultContentFilterService::new();
+
+        let (filtered, stats) = service.filter(WORDPRESS_POST).unwrap();
+        assert_eq!(stats.elements_removed, 10);
+        assert_eq!(stats.comments_removed, 3);
+        assert!(stats.filtered_size < stats.original_size);
+        assert!(stats.reduction_percent > 0.0);
+        assert!(filtered.contains("forty minutes on CI"));
+        assert!(filtered.contains("cache.example.com"));
+        assert!(!filtered.contains("We use cookies"));
+        assert!(!filtered.contains("Share this:"));
+
+        let (filtered, _) = service.filter(DOCS_PAGE).unwrap();
+        assert!(filtered.contains("curl -fsSL https://widget.dev/install.sh"));
+        assert!(!filtered.contains("menu__link"));
+
+        let (filtered, _) = service.filter(NEWS_ARTICLE).unwrap();
+        assert!(filtered.contains("Construction is expected to begin next spring."));
+        assert!(!filtered.contains("Storm closes schools"));
+    }
+
+    #[test]
+    fn test_readability_wordpress_post() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::Readability,
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(WORDPRESS_POST, config);
+
+        assert!(stats.elements_removed > 0);
+        assert!(filtered.contains("forty minutes on CI"));
+        assert!(filtered.contains("test sharding"));
+        assert!(!filtered.contains("Great write-up!"));
+        assert!(!filtered.contains("primary-menu"));
+        assert!(!filtered.contains("Recent Posts"));
+        assert!(!filtered.contains("We use cookies"));
+        assert!(!filtered.contains("Proudly powered by WordPress"));
+    }
+
+    #[test]
+    fn test_readability_ignores_script_text() {
+        let html = r#"<html><body>
+            <div class="data"><p><script>var points = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];</script></p></div>
+            <div class="story">
+                <p>The council approved the plan on Tuesday, after a long debate.</p>
+                <script>trackView("story");</script>
+            </div>
+        </body></html>"#;
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::Readability,
+            ..empty_config()
+        };
+
+        // Script text does not count as prose, and scripts in the main
+        // content stay unless remove_scripts_styles is set
+        let (filtered, _) = filter(html, config.clone());
+        assert!(filtered.contains("after a long debate"));
+        assert!(filtered.contains("trackView"));
+        assert!(!filtered.contains("var points"));
+
+        let (filtered, _) = filter(html, ContentFilterConfig { remove_scripts_styles: true, ..config });
+        assert!(filtered.contains("after a long debate"));
+        assert!(!filtered.contains("trackView"));
+    }
+
+    #[test]
+    fn test_readability_news_article() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::Readability,
+            ..empty_config()
+        };
+        let (filtered, _) = filter(NEWS_ARTICLE, config);
+
+        assert!(filtered.contains("ten-year transit expansion"));
+        assert!(filtered.contains("Construction is expected to begin next spring."));
+        assert!(!filtered.contains("Storm closes schools"));
+        assert!(!filtered.contains("Subscribe to our newsletter"));
+        assert!(!filtered.contains("ad-leaderboard"));
+    }
+
+    #[test]
+    fn test_readability_docs_page() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::Readability,
+            ..Default::default()
+        };
+        let (filtered, _) = filter(DOCS_PAGE, config);
+
+        assert!(filtered.contains("single static binary"));
+        assert!(filtered.contains("widget.dev/install.sh"));
+        assert!(!filtered.contains("menu__link"));
+        assert!(!filtered.contains("Copyright © 2024"));
+        // <head> is kept for title extraction
+        assert!(filtered.contains("<title>Installation | Widget Docs</title>"));
+    }
+
+    #[test]
+    fn test_readability_without_paragraphs_keeps_document() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::Readability,
+            ..empty_config()
+        };
+        let html = "<html><body><div>Short</div><ul><li>One</li></ul></body></html>";
+        let (filtered, stats) = filter(html, config);
+
+        assert_eq!(stats.elements_removed, 0);
+        assert!(filtered.contains("Short"));
+    }
+
+    #[test]
+    fn test_css_selector_extraction() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::CssSelector(".theme-doc-markdown".to_string()),
+            ..empty_config()
+        };
+        let (filtered, _) = filter(DOCS_PAGE, config);
+
+        assert!(filtered.contains("brew install widget"));
+        assert!(!filtered.contains("Previous: Introduction"));
+        assert!(!filtered.contains("table-of-contents"));
+    }
+
+    #[test]
+    fn test_css_selector_without_match_keeps_document() {
+        let config = ContentFilterConfig {
+            extraction: ExtractionStrategy::CssSelector("#does-not-exist".to_string()),
+            ..empty_config()
+        };
+        let (filtered, stats) = filter(NEWS_ARTICLE, config);
+
+        assert_eq!(stats.elements_removed, 0);
+        assert!(filtered.contains("Storm closes schools"));
+    }
+
+    #[test]
+    fn test_extraction_strategy_parsing() {
+        assert_eq!(
+            "readability".parse::<ExtractionStrategy>().unwrap(),
+            ExtractionStrategy::Readability
+        );
+        assert_eq!(
+            "blacklist".parse::<ExtractionStrategy>().unwrap(),
+            ExtractionStrategy::Blacklist
+        );
+        assert_eq!(
+            "css:main .content".parse::<ExtractionStrategy>().unwrap(),
+            ExtractionStrategy::CssSelector("main .content".to_string())
+        );
+        assert!("css:[[".parse::<ExtractionStrategy>().is_err());
+        assert!("magic".parse::<ExtractionStrategy>().is_err());
+    }
+
     #[test]
     fn test_clone_service() {
         let service = DefaultContentFilterService::new();
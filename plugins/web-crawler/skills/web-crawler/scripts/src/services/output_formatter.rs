@@ -4,8 +4,10 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::crawler::dedup;
 use crate::{CrawlResults, PageResult};
 
 use super::Service;
@@ -28,9 +30,11 @@ pub enum OutputFormat {
     Text,
 }
 
-impl OutputFormat {
+impl FromStr for OutputFormat {
+    type Err = String;
+
     /// Parse format from string
-    pub fn from_str(s: &str) -> Result<Self, String> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "json" => Ok(Self::Json),
             "markdown" | "md" => Ok(Self::Markdown),
@@ -41,7 +45,9 @@ impl OutputFormat {
             _ => Err(format!("Unknown output format: {}", s)),
         }
     }
+}
 
+impl OutputFormat {
     /// Get file extension for format
     pub fn extension(&self) -> &str {
         match self {
@@ -66,6 +72,8 @@ pub struct OutputFormatterConfig {
     pub include_errors: bool,
     /// Maximum items in links output
     pub max_links: Option<usize>,
+    /// Fold duplicate pages into their canonical page (JSON and Markdown)
+    pub collapse_duplicates: bool,
 }
 
 impl Default for OutputFormatterConfig {
@@ -75,6 +83,7 @@ impl Default for OutputFormatterConfig {
             pretty_json: true,
             include_errors: true,
             max_links: None,
+            collapse_duplicates: false,
         }
     }
 }
@@ -134,8 +143,22 @@ impl DefaultOutputFormatterService {
         Self { config }
     }
 
+    /// Results with duplicates folded into their canonical page, if enabled
+    fn collapsed(&self, results: &CrawlResults) -> Option<CrawlResults> {
+        if !self.config.collapse_duplicates || results.stats.duplicates == 0 {
+            return None;
+        }
+        Some(CrawlResults {
+            results: dedup::collapse_duplicates(&results.results),
+            ..results.clone()
+        })
+    }
+
     /// Format as JSON
     fn format_json(&self, results: &CrawlResults, pretty: bool) -> Result<String, String> {
+        let collapsed = self.collapsed(results);
+        let results = collapsed.as_ref().unwrap_or(results);
+
         if pretty {
             serde_json::to_string_pretty(results).map_err(|e| e.to_string())
         } else {
@@ -145,6 +168,8 @@ impl DefaultOutputFormatterService {
 
     /// Format as Markdown
     fn format_markdown(&self, results: &CrawlResults) -> Result<String, String> {
+        let collapsed = self.collapsed(results);
+        let results = collapsed.as_ref().unwrap_or(results);
         let mut md = String::new();
 
         // Title
@@ -163,15 +188,95 @@ impl DefaultOutputFormatterService {
         ));
         md.push_str(&format!("- **Errors**: {}\n", results.stats.errors));
 
+        if results.stats.duplicates > 0 {
+            md.push_str(&format!("- **Duplicates**: {}\n", results.stats.duplicates));
+        }
+
+        if results.stats.is_incremental() {
+            md.push_str(&format!(
+                "- **Changes**: {} new, {} changed, {} unchanged, {} removed\n",
+                results.stats.new_pages,
+                results.stats.changed_pages,
+                results.stats.unchanged_pages,
+                results.stats.removed_pages
+            ));
+        }
+
+        if results.stats.interrupted {
+            md.push_str("- **Interrupted**: yes (partial results)\n");
+        }
+
+        if let Some(limit) = results.stats.stopped_by {
+            md.push_str(&format!("- **Stopped by**: {} budget (partial results)\n", limit));
+        }
+
+        if let Some(concurrency) = &results.stats.concurrency {
+            md.push_str(&format!("- **Concurrency**: {}\n", concurrency));
+        }
+
         if let Some(duration) = results.stats.duration {
             md.push_str(&format!("- **Duration**: {}ms\n", duration));
         }
 
-        md.push_str("\n");
+        md.push('\n');
+
+        // Broken links
+        let broken = self.broken_pages(results);
+        if !broken.is_empty() {
+            md.push_str(&format!("## Broken Links ({})\n\n", broken.len()));
+            for page in broken {
+                md.push_str(&format!("- {} ({})", page.url, Self::failure_label(page)));
+                let inbound = Self::inbound_pages(page);
+                if !inbound.is_empty() {
+                    md.push_str(&format!(", linked from {}", inbound.join(", ")));
+                }
+                md.push('\n');
+            }
+            md.push('\n');
+        }
+
+        // Redirect loops, long chains and redirects out of scope
+        let redirected: Vec<&PageResult> = results
+            .results
+            .iter()
+            .filter(|r| r.redirect_issue.is_some())
+            .collect();
+        if !redirected.is_empty() {
+            md.push_str(&format!("## Redirect Issues ({})\n\n", redirected.len()));
+            for page in redirected {
+                let mut chain: Vec<&str> = page.redirects.iter().map(|hop| hop.url.as_str()).collect();
+                chain.extend(page.final_url.as_deref());
+                md.push_str(&format!(
+                    "- {} ({}): {}\n",
+                    page.url,
+                    page.redirect_issue.map(|issue| issue.to_string()).unwrap_or_default(),
+                    chain.join(" -> ")
+                ));
+            }
+            md.push('\n');
+        }
 
-        // Results by depth
+        // Pages whose rel=canonical points to another URL
+        let canonicalized: Vec<&PageResult> = results
+            .results
+            .iter()
+            .filter(|r| r.canonical_elsewhere().is_some())
+            .collect();
+        if !canonicalized.is_empty() {
+            md.push_str(&format!("## Canonicalized Pages ({})\n\n", canonicalized.len()));
+            for page in canonicalized {
+                md.push_str(&format!(
+                    "- {} -> {}\n",
+                    page.url,
+                    page.canonical_elsewhere().unwrap_or_default()
+                ));
+            }
+            md.push('\n');
+        }
+
+        // Results by depth (noindex pages are left out)
         let mut by_depth: HashMap<usize, Vec<&PageResult>> = HashMap::new();
-        for result in &results.results {
+        for result in results.results.iter().filter(|r| !r.robots.noindex) {
             by_depth.entry(result.depth).or_default().push(result);
         }
 
@@ -187,15 +292,40 @@ impl DefaultOutputFormatterService {
                 md.push_str(&format!("**URL**: {}\n\n", page.url));
                 md.push_str(&format!("**Status**: {}\n\n", page.status_code));
 
+                if let Some(word_count) = page.word_count {
+                    md.push_str(&format!("**Words**: {}\n\n", word_count));
+                }
+
+                if let Some(canonical) = &page.duplicate_of {
+                    md.push_str(&format!("**Duplicate of**: {}\n\n", canonical));
+                }
+
+                if !page.duplicates.is_empty() {
+                    md.push_str(&format!(
+                        "**Duplicates** ({}): {}\n\n",
+                        page.duplicates.len(),
+                        page.duplicates.join(", ")
+                    ));
+                }
+
+                if let Some(content) = &page.markdown {
+                    md.push_str(content.trim());
+                    md.push_str("\n\n");
+                }
+
                 if !page.links.is_empty() {
                     md.push_str(&format!("**Links** ({}):\n\n", page.links.len()));
                     for (i, link) in page.links.iter().enumerate().take(10) {
-                        md.push_str(&format!("{}. {}\n", i + 1, link));
+                        if link.text.is_empty() {
+                            md.push_str(&format!("{}. {}\n", i + 1, link.href));
+                        } else {
+                            md.push_str(&format!("{}. [{}]({})\n", i + 1, link.text, link.href));
+                        }
                     }
                     if page.links.len() > 10 {
                         md.push_str(&format!("\n*...and {} more*\n", page.links.len() - 10));
                     }
-                    md.push_str("\n");
+                    md.push('\n');
                 }
 
                 if let Some(error) = &page.error {
@@ -209,11 +339,15 @@ impl DefaultOutputFormatterService {
         Ok(md)
     }
 
-    /// Format as links list
+    /// Format as links list (the URL inventory in map-only mode)
     fn format_links(&self, results: &CrawlResults, max_links: Option<usize>) -> Result<String, String> {
         let mut links = String::new();
 
-        let urls: Vec<_> = results.results.iter().map(|r| &r.url).collect();
+        let urls: Vec<_> = if results.map.is_empty() {
+            results.results.iter().map(|r| &r.url).collect()
+        } else {
+            results.map.iter().map(|m| &m.url).collect()
+        };
         let limit = max_links.unwrap_or(urls.len());
 
         for (i, url) in urls.iter().enumerate().take(limit) {
@@ -234,18 +368,21 @@ impl DefaultOutputFormatterService {
         let mut csv = String::new();
 
         // Header
-        csv.push_str("URL,Title,Status Code,Depth,Links Count,Error\n");
+        csv.push_str("URL,Title,Status Code,Depth,Links Count,Word Count,Error,Error Kind,Referrer\n");
 
         // Rows
         for result in &results.results {
             csv.push_str(&format!(
-                "\"{}\",\"{}\",{},{},{},\"{}\"\n",
+                "\"{}\",\"{}\",{},{},{},{},\"{}\",\"{}\",\"{}\"\n",
                 Self::escape_csv(&result.url),
                 Self::escape_csv(&result.title),
                 result.status_code,
                 result.depth,
                 result.links.len(),
-                result.error.as_deref().unwrap_or("")
+                result.word_count.unwrap_or(0),
+                Self::escape_csv(result.error.as_deref().unwrap_or("")),
+                result.error_kind.map(|kind| kind.to_string()).unwrap_or_default(),
+                Self::escape_csv(result.referrer.as_deref().unwrap_or(""))
             ));
         }
 
@@ -264,11 +401,45 @@ impl DefaultOutputFormatterService {
         text.push_str(&format!("  External Links: {}\n", results.stats.external_links));
         text.push_str(&format!("  Errors: {}\n", results.stats.errors));
 
+        if results.stats.is_incremental() {
+            text.push_str(&format!(
+                "  Changes: {} new, {} changed, {} unchanged, {} removed\n",
+                results.stats.new_pages,
+                results.stats.changed_pages,
+                results.stats.unchanged_pages,
+                results.stats.removed_pages
+            ));
+        }
+
+        if results.stats.interrupted {
+            text.push_str("  Interrupted: yes (partial results)\n");
+        }
+
+        if let Some(limit) = results.stats.stopped_by {
+            text.push_str(&format!("  Stopped by: {} budget (partial results)\n", limit));
+        }
+
+        if let Some(concurrency) = &results.stats.concurrency {
+            text.push_str(&format!("  Concurrency: {}\n", concurrency));
+        }
+
         if let Some(duration) = results.stats.duration {
             text.push_str(&format!("  Duration: {}ms\n", duration));
         }
 
-        text.push_str("\n");
+        text.push('\n');
+
+        let broken = self.broken_pages(results);
+        if !broken.is_empty() {
+            text.push_str(&format!("Broken Links ({}):\n", broken.len()));
+            for page in broken {
+                text.push_str(&format!("  {} ({})\n", page.url, Self::failure_label(page)));
+                for referrer in Self::inbound_pages(page) {
+                    text.push_str(&format!("    linked from {}\n", referrer));
+                }
+            }
+            text.push('\n');
+        }
 
         text.push_str("Pages:\n\n");
         for (i, result) in results.results.iter().enumerate() {
@@ -278,16 +449,50 @@ impl DefaultOutputFormatterService {
             text.push_str(&format!("   Depth: {}\n", result.depth));
             text.push_str(&format!("   Links: {}\n", result.links.len()));
 
+            if let Some(word_count) = result.word_count {
+                text.push_str(&format!("   Words: {}\n", word_count));
+            }
+
             if let Some(error) = &result.error {
                 text.push_str(&format!("   Error: {}\n", error));
             }
 
-            text.push_str("\n");
+            text.push('\n');
         }
 
         Ok(text)
     }
 
+    /// Pages that failed to crawl (empty when errors are excluded)
+    fn broken_pages<'a>(&self, results: &'a CrawlResults) -> Vec<&'a PageResult> {
+        if !self.config.include_errors {
+            return Vec::new();
+        }
+        results.results.iter().filter(|r| r.error.is_some()).collect()
+    }
+
+    /// Pages linking to a failed URL (its first referrer if none were indexed)
+    fn inbound_pages(page: &PageResult) -> Vec<&str> {
+        if page.linked_from.is_empty() {
+            page.referrer.iter().map(String::as_str).collect()
+        } else {
+            page.linked_from.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Short description of a failure, e.g. "HTTP status 404" or "timeout"
+    fn failure_label(page: &PageResult) -> String {
+        let kind = page
+            .error_kind
+            .map(|kind| kind.to_string())
+            .unwrap_or_else(|| "error".to_string());
+        if page.status_code > 0 {
+            format!("{} {}", kind, page.status_code)
+        } else {
+            kind
+        }
+    }
+
     /// Escape CSV field
     fn escape_csv(s: &str) -> String {
         s.replace('"', "\"\"")
@@ -331,9 +536,7 @@ impl OutputFormatterService for DefaultOutputFormatterService {
             OutputFormat::Json => self.format_json(results, self.config.pretty_json)?,
             OutputFormat::Markdown => self.format_markdown(results)?,
             OutputFormat::Html => {
-                // Note: HTML formatting would use the existing html.rs module
-                // For now, we'll use JSON as fallback
-                self.format_json(results, false)?
+                crate::output::html::generate_html(results).map_err(|e| e.to_string())?
             }
             OutputFormat::Links => self.format_links(results, self.config.max_links)?,
             OutputFormat::Csv => self.format_csv(results)?,
@@ -357,7 +560,9 @@ impl OutputFormatterService for DefaultOutputFormatterService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{CrawlStats, PageResult};
+    use crate::crawler::budget::BudgetLimit;
+    use crate::crawler::concurrency::ConcurrencyStats;
+    use crate::{CrawlStats, DiscoverySource, ErrorKind, LinkLocation, MappedUrl, PageLink, PageResult};
     use chrono::Utc;
 
     fn create_test_results() -> CrawlResults {
@@ -371,6 +576,16 @@ mod tests {
                 start_time: Utc::now(),
                 end_time: Some(Utc::now()),
                 duration: Some(5000),
+                interrupted: false,
+                stopped_by: None,
+                bytes_downloaded: 0,
+                host_budget_skips: 0,
+                duplicates: 0,
+                new_pages: 0,
+                changed_pages: 0,
+                unchanged_pages: 0,
+                removed_pages: 0,
+                concurrency: None,
             },
             results: vec![
                 PageResult {
@@ -378,10 +593,11 @@ mod tests {
                     title: "Example Domain".to_string(),
                     status_code: 200,
                     depth: 0,
-                    links: vec!["https://example.com/page1".to_string()],
+                    links: vec![PageLink::new("https://example.com/page1")],
                     error: None,
                     crawled_at: Utc::now(),
                     content_type: "text/html".to_string(),
+                    ..Default::default()
                 },
                 PageResult {
                     url: "https://example.com/page1".to_string(),
@@ -392,8 +608,12 @@ mod tests {
                     error: None,
                     crawled_at: Utc::now(),
                     content_type: "text/html".to_string(),
+                    markdown: Some("# Page 1\n\nFirst page body.".to_string()),
+                    word_count: Some(5),
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         }
     }
 
@@ -409,6 +629,28 @@ mod tests {
         assert!(output.full_filename().ends_with(".json"));
     }
 
+    #[test]
+    fn test_json_with_plain_links_still_loads() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.results[0].links.push(PageLink {
+            text: "About".to_string(),
+            rel: Some("nofollow".to_string()),
+            location: LinkLocation::Nav,
+            ..PageLink::new("https://example.com/about")
+        });
+        let json = service.format_single(&results, OutputFormat::Json).unwrap();
+
+        let parsed: CrawlResults = serde_json::from_str(&json.content).unwrap();
+        assert_eq!(parsed.results[0].links, results.results[0].links);
+
+        // Files written before links were structured hold plain hrefs
+        let mut old: serde_json::Value = serde_json::from_str(&json.content).unwrap();
+        old["results"][0]["links"] = serde_json::json!(["https://example.com/page1"]);
+        let parsed: CrawlResults = serde_json::from_value(old).unwrap();
+        assert_eq!(parsed.results[0].links, vec![PageLink::new("https://example.com/page1")]);
+    }
+
     #[test]
     fn test_format_markdown() {
         let service = DefaultOutputFormatterService::new();
@@ -422,6 +664,85 @@ mod tests {
         assert!(output.content.contains("Example Domain"));
     }
 
+    #[test]
+    fn test_format_markdown_includes_page_content() {
+        let service = DefaultOutputFormatterService::new();
+        let results = create_test_results();
+
+        let output = service.format_single(&results, OutputFormat::Markdown).unwrap();
+
+        assert!(output.content.contains("First page body."));
+        assert!(output.content.contains("**Words**: 5"));
+    }
+
+    #[test]
+    fn test_format_marks_interrupted_crawl() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.stats.interrupted = true;
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        let text = service.format_single(&results, OutputFormat::Text).unwrap();
+
+        assert!(markdown.content.contains("**Interrupted**: yes"));
+        assert!(text.content.contains("Interrupted: yes"));
+    }
+
+    #[test]
+    fn test_format_names_exhausted_budget() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.stats.stopped_by = Some(BudgetLimit::Bytes);
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        let text = service.format_single(&results, OutputFormat::Text).unwrap();
+
+        assert!(markdown.content.contains("**Stopped by**: byte budget"));
+        assert!(text.content.contains("Stopped by: byte budget"));
+    }
+
+    #[test]
+    fn test_format_summarizes_adaptive_concurrency() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.stats.concurrency = Some(ConcurrencyStats {
+            initial: 20,
+            current: 12,
+            lowest: 10,
+            highest: 24,
+            increases: 4,
+            decreases: 2,
+            decisions: Vec::new(),
+        });
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        let text = service.format_single(&results, OutputFormat::Text).unwrap();
+
+        let summary = "20 -> 12 (lowest 10, highest 24; 4 increases, 2 back-offs)";
+        assert!(markdown.content.contains(&format!("**Concurrency**: {}", summary)));
+        assert!(text.content.contains(&format!("Concurrency: {}", summary)));
+    }
+
+    #[test]
+    fn test_format_reports_incremental_changes() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        assert!(!markdown.content.contains("**Changes**"));
+
+        results.stats.new_pages = 1;
+        results.stats.unchanged_pages = 6;
+        results.stats.removed_pages = 2;
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        let text = service.format_single(&results, OutputFormat::Text).unwrap();
+
+        assert!(markdown
+            .content
+            .contains("**Changes**: 1 new, 0 changed, 6 unchanged, 2 removed"));
+        assert!(text.content.contains("Changes: 1 new, 0 changed, 6 unchanged, 2 removed"));
+    }
+
     #[test]
     fn test_format_links() {
         let service = DefaultOutputFormatterService::new();
@@ -434,6 +755,21 @@ mod tests {
         assert!(output.content.contains("https://example.com/page1"));
     }
 
+    #[test]
+    fn test_format_links_uses_map() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.map = vec![MappedUrl {
+            url: "https://example.com/from-sitemap".to_string(),
+            source: DiscoverySource::Sitemap,
+            depth: 1,
+        }];
+
+        let output = service.format_single(&results, OutputFormat::Links).unwrap();
+
+        assert_eq!(output.content, "https://example.com/from-sitemap\n");
+    }
+
     #[test]
     fn test_format_csv() {
         let service = DefaultOutputFormatterService::new();
@@ -446,6 +782,64 @@ mod tests {
         assert!(output.content.contains("Example Domain"));
     }
 
+    fn with_broken_link(mut results: CrawlResults) -> CrawlResults {
+        results.results.push(PageResult {
+            url: "https://example.com/missing".to_string(),
+            status_code: 404,
+            depth: 1,
+            error: Some("HTTP 404 Not Found".to_string()),
+            error_kind: Some(ErrorKind::HttpStatus),
+            referrer: Some("https://example.com".to_string()),
+            linked_from: vec![
+                "https://example.com".to_string(),
+                "https://example.com/page1".to_string(),
+            ],
+            ..Default::default()
+        });
+        results
+    }
+
+    #[test]
+    fn test_broken_links_sections() {
+        let service = DefaultOutputFormatterService::new();
+        let results = with_broken_link(create_test_results());
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        let text = service.format_single(&results, OutputFormat::Text).unwrap();
+        let csv = service.format_single(&results, OutputFormat::Csv).unwrap();
+
+        assert!(markdown.content.contains("## Broken Links (1)"));
+        assert!(markdown.content.contains(
+            "- https://example.com/missing (HTTP status 404), linked from https://example.com, https://example.com/page1"
+        ));
+        assert!(text.content.contains("Broken Links (1):"));
+        assert!(csv.content.contains("\"HTTP 404 Not Found\",\"HTTP status\",\"https://example.com\""));
+    }
+
+    #[test]
+    fn test_broken_links_respect_include_errors() {
+        let service = DefaultOutputFormatterService::with_config(OutputFormatterConfig {
+            include_errors: false,
+            ..Default::default()
+        });
+        let results = with_broken_link(create_test_results());
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+
+        assert!(!markdown.content.contains("Broken Links"));
+    }
+
+    #[test]
+    fn test_format_html_report() {
+        let service = DefaultOutputFormatterService::new();
+        let results = create_test_results();
+
+        let output = service.format_single(&results, OutputFormat::Html).unwrap();
+
+        assert!(output.content.starts_with("<!DOCTYPE html>") || output.content.contains("<html"));
+        assert!(output.content.contains("Example Domain"));
+    }
+
     #[test]
     fn test_format_text() {
         let service = DefaultOutputFormatterService::new();
@@ -475,6 +869,102 @@ mod tests {
         assert_eq!(outputs[2].format, OutputFormat::Links);
     }
 
+    #[test]
+    fn test_markdown_canonical_and_noindex() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.results[1].canonical_url = Some("https://example.com/".to_string());
+        results.results.push(PageResult {
+            url: "https://example.com/private".to_string(),
+            title: "Private".to_string(),
+            status_code: 200,
+            robots: crate::RobotsDirectives { noindex: true, nofollow: false },
+            ..Default::default()
+        });
+
+        let output = service.format_single(&results, OutputFormat::Markdown).unwrap();
+
+        assert!(output.content.contains("## Canonicalized Pages (1)"));
+        assert!(output.content.contains("- https://example.com/page1 -> https://example.com/"));
+        assert!(!output.content.contains("Private"));
+    }
+
+    #[test]
+    fn test_markdown_redirect_issues() {
+        let service = DefaultOutputFormatterService::new();
+        let mut results = create_test_results();
+        results.results.push(PageResult {
+            url: "https://example.com/a".to_string(),
+            final_url: Some("https://example.com/d".to_string()),
+            redirects: ["a", "b", "c"]
+                .iter()
+                .map(|path| crate::RedirectHop {
+                    url: format!("https://example.com/{}", path),
+                    status: 301,
+                    location: String::new(),
+                })
+                .collect(),
+            redirect_issue: Some(crate::RedirectIssue::LongChain),
+            ..Default::default()
+        });
+
+        let output = service.format_single(&results, OutputFormat::Markdown).unwrap();
+
+        assert!(output.content.contains("## Redirect Issues (1)"));
+        assert!(output.content.contains(
+            "- https://example.com/a (long redirect chain): https://example.com/a -> \
+             https://example.com/b -> https://example.com/c -> https://example.com/d"
+        ));
+    }
+
+    fn duplicate_results() -> CrawlResults {
+        let mut results = create_test_results();
+        results.stats.duplicates = 1;
+        results.results.push(PageResult {
+            url: "https://example.com/page1?print=1".to_string(),
+            title: "Page 1 (print)".to_string(),
+            status_code: 200,
+            depth: 2,
+            duplicate_of: Some("https://example.com/page1".to_string()),
+            duplicate_kind: Some(crate::crawler::dedup::DuplicateKind::Exact),
+            ..Default::default()
+        });
+        results
+    }
+
+    #[test]
+    fn test_duplicates_listed_by_default() {
+        let service = DefaultOutputFormatterService::new();
+        let results = duplicate_results();
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        assert!(markdown.content.contains("Page 1 (print)"));
+        assert!(markdown.content.contains("**Duplicate of**: https://example.com/page1"));
+
+        let json = service.format_single(&results, OutputFormat::Json).unwrap();
+        assert!(json.content.contains("\"duplicateOf\""));
+    }
+
+    #[test]
+    fn test_collapse_duplicates() {
+        let service = DefaultOutputFormatterService::with_config(OutputFormatterConfig {
+            collapse_duplicates: true,
+            ..Default::default()
+        });
+        let results = duplicate_results();
+
+        let markdown = service.format_single(&results, OutputFormat::Markdown).unwrap();
+        assert!(!markdown.content.contains("Page 1 (print)"));
+        assert!(markdown
+            .content
+            .contains("**Duplicates** (1): https://example.com/page1?print=1"));
+
+        let json = service.format_single(&results, OutputFormat::Json).unwrap();
+        let parsed: CrawlResults = serde_json::from_str(&json.content).unwrap();
+        assert_eq!(parsed.results.len(), 2);
+        assert_eq!(parsed.results[1].duplicates, vec!["https://example.com/page1?print=1".to_string()]);
+    }
+
     #[test]
     fn test_output_format_parsing() {
         assert_eq!(OutputFormat::from_str("json").unwrap(), OutputFormat::Json);
@@ -234,6 +234,6 @@ mod tests {
         let service = DefaultStealthService::with_config(config);
         let delay = service.get_random_delay();
 
-        assert!(delay >= 100 && delay < 500);
+        assert!((100..500).contains(&delay));
     }
 }
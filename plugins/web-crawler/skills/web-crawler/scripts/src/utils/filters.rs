@@ -28,10 +28,10 @@ impl UrlFilter {
 	/// Checks if a URL should be crawled based on patterns
 	pub fn should_crawl(&self, url: &str) -> bool {
 		// If include patterns exist, URL must match at least one
-		if !self.include_patterns.is_empty() {
-			if !self.include_patterns.iter().any(|re| re.is_match(url)) {
-				return false;
-			}
+		if !self.include_patterns.is_empty()
+			&& !self.include_patterns.iter().any(|re| re.is_match(url))
+		{
+			return false;
 		}
 
 		// If URL matches any exclude pattern, reject it
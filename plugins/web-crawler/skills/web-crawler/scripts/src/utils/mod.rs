@@ -1,3 +1,4 @@
 //! Utils module
 pub mod filters;
 pub mod logger;
+pub mod url;
@@ -0,0 +1,309 @@
+//! URL canonicalization
+//!
+//! Normalizes URLs before they are deduplicated, so `/page`, `/page/`,
+//! `/page#top` and `/page?utm_source=x` are recognized as one page.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use url::Url;
+
+/// Query parameters stripped by default (`*` matches any suffix)
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+	"utm_*", "gclid", "gclsrc", "dclid", "fbclid", "msclkid", "yclid", "twclid", "igshid",
+	"mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+/// File names treated as the directory they sit in
+const INDEX_FILES: &[&str] = &["index.html", "index.htm", "index.php", "default.htm", "default.aspx"];
+
+/// What to do with a trailing slash on non-root paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+	/// `/docs/` becomes `/docs`
+	#[default]
+	Strip,
+	/// `/docs` becomes `/docs/` (paths whose last segment has an extension are left alone)
+	Add,
+	/// Paths are left as they are
+	Keep,
+}
+
+impl FromStr for TrailingSlash {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"strip" => Ok(Self::Strip),
+			"add" => Ok(Self::Add),
+			"keep" => Ok(Self::Keep),
+			_ => Err(format!("Unknown trailing slash mode: {}", s)),
+		}
+	}
+}
+
+/// Configuration for URL normalization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UrlNormalizerConfig {
+	/// Remove `#fragment`
+	pub strip_fragment: bool,
+
+	/// Trailing slash handling
+	pub trailing_slash: TrailingSlash,
+
+	/// Treat `/dir/index.html` as `/dir/`
+	pub strip_index: bool,
+
+	/// Sort query parameters
+	pub sort_query: bool,
+
+	/// Query parameters to remove (`*` suffix for prefixes, e.g. `utm_*`)
+	pub tracking_params: Vec<String>,
+
+	/// Decode percent-encoded unreserved characters and uppercase the rest
+	pub decode_percent: bool,
+}
+
+impl Default for UrlNormalizerConfig {
+	fn default() -> Self {
+		Self {
+			strip_fragment: true,
+			trailing_slash: TrailingSlash::Strip,
+			strip_index: true,
+			sort_query: true,
+			tracking_params: DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect(),
+			decode_percent: true,
+		}
+	}
+}
+
+/// Canonicalizes URLs for the visited set, filters and outputs
+///
+/// Scheme and host are lowercased and default ports dropped by `Url` itself.
+#[derive(Debug, Clone, Default)]
+pub struct UrlNormalizer {
+	config: UrlNormalizerConfig,
+}
+
+impl UrlNormalizer {
+	pub fn new(config: UrlNormalizerConfig) -> Self {
+		Self { config }
+	}
+
+	/// Canonical form of `url`, or `url` unchanged if it cannot be parsed
+	pub fn normalize(&self, url: &str) -> String {
+		match Url::parse(url.trim()) {
+			Ok(parsed) => self.normalize_url(parsed).to_string(),
+			Err(_) => url.to_string(),
+		}
+	}
+
+	/// Canonical form of a parsed URL
+	pub fn normalize_url(&self, mut url: Url) -> Url {
+		if self.config.strip_fragment {
+			url.set_fragment(None);
+		}
+
+		// Opaque URLs (mailto:, javascript:, data:) have no path or query to normalize
+		if url.cannot_be_a_base() {
+			return url;
+		}
+
+		let path = self.normalize_path(url.path());
+		url.set_path(&path);
+
+		let query = url.query().map(|query| self.normalize_query(query));
+		url.set_query(query.as_deref().filter(|q| !q.is_empty()));
+
+		url
+	}
+
+	fn normalize_path(&self, path: &str) -> String {
+		let mut path = if self.config.decode_percent {
+			normalize_percent(path)
+		} else {
+			path.to_string()
+		};
+
+		if self.config.strip_index {
+			if let Some((dir, file)) = path.rsplit_once('/') {
+				if INDEX_FILES.iter().any(|index| file.eq_ignore_ascii_case(index)) {
+					path = format!("{}/", dir);
+				}
+			}
+		}
+
+		match self.config.trailing_slash {
+			TrailingSlash::Strip => {
+				let trimmed = path.trim_end_matches('/');
+				if trimmed.is_empty() {
+					"/".to_string()
+				} else {
+					trimmed.to_string()
+				}
+			}
+			TrailingSlash::Add => {
+				let last = path.rsplit('/').next().unwrap_or("");
+				if path.ends_with('/') || last.contains('.') {
+					path
+				} else {
+					format!("{}/", path)
+				}
+			}
+			TrailingSlash::Keep => path,
+		}
+	}
+
+	fn normalize_query(&self, query: &str) -> String {
+		let mut params: Vec<String> = query
+			.split('&')
+			.filter(|param| !param.is_empty())
+			.filter(|param| {
+				let key = param.split('=').next().unwrap_or("");
+				!self.is_tracking_param(&normalize_percent(key))
+			})
+			.map(|param| {
+				if self.config.decode_percent {
+					normalize_percent(param)
+				} else {
+					param.to_string()
+				}
+			})
+			.collect();
+
+		if self.config.sort_query {
+			// Stable sort on the key keeps repeated keys (a=2&a=1) in their original order
+			params.sort_by(|a, b| {
+				let key_a = a.split('=').next().unwrap_or("");
+				let key_b = b.split('=').next().unwrap_or("");
+				key_a.cmp(key_b)
+			});
+		}
+
+		params.join("&")
+	}
+
+	fn is_tracking_param(&self, key: &str) -> bool {
+		self.config.tracking_params.iter().any(|param| match param.strip_suffix('*') {
+			Some(prefix) => key.len() >= prefix.len()
+				&& key[..prefix.len()].eq_ignore_ascii_case(prefix),
+			None => key.eq_ignore_ascii_case(param),
+		})
+	}
+}
+
+/// Decodes percent-encoded unreserved characters (`A-Z a-z 0-9 - . _ ~`)
+/// and uppercases the hex digits of the escapes that must stay encoded
+fn normalize_percent(input: &str) -> String {
+	let mut output = String::with_capacity(input.len());
+	let mut rest = input;
+
+	while let Some(pos) = rest.find('%') {
+		output.push_str(&rest[..pos]);
+		rest = &rest[pos..];
+
+		let byte = rest
+			.get(1..3)
+			.filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+		match byte {
+			Some(byte) if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') => {
+				output.push(byte as char);
+				rest = &rest[3..];
+			}
+			Some(_) => {
+				output.push('%');
+				output.push_str(&rest[1..3].to_ascii_uppercase());
+				rest = &rest[3..];
+			}
+			None => {
+				// Stray '%' that is not an escape
+				output.push('%');
+				rest = &rest[1..];
+			}
+		}
+	}
+
+	output.push_str(rest);
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn normalize(url: &str) -> String {
+		UrlNormalizer::default().normalize(url)
+	}
+
+	#[test]
+	fn test_same_page_variants() {
+		let canonical = "https://example.com/page";
+
+		assert_eq!(normalize("https://example.com/page"), canonical);
+		assert_eq!(normalize("https://example.com/page/"), canonical);
+		assert_eq!(normalize("https://example.com/page#top"), canonical);
+		assert_eq!(normalize("https://example.com/page?utm_source=x"), canonical);
+		assert_eq!(normalize("HTTPS://Example.COM:443/page"), canonical);
+		assert_eq!(normalize("https://example.com/%70age"), canonical);
+	}
+
+	#[test]
+	fn test_root_and_index_files() {
+		assert_eq!(normalize("https://example.com"), "https://example.com/");
+		assert_eq!(normalize("https://example.com/index.html"), "https://example.com/");
+		assert_eq!(normalize("https://example.com/docs/Index.HTML"), "https://example.com/docs");
+		assert_eq!(normalize("http://example.com:80/a/index.php?x=1"), "http://example.com/a?x=1");
+	}
+
+	#[test]
+	fn test_query_sorting_and_tracking() {
+		assert_eq!(
+			normalize("https://example.com/s?b=2&a=1&utm_medium=mail&fbclid=abc&a=0"),
+			"https://example.com/s?a=1&a=0&b=2"
+		);
+		assert_eq!(normalize("https://example.com/s?gclid=1&"), "https://example.com/s");
+	}
+
+	#[test]
+	fn test_percent_encoding() {
+		// Reserved and non-ASCII bytes stay encoded, with uppercase hex
+		assert_eq!(
+			normalize("https://example.com/a%2fb/caf%c3%a9?q=%7euser%20name"),
+			"https://example.com/a%2Fb/caf%C3%A9?q=~user%20name"
+		);
+	}
+
+	#[test]
+	fn test_configurable_options() {
+		let normalizer = UrlNormalizer::new(UrlNormalizerConfig {
+			strip_fragment: false,
+			trailing_slash: TrailingSlash::Add,
+			strip_index: false,
+			sort_query: false,
+			tracking_params: vec!["ref".to_string()],
+			decode_percent: false,
+		});
+
+		assert_eq!(
+			normalizer.normalize("https://example.com/docs?b=1&ref=x&a=2#intro"),
+			"https://example.com/docs/?b=1&a=2#intro"
+		);
+		assert_eq!(
+			normalizer.normalize("https://example.com/file.pdf?utm_source=x"),
+			"https://example.com/file.pdf?utm_source=x"
+		);
+		assert_eq!(
+			normalizer.normalize("https://example.com/index.html"),
+			"https://example.com/index.html"
+		);
+	}
+
+	#[test]
+	fn test_unparseable_and_opaque_urls() {
+		assert_eq!(normalize("not a url"), "not a url");
+		assert_eq!(normalize("mailto:someone@example.com"), "mailto:someone@example.com");
+	}
+}